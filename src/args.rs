@@ -0,0 +1,221 @@
+use crate::dsp::{self, EffectSpec, FilterKind};
+use crate::state::Command;
+use std::str::FromStr;
+
+// Argument parsing for the CLI. Every numeric argument goes through these
+// helpers so a typo gets the same kind of message from every command
+// ("expected a number for <ms>, got `abc`") instead of a silent no-op.
+// Errors are plain strings, run_cli prints them.
+
+pub type ArgResult<T> = Result<T, String>;
+
+// required argument `i`, named `name` in the message
+pub fn num<T: FromStr>(parts: &[&str], i: usize, name: &str) -> ArgResult<T> {
+    opt_num(parts, i, name)?.ok_or_else(|| format!("missing {}", name))
+}
+
+// optional argument: Ok(None) when it isn't there, an error when it's there but isn't a number
+pub fn opt_num<T: FromStr>(parts: &[&str], i: usize, name: &str) -> ArgResult<Option<T>> {
+    match parts.get(i) {
+        None => Ok(None),
+        Some(s) => s.parse().map(Some).map_err(|_| format!("expected a number for {}, got `{}`", name, s)),
+    }
+}
+
+// a length in milliseconds, negative means 0. `inf` is refused, it would
+// turn into a sample count nothing can hold
+pub fn ms(parts: &[&str], i: usize, name: &str) -> ArgResult<f32> {
+    opt_ms(parts, i, name)?.ok_or_else(|| format!("missing {}", name))
+}
+
+pub fn opt_ms(parts: &[&str], i: usize, name: &str) -> ArgResult<Option<f32>> {
+    match opt_num::<f32>(parts, i, name)? {
+        Some(v) if !v.is_finite() => Err(format!("{} must be a finite number, got `{}`", name, parts[i])),
+        v => Ok(v.map(|v| v.max(0.0))),
+    }
+}
+
+// 1-based segment number as typed -> 0-based index
+pub fn segment(parts: &[&str], i: usize) -> ArgResult<usize> {
+    opt_segment(parts, i)?.ok_or_else(|| "missing segment number".to_string())
+}
+
+pub fn opt_segment(parts: &[&str], i: usize) -> ArgResult<Option<usize>> {
+    match opt_num::<usize>(parts, i, "segment number")? {
+        Some(0) => Err("segment numbers start at 1".into()),
+        n => Ok(n.map(|n| n - 1)),
+    }
+}
+
+// commands that turn straight into a Command from their arguments alone.
+// None = not one of these, run_cli handles it
+pub fn parse_command(parts: &[&str]) -> Option<ArgResult<Command>> {
+    let cmd = match *parts.first()? {
+        "retry"    => segment(parts, 1).map(Command::RetrySegment),
+        "insert"   => segment(parts, 1).map(Command::InsertAfter),
+        // run_cli asks before a plain `delete`, `delete!` / `delete <n> --force` don't
+        "delete" | "delete!" => segment(parts, 1).map(Command::DeleteSegment),
+        "dup"      => segment(parts, 1).map(Command::DuplicateSegment),
+        "rev"      => segment(parts, 1).map(Command::ReverseSegment),
+        // move <from> <to>, 1-based, <to> = count + 1 also means "to the end"
+        "move"     => (|| Ok(Command::MoveSegment(segment(parts, 1)?, segment(parts, 2)?)))(),
+        // split <n> <seconds>, seconds from the start of segment n
+        "split"    => (|| Ok(Command::SplitSegment(segment(parts, 1)?, num(parts, 2, "seconds")?)))(),
+        // paste <n> <seconds>, what `copy` last took
+        "paste"    => (|| Ok(Command::Paste(segment(parts, 1)?, num(parts, 2, "seconds")?)))(),
+        // merge <start> <end>, inclusive
+        "merge"    => (|| Ok(Command::MergeSegments(segment(parts, 1)?, segment(parts, 2)?)))(),
+        // name <n> <text...>, no text clears the name
+        "name"     => segment(parts, 1)
+            .map(|i| Command::NameSegment(i, (parts.len() > 2).then(|| parts[2..].join(" ")))),
+        // gain <n> <multiplier>, 1 = as recorded
+        "gain" => (|| {
+            let (n, gain) = (segment(parts, 1)?, num::<f32>(parts, 2, "gain")?);
+            if gain >= 0.0 { Ok(Command::SetGain(n, gain)) } else { Err("gain can't be negative".into()) }
+        })(),
+        // pan <n> <-1..1>, -1 left, 1 right
+        "pan" => (|| {
+            let (n, pan) = (segment(parts, 1)?, num::<f32>(parts, 2, "pan")?);
+            if (-1.0..=1.0).contains(&pan) { Ok(Command::SetPan(n, pan)) } else { Err("pan must be between -1 and 1".into()) }
+        })(),
+        // norm <n> | norm all
+        "norm" => match parts.get(1).copied() {
+            Some("all") => Ok(Command::Normalize(None)),
+            _ => segment(parts, 1).map(|i| Command::Normalize(Some(i))),
+        },
+        // sil <n> <seconds>, silence after segment n
+        "sil" => (|| {
+            let (n, secs) = (segment(parts, 1)?, num::<f32>(parts, 2, "seconds")?);
+            if secs > 0.0 { Ok(Command::InsertSilence(n, secs)) } else { Err("seconds must be above 0".into()) }
+        })(),
+        // normproj [peak], 0.99 when left out
+        "normproj" => opt_num::<f32>(parts, 1, "peak").and_then(|t| match t.unwrap_or(crate::state::NORMALIZE_PEAK) {
+            t if t > 0.0 && t <= 1.0 => Ok(Command::NormalizeProject(t)),
+            _ => Err("peak must be above 0 and at most 1".into()),
+        }),
+        // dc <n> | dc all
+        "dc" => match parts.get(1).copied() {
+            Some("all") => Ok(Command::RemoveDc(None)),
+            _ => segment(parts, 1).map(|i| Command::RemoveDc(Some(i))),
+        },
+        "insertat" => num::<f32>(parts, 1, "seconds")
+            .and_then(|s| if s >= 0.0 { Ok(Command::InsertAt(s)) } else { Err("seconds can't be negative".into()) }),
+        "matchvol" => num::<f32>(parts, 1, "target dBFS")
+            .and_then(|db| if db <= 0.0 { Ok(Command::MatchLoudness(db)) } else { Err("target dBFS must be 0 or below, e.g. -20".into()) }),
+        // eq <n> lp|hp|notch <hz> [q]   or   eq <n> peak <hz> <db> [q]
+        "eq" => (|| {
+            let n = segment(parts, 1)?;
+            let hz = num::<f32>(parts, 3, "frequency (Hz)");
+            let (kind, q) = match parts.get(2).copied() {
+                Some("lp")    => (FilterKind::LowPass, opt_num(parts, 4, "q")?.unwrap_or(0.707)),
+                Some("hp")    => (FilterKind::HighPass, opt_num(parts, 4, "q")?.unwrap_or(0.707)),
+                Some("notch") => (FilterKind::Notch, opt_num(parts, 4, "q")?.unwrap_or(10.0)),
+                Some("peak")  => (FilterKind::Peaking(num(parts, 4, "gain (dB)")?), opt_num(parts, 5, "q")?.unwrap_or(1.0)),
+                _ => return Err("filter type must be lp, hp, notch or peak".into()),
+            };
+            Ok(Command::Filter(n, kind, hz?, q))
+        })(),
+        // stretch <n> <factor>, 2 = twice as long
+        "stretch" => (|| {
+            let (n, factor) = (segment(parts, 1)?, num::<f32>(parts, 2, "factor")?);
+            if crate::state::STRETCH_RANGE.contains(&factor) { Ok(Command::TimeStretch(n, factor)) }
+            else { Err(format!("factor must be between {} and {}", crate::state::STRETCH_RANGE.start(), crate::state::STRETCH_RANGE.end())) }
+        })(),
+        // pitch <n> <semitones>, negative lowers it
+        "pitch" => (|| {
+            let (n, st) = (segment(parts, 1)?, num::<f32>(parts, 2, "semitones")?);
+            if crate::state::PITCH_RANGE.contains(&st) { Ok(Command::PitchShift(n, st)) }
+            else { Err(format!("semitones must be between {} and {}", crate::state::PITCH_RANGE.start(), crate::state::PITCH_RANGE.end())) }
+        })(),
+        // hpf <n> <hz>, shorthand for `eq <n> hp <hz>`
+        "hpf" => (|| {
+            let (n, hz) = (segment(parts, 1)?, num::<f32>(parts, 2, "cutoff (Hz)")?);
+            if hz > 0.0 { Ok(Command::HighPass(n, hz)) } else { Err("cutoff must be above 0 Hz".into()) }
+        })(),
+        "reverb" => (|| Ok(Command::Reverb(segment(parts, 1)?, num(parts, 2, "room size")?, num(parts, 3, "wet")?)))(),
+        "delay" => (|| Ok(Command::Delay(
+            segment(parts, 1)?, delay_ms(parts, 2)?, num(parts, 3, "feedback")?, num(parts, 4, "wet")?,
+        )))(),
+        // render <n> <after> <effect...>
+        "render" => (|| {
+            let (n, after) = (segment(parts, 1)?, segment(parts, 2)?);
+            Ok(Command::RenderEffect(n, parse_effect(&parts[3.min(parts.len())..])?, after))
+        })(),
+        _ => return None,
+    };
+    Some(cmd)
+}
+
+// effect name + parameters as typed on the command line
+fn delay_ms(parts: &[&str], i: usize) -> ArgResult<f32> {
+    let time = num::<f32>(parts, i, "delay (ms)")?;
+    if dsp::delay_time_ok(time) { Ok(time) } else { Err(format!("delay must be above 0 and at most {} ms", dsp::MAX_DELAY_MS)) }
+}
+
+pub fn parse_effect(args: &[&str]) -> ArgResult<EffectSpec> {
+    match args.first().copied() {
+        Some("gain")   => Ok(EffectSpec::Gain(num(args, 1, "gain")?)),
+        Some("reverb") => Ok(EffectSpec::Reverb(num(args, 1, "room size")?, num(args, 2, "wet")?)),
+        Some("delay")  => Ok(EffectSpec::Delay(delay_ms(args, 1)?, num(args, 2, "feedback")?, num(args, 3, "wet")?)),
+        Some("hp")     => Ok(EffectSpec::Filter(FilterKind::HighPass, num(args, 1, "frequency (Hz)")?, 0.707)),
+        Some("lp")     => Ok(EffectSpec::Filter(FilterKind::LowPass, num(args, 1, "frequency (Hz)")?, 0.707)),
+        Some(other)    => Err(format!("unknown effect `{}`, use gain, reverb, delay, hp or lp", other)),
+        None           => Err("missing effect".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn err(line: &str) -> String {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parse_command(&parts) {
+            Some(Err(e)) => e,
+            Some(Ok(_)) => panic!("`{}` parsed", line),
+            None => panic!("`{}` not handled", line),
+        }
+    }
+
+    #[test]
+    fn test_malformed_arguments_are_reported() {
+        assert_eq!(err("retry abc"), "expected a number for segment number, got `abc`");
+        assert_eq!(err("delete"), "missing segment number");
+        assert_eq!(err("move 1"), "missing segment number");
+        assert_eq!(err("insert 0"), "segment numbers start at 1");
+        assert_eq!(err("insertat soon"), "expected a number for seconds, got `soon`");
+        assert_eq!(err("split 2"), "missing seconds");
+        assert_eq!(err("reverb 2 0.5"), "missing wet");
+        assert_eq!(err("delay 1 250 lots 0.3"), "expected a number for feedback, got `lots`");
+        assert_eq!(err("eq 1 bp 300"), "filter type must be lp, hp, notch or peak");
+        assert_eq!(err("eq 1 peak 300"), "missing gain (dB)");
+        assert_eq!(err("render 1 2 fuzz"), "unknown effect `fuzz`, use gain, reverb, delay, hp or lp");
+        assert_eq!(err("gain 2 -1"), "gain can't be negative");
+        assert_eq!(err("matchvol 3"), "target dBFS must be 0 or below, e.g. -20");
+        assert_eq!(err("delay 1 inf 0.5 0.5"), "delay must be above 0 and at most 2000 ms");
+        assert_eq!(err("render 1 2 delay 1e30 0.5 0.5"), "delay must be above 0 and at most 2000 ms");
+    }
+
+    #[test]
+    fn test_well_formed_arguments_parse() {
+        let parse = |line: &str| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            parse_command(&parts).unwrap().unwrap()
+        };
+        assert!(matches!(parse("retry 3"), Command::RetrySegment(2)));
+        assert!(matches!(parse("name 2 chapter one"), Command::NameSegment(1, Some(n)) if n == "chapter one"));
+        assert!(matches!(parse("eq 2 hp 80"), Command::Filter(1, FilterKind::HighPass, 80.0, _)));
+        assert!(matches!(parse("hpf 1 80"), Command::HighPass(0, 80.0)));
+        assert!(matches!(parse("render 1 4 gain 0.5"), Command::RenderEffect(0, EffectSpec::Gain(_), 3)));
+        assert!(matches!(parse("norm all"), Command::Normalize(None)));
+        assert!(matches!(parse("norm 2"), Command::Normalize(Some(1))));
+        assert!(matches!(parse("delete! 4"), Command::DeleteSegment(3)));
+        assert!(parse_command(&["q"]).is_none());
+
+        // optional arguments: absent is fine, garbage isn't
+        assert_eq!(opt_num::<f32>(&["trim", "2"], 2, "threshold"), Ok(None));
+        assert!(opt_num::<f32>(&["trim", "2", "x"], 2, "threshold").is_err());
+        assert_eq!(ms(&["trimpad", "-5"], 1, "ms"), Ok(0.0));
+        assert_eq!(ms(&["trimpad", "inf"], 1, "ms"), Err("ms must be a finite number, got `inf`".into()));
+    }
+}
@@ -1,36 +1,89 @@
-use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::state::{AppState, RecorderState};
+use crate::dsp;
+use crate::priority::{self, PriorityOutcome};
+use crate::state::{AppState, AutoStop, ChannelPlan, RecorderState};
 
-// start_input_stream is a background thread
+// start_input_stream_with_device is a background thread
 // that is always listening to the mic
-// but only saves audio when AppState::Recording.
+// but only saves audio when AppState::Recording (and not paused).
 
-pub fn start_input_stream(
+// device_name picks the input device (see find_input_device), None = the
+// system default. a name that matches nothing is an error listing the
+// devices there are, not a silent switch to the default
+pub fn start_input_stream_with_device(
     recorder: Arc<Mutex<RecorderState>>,
+    device_name: Option<&str>,
     on_new_data: impl Fn() + Send + 'static, // callback function to ctx.request_repaint
-) -> cpal::Stream {
+) -> Result<cpal::Stream, String> {
     let host = cpal::default_host();
-    let device = host.default_input_device().expect("No input device");
+    let device = match device_name {
+        Some(name) => find_input_device(&host, name)?,
+        None => host.default_input_device().ok_or("No input device")?,
+    };
     
     // get hardware config
-    let config = device.default_input_config().expect("Failed to get default input config");
+    let config = device.default_input_config().map_err(|e| format!("Failed to get default input config: {}", e))?;
     let hardware_sample_rate = config.sample_rate(); // cpal::SampleRate
     let hardware_channels = config.channels(); // u16
 
     // sync RecorderState to hardware settings to avoid mismatch
     // e.g. mic set to 48000Hz in OS settings, but RecorderState 44100
-    {
+    let (selection, meter, mut boost_priority) = {
         let mut rec = recorder.lock().unwrap();
-        rec.project.sample_rate = hardware_sample_rate;
-        rec.project.channels = hardware_channels;
+        rec.device_sample_rate = Some(hardware_sample_rate);
+        // with a target rate the input is resampled to it in the callback,
+        // otherwise the project follows the device. a loaded project keeps
+        // its audio intact, resampled to whichever rate that is
+        let rate = rec.recording_rate().unwrap_or(hardware_sample_rate);
+        let converted = rec.convert_sample_rate(rate);
+        if converted > 0 {
+            println!("Resampled {} segment(s) to {}Hz", converted, rate);
+        }
+        if rate != hardware_sample_rate {
+            println!("Recording at {}Hz, resampled from the device's {}Hz", rate, hardware_sample_rate);
+        }
         println!("Hardware: {}Hz, {} channel(s)", hardware_sample_rate, hardware_channels);
-    }
+        let selection = resolve_channel_selection(rec.settings.input_channels.as_deref(), hardware_channels);
+        // the callback down-mixes to mono unless stereo was asked for and
+        // there are two channels to keep, that's the layout segments are
+        // stored in, not the hardware's channel count
+        if rec.settings.stereo && selection.len() < 2 {
+            eprintln!("Stereo needs two input channels, recording mono");
+        }
+        rec.capture_channels = if rec.settings.stereo && selection.len() >= 2 { 2 } else { 1 };
+        if rec.project.segments.is_empty() {
+            rec.project.channels = rec.capture_channels;
+        }
+        rec.meter.set_channels(selection.len());
+        (selection, rec.meter.clone(), rec.settings.realtime_priority)
+    };
+    // last input gain seen under the lock, so the meter (which runs even
+    // when try_lock fails) shows the level that's actually recorded
+    let mut input_gain = recorder.lock().unwrap().settings.input_gain;
+    let stereo = selection.len() >= 2 && recorder.lock().unwrap().capture_channels == 2;
+    // lives with the stream so its envelope carries across buffers,
+    // rebuilt whenever the threshold is changed
+    let mut gate: Option<dsp::NoiseGate> = None;
+    // device rate -> project rate, fresh for every take (see StreamResampler)
+    let mut resampler: Option<dsp::StreamResampler> = None;
 
-    let stream = device.build_input_stream(
+    device.build_input_stream(
         &config.into(),
         move |data: &[f32], _| {
+            // the callback thread is created by the backend, so the first
+            // callback is the earliest point we can ask for it to be boosted
+            if boost_priority {
+                boost_priority = false;
+                match priority::raise_current_thread() {
+                    PriorityOutcome::Realtime => println!("Input thread running with real-time priority"),
+                    PriorityOutcome::Elevated => println!("Input thread priority raised (real-time not granted)"),
+                    PriorityOutcome::Unavailable(why) =>
+                        eprintln!("Couldn't raise input thread priority, recording at normal priority ({})", why),
+                }
+            }
             // Determine whether new samples were written with the mutex held,
             // then call on_new_data() AFTER releasing it to fix deadlock
             //
@@ -40,21 +93,54 @@ pub fn start_input_stream(
             // mutex. If calling on_new_data() while still holding the recorder
             // mutex we get a lock-order inversion and the app freezes
             // releasing the mutex first breaks the cycle.
+            let peaks = channel_peaks(data, hardware_channels as usize, &selection);
+            meter.store(&peaks.map(|p| (p * input_gain).min(1.0)));
+
             let should_repaint = if let Ok(mut rec) = recorder.try_lock() {
-                if let AppState::Recording = rec.state {
-                    if let Some(seg) = rec.current.as_mut() {
-                        if hardware_channels == 1 { // mono, just copy
-                            seg.samples.extend_from_slice(data);
-                        } else {
-                            // stereo (or more), down-mix to Mono
-                            // .chunks_exact(2) gives [[L, R], [L, R], ...] so L + R / 2
-                            let mono_data = data //.into() converts u16 into usize
-                                .chunks_exact(hardware_channels as usize)
-                                .map(|frame| frame.iter().sum::<f32>() / hardware_channels as f32);
-                            seg.samples.extend(mono_data);
+                // the project may have been loaded with a different layout since startup
+                let plan = rec.capture_plan();
+                if let (AppState::Recording, Ok(plan)) = (&rec.state, plan) && rec.current.is_some() && !rec.paused {
+                    // stereo keeps the first two selected channels, otherwise
+                    // multi-channel hardware is down-mixed from the selection
+                    let mut captured: Vec<f32> = if stereo { pick_pair(data, hardware_channels as usize, &selection).collect() }
+                        else if hardware_channels == 1 { data.to_vec() }
+                        else { mix_selected(data, hardware_channels as usize, &selection).collect() };
+                    let rate = rec.project.sample_rate;
+                    if rate == hardware_sample_rate {
+                        resampler = None;
+                    } else {
+                        if rec.current.as_ref().is_some_and(|c| c.samples.is_empty()) || resampler.is_none() {
+                            resampler = Some(dsp::StreamResampler::new(hardware_sample_rate, rate, if stereo { 2 } else { 1 }));
                         }
-                        true // samples written, request repaint
-                    } else { false }
+                        captured = resampler.as_mut().unwrap().process(&captured);
+                    }
+                    input_gain = rec.settings.input_gain;
+                    apply_input_gain(&mut captured, input_gain);
+                    let threshold = rec.settings.noise_gate;
+                    if threshold <= 0.0 {
+                        gate = None;
+                    } else if gate.as_ref().is_none_or(|g| g.threshold() != threshold) {
+                        gate = Some(dsp::NoiseGate::new(threshold, rec.project.sample_rate));
+                    }
+                    if let Some(gate) = gate.as_mut() {
+                        gate.process_interleaved(&mut captured, if stereo { 2 } else { 1 });
+                    }
+                    let silent_too_long = rec.track_silence(&captured); // before appending, see track_silence
+                    let seg = rec.current.as_mut().unwrap();
+                    match plan {
+                        ChannelPlan::Passthrough => seg.samples.extend(captured),
+                        ChannelPlan::Duplicate(n) => seg.samples.extend(
+                            captured.iter().flat_map(|&s| std::iter::repeat_n(s, n as usize))),
+                    }
+                    // same as `s`, the app picks up auto_stopped to play it back
+                    let stop = if rec.reached_max_length() { Some(AutoStop::MaxLength) }
+                        else if silent_too_long { Some(AutoStop::Silence) }
+                        else { None };
+                    if stop.is_some() {
+                        rec.stop_recording();
+                        rec.auto_stopped = stop;
+                    }
+                    true // samples written, request repaint
                 } else { false }
             } else { false }; // try_lock failed, skip this callback
 
@@ -64,7 +150,217 @@ pub fn start_input_stream(
         },
         |err| eprintln!("input error: {:?}", err),
         None,
-    ).unwrap();
+    ).map_err(|e| format!("Failed to open input stream: {}", e))
+}
+
+// names of the input devices the host offers, what --device accepts
+pub fn input_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    host.input_devices()
+        .map(|devices| devices.filter_map(|d| device_name(&d)).collect())
+        .unwrap_or_default()
+}
+
+// the full name of the input device `wanted` refers to, for checking a
+// --device before the stream is opened
+pub fn resolve_input_device_name(wanted: &str) -> Result<String, String> {
+    let host = cpal::default_host();
+    find_input_device(&host, wanted).map(|d| device_name(&d).unwrap_or_else(|| wanted.to_string()))
+}
+
+pub fn device_name(device: &cpal::Device) -> Option<String> {
+    device.description().ok().map(|d| d.name().to_string())
+}
+
+fn find_input_device(host: &cpal::Host, name: &str) -> Result<cpal::Device, String> {
+    let devices: Vec<cpal::Device> = host.input_devices().map_err(|e| e.to_string())?.collect();
+    let names: Vec<String> = devices.iter().map(|d| device_name(d).unwrap_or_default()).collect();
+    match match_device_name(&names, name) {
+        Some(i) => Ok(devices.into_iter().nth(i).unwrap()),
+        None => Err(format!("No input device called `{}`, available: {}", name, names.join(", "))),
+    }
+}
 
-    stream
+// index of the device `wanted` refers to: an exact name, otherwise a
+// case-insensitive part of exactly one name ("usb" for "USB Audio CODEC")
+pub fn match_device_name(names: &[String], wanted: &str) -> Option<usize> {
+    if let Some(i) = names.iter().position(|n| n == wanted) { return Some(i); }
+    let wanted = wanted.to_lowercase();
+    let mut hits = names.iter().enumerate().filter(|(_, n)| n.to_lowercase().contains(&wanted));
+    match (hits.next(), hits.next()) {
+        (Some((i, _)), None) => Some(i),
+        _ => None, // nothing, or ambiguous
+    }
+}
+
+// turn the user's requested input channels (0-based) into the list the
+// callback mixes. out-of-range picks fall back to every channel with a warning
+// rather than silently recording nothing
+pub fn resolve_channel_selection(requested: Option<&[usize]>, hardware_channels: u16) -> Vec<usize> {
+    let all: Vec<usize> = (0..hardware_channels as usize).collect();
+    match requested {
+        None => all,
+        Some(sel) if !sel.is_empty() && sel.iter().all(|&c| c < hardware_channels as usize) => sel.to_vec(),
+        Some(sel) => {
+            let shown: Vec<String> = sel.iter().map(|c| (c + 1).to_string()).collect();
+            eprintln!("Input channel(s) {} not available on a {}-channel device, using all channels",
+                shown.join(","), hardware_channels);
+            all
+        }
+    }
+}
+
+// average the selected channels of every interleaved frame into one mono sample
+// .chunks_exact(n) gives [[ch0, ch1, ...], ...] so we pick out `selection` from each
+pub fn mix_selected<'a>(
+    data: &'a [f32],
+    channels: usize,
+    selection: &'a [usize],
+) -> impl Iterator<Item = f32> + 'a {
+    data.chunks_exact(channels)
+        .map(move |frame| selection.iter().map(|&c| frame[c]).sum::<f32>() / selection.len() as f32)
+}
+
+// boost (or cut) the captured samples. anything pushed past full scale is
+// held at ±1.0 so the take never holds overs the export would have to
+// flatten anyway; approve reports those samples as clipped
+pub fn apply_input_gain(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 { return; }
+    for s in samples {
+        *s = (*s * gain).clamp(-1.0, 1.0);
+    }
+}
+
+// the first two selected channels of every frame, interleaved L R L R
+pub fn pick_pair<'a>(data: &'a [f32], channels: usize, selection: &'a [usize]) -> impl Iterator<Item = f32> + 'a {
+    data.chunks_exact(channels).flat_map(move |frame| [frame[selection[0]], frame[selection[1]]])
+}
+
+// peak of each of the first two selected channels in an interleaved buffer,
+// index 0 = left (or mono), 1 = right
+pub fn channel_peaks(data: &[f32], channels: usize, selection: &[usize]) -> [f32; 2] {
+    let mut peaks = [0.0_f32; 2];
+    for frame in data.chunks_exact(channels) {
+        for (peak, &c) in peaks.iter_mut().zip(selection) {
+            *peak = peak.max(frame[c].abs());
+        }
+    }
+    peaks
+}
+
+// ===== Shutdown =====
+
+// how long a callback that was already running when the stream was paused
+// gets to finish (it may be holding the recorder lock) before the drop
+pub const SHUTDOWN_SETTLE: Duration = Duration::from_millis(50);
+
+// the one thing stop_input_stream needs from a stream, so the ordering
+// can be tested without an audio device
+pub trait InputStream {
+    fn pause(&self) -> Result<(), String>;
+}
+
+impl InputStream for cpal::Stream {
+    fn pause(&self) -> Result<(), String> {
+        StreamTrait::pause(self).map_err(|e| e.to_string())
+    }
+}
+
+// stop the mic explicitly instead of leaving it to an implicit drop at the
+// end of main, which hangs or errors on some backends: pause so no new
+// callbacks start, let an in-flight one return, then drop the stream.
+// the stream is dropped even if pausing fails
+pub fn stop_input_stream<S: InputStream>(stream: S, settle: Duration) {
+    if let Err(e) = stream.pause() {
+        eprintln!("input stream did not pause cleanly: {}", e);
+    }
+    std::thread::sleep(settle);
+    drop(stream);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mix_selected_channels() {
+        // two frames of a 4-channel interface, channels 3 and 4 carry the signal
+        let data = [0.9, 0.9, 0.2, 0.4,
+                    0.9, 0.9, -0.2, 0.0];
+        let sel = resolve_channel_selection(Some(&[2, 3]), 4);
+        let mono: Vec<f32> = mix_selected(&data, 4, &sel).collect();
+        assert_eq!(mono.len(), 2);
+        assert!((mono[0] - 0.3).abs() < 1e-6);
+        assert!((mono[1] + 0.1).abs() < 1e-6);
+
+        // stereo keeps the pair as-is
+        let pair: Vec<f32> = pick_pair(&data, 4, &sel).collect();
+        assert_eq!(pair, vec![0.2, 0.4, -0.2, 0.0]);
+
+        // a channel the device doesn't have falls back to all channels
+        assert_eq!(resolve_channel_selection(Some(&[4]), 4), vec![0, 1, 2, 3]);
+        assert_eq!(resolve_channel_selection(None, 2), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_input_gain_boosts_and_holds_full_scale() {
+        let mut samples = [0.1, -0.2, 0.6, -0.9];
+        apply_input_gain(&mut samples, 2.0);
+        assert_eq!(samples, [0.2, -0.4, 1.0, -1.0]);
+        apply_input_gain(&mut samples, 1.0);
+        assert_eq!(samples, [0.2, -0.4, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_match_device_name() {
+        let names: Vec<String> = ["default", "USB Audio CODEC", "HDA Intel PCH", "USB Headset"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(match_device_name(&names, "HDA Intel PCH"), Some(2));
+        assert_eq!(match_device_name(&names, "codec"), Some(1));
+        assert_eq!(match_device_name(&names, "usb"), None); // two of them
+        assert_eq!(match_device_name(&names, "Blue Yeti"), None);
+    }
+
+    #[test]
+    fn test_channel_peaks_per_side() {
+        // loud left, quiet (and negative) right
+        let data = [0.8, 0.1, -0.9, -0.2, 0.3, 0.05];
+        assert_eq!(channel_peaks(&data, 2, &[0, 1]), [0.9, 0.2]);
+        // swapped selection swaps the bars
+        assert_eq!(channel_peaks(&data, 2, &[1, 0]), [0.2, 0.9]);
+        // mono leaves the right slot empty
+        assert_eq!(channel_peaks(&[0.5, -0.7], 1, &[0]), [0.7, 0.0]);
+    }
+
+    // records pause/drop into a shared log
+    struct MockStream {
+        log: Arc<Mutex<Vec<&'static str>>>,
+        pause_fails: bool,
+    }
+
+    impl InputStream for MockStream {
+        fn pause(&self) -> Result<(), String> {
+            self.log.lock().unwrap().push("pause");
+            if self.pause_fails { Err("device gone".into()) } else { Ok(()) }
+        }
+    }
+
+    impl Drop for MockStream {
+        fn drop(&mut self) {
+            self.log.lock().unwrap().push("drop");
+        }
+    }
+
+    #[test]
+    fn test_stop_input_stream_pauses_before_drop() {
+        for pause_fails in [false, true] {
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let stream = MockStream { log: log.clone(), pause_fails };
+            let start = std::time::Instant::now();
+            stop_input_stream(stream, Duration::from_millis(10));
+            assert!(start.elapsed() >= Duration::from_millis(10));
+            // a failed pause still ends in a drop
+            assert_eq!(*log.lock().unwrap(), vec!["pause", "drop"]);
+        }
+    }
 }
@@ -1,7 +1,37 @@
-use rodio::{DeviceSinkBuilder, Player, buffer::SamplesBuffer};
+use rodio::{DeviceSinkBuilder, MixerDeviceSink, Player, Source, buffer::SamplesBuffer};
+use cpal::traits::HostTrait;
 use std::num::{NonZeroU16, NonZeroU32}; // positive channel and sample_rate
 use std::sync::{Arc, Mutex};
-use crate::state::{Segment, Project, PlaybackState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use crate::state::{Segment, Project};
+
+// monitoring settings, they change what you hear and nothing else
+#[derive(Clone, Copy, Debug)]
+pub struct PlaybackOptions {
+    pub volume: f32, // player volume, 0.0..=MAX_VOLUME, 1.0 = as recorded
+    // playback rate for skimming long takes, 1.0 = untouched. rodio's speed
+    // control resamples, so pitch moves with it (1.5x sounds higher)
+    pub speed: f32,
+}
+
+// above this the player's own gain makes even normal takes distort
+pub const MAX_VOLUME: f32 = 2.0;
+// outside this range speech stops being intelligible
+pub const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self { volume: 1.0, speed: 1.0 }
+    }
+}
+
+impl PlaybackOptions {
+    fn apply(&self, player: &Player) {
+        player.set_volume(self.volume);
+        player.set_speed(self.speed);
+    }
+}
 
 // For output, spawn a thread to do the playback. When it finishes,
 // it sets playback_state back to Idle so the UI can react.
@@ -11,35 +41,88 @@ use crate::state::{Segment, Project, PlaybackState};
 //   2. set PlaybackState::Idle when done
 //   3. call ctx.request_repaint() so egui redraws (passed as callback)
 //
+// Playback can be cut short: the thread polls the stop flag handed out by
+// begin_playback() instead of blocking in sleep_until_end(), so `stop`
+// halts the player and the sink is dropped (device released) right away.
+//
 pub fn play_segment_async(
     segment: Segment,
     sample_rate: u32,
+    channels: u16, // interleaved layout of segment.samples (the project's)
+    opts: PlaybackOptions,
     recorder: Arc<Mutex<crate::state::RecorderState>>,
     on_done: impl Fn() + Send + 'static // callback after playback finished
+) {
+    play_segment_loop(segment, sample_rate, channels, opts, Some(1), recorder, on_done);
+}
+
+// play from `start_sec` into the segment instead of from the top, for
+// checking the end of a long take. false (and nothing played) when the
+// offset is at or past the end
+pub fn play_segment_from(
+    mut segment: Segment,
+    sample_rate: u32,
+    channels: u16,
+    start_sec: f32,
+    opts: PlaybackOptions,
+    recorder: Arc<Mutex<crate::state::RecorderState>>,
+    on_done: impl Fn() + Send + 'static,
+) -> bool {
+    let skip = seek_offset(segment.samples.len(), sample_rate, channels, start_sec);
+    if skip >= segment.samples.len() { return false; }
+    segment.samples.drain(..skip);
+    play_segment_async(segment, sample_rate, channels, opts, recorder, on_done);
+    true
+}
+
+// samples to skip for a start `seconds` in, whole frames so the channels
+// stay in place. negative counts as 0, past the end gives `len`
+fn seek_offset(len: usize, sample_rate: u32, channels: u16, seconds: f32) -> usize {
+    let ch = channels.max(1) as usize;
+    let frames = (seconds.max(0.0) as f64 * sample_rate as f64).round() as usize;
+    frames.saturating_mul(ch).min(len)
+}
+
+// same, played `count` times back to back, None = until `stop`. it runs on
+// the playback thread like any other playback so the prompt stays usable
+pub fn play_segment_loop(
+    segment: Segment,
+    sample_rate: u32,
+    channels: u16,
+    opts: PlaybackOptions,
+    count: Option<u32>,
+    recorder: Arc<Mutex<crate::state::RecorderState>>,
+    on_done: impl Fn() + Send + 'static,
 ) {
     // set as playing before spawning to disable input
-    {
+    let (stop, device) = {
         let mut rec = recorder.lock().unwrap();
-        rec.playback_state = PlaybackState::Playing;
-    }
+        (rec.begin_playback(), rec.settings.output_device.clone())
+    };
 
     std::thread::spawn(move || {
-        let mut handle = DeviceSinkBuilder::open_default_sink()
-            .expect("Failed to open default audio device");
+        let mut handle = open_sink(device.as_deref());
         handle.log_on_drop(false);
 
         let player = Player::connect_new(handle.mixer()); // connect to audio device
+        opts.apply(&player);
 
-        let channels = NonZeroU16::new(1).unwrap(); // segments are always mono now after downmix
+        let channels = NonZeroU16::new(channels.max(1)).unwrap();
         let rate = NonZeroU32::new(sample_rate).unwrap();
-        let source = SamplesBuffer::new(channels, rate, segment.samples); // copy of audio segment
+        let source = SamplesBuffer::new(channels, rate, segment.heard(channels.get()).into_owned()); // copy of audio segment, at its gain and pan
 
-        player.append(source); // add samplesbuffer to player for playback
-        player.sleep_until_end(); // blocking until playback finished (safe since new thread)
+        // add samplesbuffer to player for playback
+        match count {
+            Some(n) => for _ in 0..n.max(1) { player.append(source.clone()); },
+            None => player.append(source.repeat_infinite()), // never empty, only the stop flag ends it
+        }
+        wait_or_stop(&player, &stop); // blocking until finished or stopped (safe since new thread)
+        drop(player);
+        drop(handle); // release the output device before reporting Idle
 
         //playback is finished at this point
         if let Ok(mut rec) = recorder.lock() {
-            rec.playback_state = PlaybackState::Idle; 
+            rec.finish_playback();
         }
         on_done(); // callback, update UI
     });
@@ -47,51 +130,98 @@ pub fn play_segment_async(
 
 pub fn play_project_async(
     project_snapshot: ProjectSnapshot, // copy of whole project
+    opts: PlaybackOptions,
     recorder: Arc<Mutex<crate::state::RecorderState>>,
     on_done: impl Fn() + Send + 'static,
 ) {
-    {
+    let (stop, device) = {
         let mut rec = recorder.lock().unwrap();
-        rec.playback_state = PlaybackState::Playing;
-    }
-
+        (rec.begin_playback(), rec.settings.output_device.clone())
+    };
 
     std::thread::spawn(move || {
-        let mut handle = DeviceSinkBuilder::open_default_sink()
-            .expect("Failed to open default audio device");
+        let mut handle = open_sink(device.as_deref());
         handle.log_on_drop(false);
 
         let player = Player::connect_new(handle.mixer());
+        opts.apply(&player);
 
-        let mut all_samples: Vec<f32> = Vec::new(); // copy of all audio samples
-        for samples in project_snapshot.segments { // add all project samples to all_samples
-            all_samples.extend(samples);
-        }
+        let all_samples = project_snapshot.joined(); // copy of all audio samples
 
         if all_samples.is_empty() {
             if let Ok(mut rec) = recorder.lock() {
-                rec.playback_state = PlaybackState::Idle;
+                rec.finish_playback();
             }
             on_done();
             return;
         }
 
-        let channels = NonZeroU16::new(1).unwrap(); // force mono like play_segment_async
+        let channels = NonZeroU16::new(project_snapshot.channels.max(1)).unwrap();
         let rate = NonZeroU32::new(project_snapshot.sample_rate)
             .expect("Invalid sample rate");
 
         let source = SamplesBuffer::new(channels, rate, all_samples);
         player.append(source);
-        player.sleep_until_end();
+        wait_or_stop(&player, &stop);
+        drop(player);
+        drop(handle);
 
         if let Ok(mut rec) = recorder.lock() {
-            rec.playback_state = PlaybackState::Idle;
+            rec.finish_playback();
         }
         on_done();
 
     });
 }
 
+// names of the output devices the host offers, what `odev` accepts
+pub fn output_device_names() -> Vec<String> {
+    cpal::default_host().output_devices()
+        .map(|devices| devices.filter_map(|d| crate::audio_input::device_name(&d)).collect())
+        .unwrap_or_default()
+}
+
+// the full name of the output device `wanted` refers to (same matching as
+// --device), or why there isn't one
+pub fn resolve_output_device_name(wanted: &str) -> Result<String, String> {
+    let names = output_device_names();
+    crate::audio_input::match_device_name(&names, wanted)
+        .map(|i| names[i].clone())
+        .ok_or_else(|| format!("No output device called `{}`, available: {}", wanted, names.join(", ")))
+}
+
+// the chosen output device, or the default one. a device that has gone
+// away (unplugged headphones) falls back to the default with a warning
+// rather than leaving the take unplayable
+fn open_sink(device: Option<&str>) -> MixerDeviceSink {
+    if let Some(name) = device {
+        let devices: Vec<cpal::Device> = cpal::default_host().output_devices().map(|d| d.collect()).unwrap_or_default();
+        let names: Vec<String> = devices.iter().map(|d| crate::audio_input::device_name(d).unwrap_or_default()).collect();
+        let found = crate::audio_input::match_device_name(&names, name).map(|i| devices[i].clone());
+        match found.map(|d| DeviceSinkBuilder::from_device(d).and_then(|b| b.open_stream())) {
+            Some(Ok(sink)) => return sink,
+            Some(Err(e)) => eprintln!("Failed to open output device `{}` ({}), using the default", name, e),
+            None => eprintln!("Output device `{}` not found, using the default", name),
+        }
+    }
+    DeviceSinkBuilder::open_default_sink().expect("Failed to open default audio device")
+}
+
+// stand-in for player.sleep_until_end() that can be interrupted,
+// polls often enough that `stop` feels instant
+fn wait_or_stop(player: &Player, stop: &AtomicBool) {
+    while !player.empty() {
+        if stop.load(Ordering::Relaxed) {
+            player.stop();
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+// longest silence `pa <gap>` puts between takes, longer only wastes memory
+pub const MAX_GAP_SECONDS: f32 = 10.0;
+
 // *** plain-data snapshot of the project
 // the problem is we can't send &Project across threads (because its behind
 // a mutex and non-Send types), so instead clone the data before spawning
@@ -99,14 +229,116 @@ pub fn play_project_async(
 pub struct ProjectSnapshot {
     pub segments: Vec<Vec<f32>>,
     pub sample_rate: u32,
-    // channels always mono so not needed
+    pub channels: u16,
+    pub crossfade_ms: f32, // 0 = hard cuts, set from ExportOptions so `pa` sounds like the export
+    pub gap_seconds: f32, // silence between segments for `pa <gap>`, 0 = joined like the export
+    pub boundary_fade_ms: f32, // short fades at the joins, from ExportOptions like crossfade_ms
 }
 
 impl ProjectSnapshot {
     pub fn from_project(project: &Project) -> Self {
         Self {
-            segments: project.segments.iter().map(|s| s.samples.clone()).collect(),
+            segments: project.segments.iter().filter(|s| !s.muted).map(|s| s.heard(project.channels).into_owned()).collect(),
+            sample_rate: project.sample_rate,
+            channels: project.channels,
+            crossfade_ms: 0.0,
+            gap_seconds: 0.0,
+            boundary_fade_ms: 0.0,
+        }
+    }
+
+    // just segments start..=end (0-based), for auditioning the joins
+    // between a few takes. None when the range is empty or out of bounds.
+    // muted segments are left out here as in from_project
+    pub fn from_range(project: &Project, start: usize, end: usize) -> Option<Self> {
+        let segs = project.segments.get(start..=end)?;
+        Some(Self {
+            segments: segs.iter().filter(|s| !s.muted).map(|s| s.heard(project.channels).into_owned()).collect(),
             sample_rate: project.sample_rate,
+            channels: project.channels,
+            crossfade_ms: 0.0,
+            gap_seconds: 0.0,
+            boundary_fade_ms: 0.0,
+        })
+    }
+
+    // every segment back to back. with a gap the takes are kept apart by
+    // that much silence (no fades, the point is to hear the edges),
+    // otherwise they're joined the way export joins them
+    pub fn joined(&self) -> Vec<f32> {
+        if self.gap_seconds > 0.0 { // NaN falls through to the plain join
+            let channels = self.channels.max(1) as usize;
+            let gap = (self.gap_seconds.min(MAX_GAP_SECONDS) * self.sample_rate as f32) as usize * channels;
+            let mut out = Vec::with_capacity(self.segments.iter().map(Vec::len).sum::<usize>() + gap * self.segments.len());
+            for (i, seg) in self.segments.iter().enumerate() {
+                if i > 0 { out.extend(std::iter::repeat_n(0.0, gap)); }
+                out.extend_from_slice(seg);
+            }
+            return out;
+        }
+        let fade = crate::state::ms_to_samples(self.crossfade_ms, self.sample_rate);
+        let boundary = crate::state::ms_to_samples(self.boundary_fade_ms, self.sample_rate);
+        if fade == 0 && boundary > 0 {
+            let last = self.segments.len().saturating_sub(1);
+            let mut out = Vec::with_capacity(self.segments.iter().map(Vec::len).sum());
+            for (i, seg) in self.segments.iter().enumerate() {
+                let start = out.len();
+                out.extend_from_slice(seg);
+                crate::dsp::fade_edges(&mut out[start..], self.channels, boundary, i > 0, i < last);
+            }
+            return out;
         }
+        let parts: Vec<&[f32]> = self.segments.iter().map(|s| &s[..]).collect();
+        crate::dsp::crossfade_concat(&parts, fade, self.channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_of_a_range() {
+        let project = Project {
+            segments: (1..=5).map(|i| Segment::new(vec![i as f32])).collect(),
+            sample_rate: 8000,
+            channels: 1,
+            editing_index: None,
+            metadata: Default::default(),
+        };
+        let snap = ProjectSnapshot::from_range(&project, 1, 3).unwrap();
+        assert_eq!(snap.segments, vec![vec![2.0], vec![3.0], vec![4.0]]);
+        assert_eq!(ProjectSnapshot::from_range(&project, 4, 4).unwrap().segments.len(), 1);
+        assert!(ProjectSnapshot::from_range(&project, 3, 5).is_none()); // past the end
+        assert!(ProjectSnapshot::from_range(&project, 3, 1).is_none()); // backwards
+    }
+
+    #[test]
+    fn test_seek_offset() {
+        assert_eq!(seek_offset(400, 100, 2, 0.5), 100); // 50 frames in
+        assert_eq!(seek_offset(400, 100, 2, -1.0), 0);
+        assert_eq!(seek_offset(400, 100, 2, 2.0), 400); // exactly the end
+        assert_eq!(seek_offset(400, 100, 2, 60.0), 400);
+        assert_eq!(seek_offset(9, 100, 1, 0.016), 2); // rounds to the nearest frame
+    }
+
+    #[test]
+    fn test_gap_between_segments() {
+        let snap = ProjectSnapshot {
+            segments: vec![vec![1.0, 1.0], vec![2.0, 2.0], vec![3.0, 3.0]],
+            sample_rate: 4,
+            channels: 2,
+            crossfade_ms: 0.0,
+            gap_seconds: 0.5, // 2 frames
+            boundary_fade_ms: 0.0,
+        };
+        assert_eq!(snap.joined(), vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 0.0, 0.0, 0.0, 0.0, 3.0, 3.0]);
+        let hard = ProjectSnapshot { gap_seconds: 0.0, ..snap };
+        assert_eq!(hard.joined(), vec![1.0, 1.0, 2.0, 2.0, 3.0, 3.0]);
+        let faded = ProjectSnapshot { boundary_fade_ms: 250.0, ..hard }; // 1 frame
+        assert_eq!(faded.joined(), vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        // an absurd gap is capped instead of overflowing
+        let endless = ProjectSnapshot { gap_seconds: f32::INFINITY, boundary_fade_ms: 0.0, ..faded };
+        assert_eq!(endless.joined().len(), 6 + 2 * (MAX_GAP_SECONDS as usize * 4 * 2));
     }
 }
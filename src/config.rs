@@ -0,0 +1,177 @@
+use crate::state::{ChannelPolicy, ReviewAction, Settings};
+use std::str::FromStr;
+
+// startup config loaded with --config: a flat `key = value` file (a TOML
+// subset, so editors highlight it), `#` starts a comment and strings may be
+// quoted. every key is a Settings field. unknown keys and bad values come
+// back as warnings and are skipped, a typo never stops the app starting
+//
+//   review_repeats = 2
+//   auto_trim = 0.01        # off to disable
+//   input_channels = "3,4"  # 1-based, like --channels
+//   prompt = "{state} {segs} segs {total}"
+
+pub fn load_config(path: &str, settings: &mut Settings) -> std::io::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(apply_config(&text, settings))
+}
+
+pub fn apply_config(text: &str, settings: &mut Settings) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() { continue; }
+        let Some((key, value)) = line.split_once('=') else {
+            warnings.push(format!("line {}: expected `key = value`", n + 1));
+            continue;
+        };
+        let value = value.trim();
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+        if let Err(e) = set(settings, key.trim(), value) {
+            warnings.push(format!("line {}: {}", n + 1, e));
+        }
+    }
+    warnings
+}
+
+// 1-based channel numbers "3,4" -> 0-based [2, 3], shared with --channels
+pub fn parse_channel_list(s: &str) -> Option<Vec<usize>> {
+    let chans = s.split(',').map(|c| c.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>().ok()?;
+    chans.iter().all(|&c| c >= 1).then(|| chans.iter().map(|c| c - 1).collect())
+}
+
+fn set(s: &mut Settings, key: &str, v: &str) -> Result<(), String> {
+    match key {
+        "sparkline_width"      => s.sparkline_width = parse(v)?,
+        "review_repeats"       => s.review_repeats = parse::<u32>(v)?.max(1),
+        "auto_export_segments" => s.auto_export_segments = optional(v)?,
+        "auto_export_seconds"  => s.auto_export_seconds = optional(v)?,
+        "trim_pad_ms"          => s.trim_pad_ms = ms(v)?,
+        "context_ms"           => s.context_ms = ms(v)?,
+        "auto_trim"            => s.auto_trim = optional(v)?,
+        "min_segment_ms"       => s.min_segment_ms = optional::<String>(v)?.map(|m| ms(&m)).transpose()?,
+        "reject_short"         => s.reject_short = parse(v)?,
+        "review_timeout"       => s.review_timeout = optional(v)?,
+        "auto_stop"            => s.auto_stop = optional(v)?,
+        "auto_stop_threshold"  => s.auto_stop_threshold = parse::<f32>(v)?.abs(),
+        "max_record_seconds"   => s.max_record_seconds = optional(v)?,
+        "realtime_priority"    => s.realtime_priority = parse(v)?,
+        "block_size"           => s.block_size = parse::<usize>(v)?.max(1),
+        "dither"               => s.export.dither = parse(v)?,
+        "dither_seed"          => s.export.dither_seed = optional(v)?,
+        "limit"                => s.export.limit = parse(v)?,
+        "export_format"        => s.export.format = crate::export::ExportFormat::parse(v).ok_or_else(|| format!("`{}` is not 16, 24 or float", v))?,
+        "crossfade_ms"         => s.export.crossfade_ms = ms(v)?,
+        "boundary_fade_ms"     => s.export.boundary_fade_ms = ms(v)?,
+        "speed"                => s.playback.speed = parse::<f32>(v)?.clamp(*crate::audio_output::SPEED_RANGE.start(), *crate::audio_output::SPEED_RANGE.end()),
+        "volume"               => s.playback.volume = parse::<f32>(v)?.clamp(0.0, crate::audio_output::MAX_VOLUME),
+        "input_gain"           => s.input_gain = parse::<f32>(v)?.max(0.0),
+        "sample_rate"          => s.target_sample_rate = optional::<u32>(v)?.filter(|&r| r > 0),
+        "noise_gate"           => s.noise_gate = parse::<f32>(v)?.abs(),
+        "stereo"               => s.stereo = parse(v)?,
+        "input_device" => s.input_device = (v != "default").then(|| v.to_string()),
+        "output_device" => s.output_device = (v != "default").then(|| v.to_string()),
+        "input_channels" => {
+            s.input_channels = match v {
+                "all" => None,
+                _ => Some(parse_channel_list(v).ok_or_else(|| format!("`{}` is not a list of channel numbers", v))?),
+            }
+        }
+        "review_default" => {
+            s.review_default = match v {
+                "approve" => ReviewAction::Approve,
+                "reject" => ReviewAction::Reject,
+                _ => return Err(format!("review_default must be approve or reject, not `{}`", v)),
+            }
+        }
+        "channel_mismatch" => {
+            s.channel_mismatch = match v {
+                "dup" => ChannelPolicy::Duplicate,
+                "refuse" => ChannelPolicy::Refuse,
+                _ => return Err(format!("channel_mismatch must be dup or refuse, not `{}`", v)),
+            }
+        }
+        "prompt" => s.prompt_template = (v != "default").then(|| v.to_string()),
+        _ => return Err(format!("unknown key `{}`", key)),
+    }
+    Ok(())
+}
+
+fn parse<T: FromStr>(v: &str) -> Result<T, String> {
+    v.parse().map_err(|_| format!("invalid value `{}`", v))
+}
+
+// milliseconds, finite (they become sample counts) and at least 0
+fn ms(v: &str) -> Result<f32, String> {
+    let ms = parse::<f32>(v)?;
+    if ms.is_finite() { Ok(ms.max(0.0)) } else { Err(format!("`{}` is not a finite number of ms", v)) }
+}
+
+// "off" / "none" turn an optional setting off
+fn optional<T: FromStr>(v: &str) -> Result<Option<T>, String> {
+    match v {
+        "off" | "none" => Ok(None),
+        _ => parse(v).map(Some),
+    }
+}
+
+// drop a trailing # comment, but not a # inside a quoted string
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_config_populates_settings() {
+        let text = r##"
+            # booth setup
+            review_repeats = 2
+            auto_trim = 0.02
+            trim_pad_ms = 40
+            min_segment_ms = 50
+            reject_short = true
+            review_timeout = off
+            review_default = reject
+            input_channels = "3,4"
+            prompt = "#{segs} {state}"   # quoted # is kept
+            dither_seed = 42
+            volume = 3              # clamped to the player's max
+            colour = blue
+            sparkline_width = wide
+            crossfade_ms = inf
+        "##;
+        let mut settings = Settings::default();
+        let warnings = apply_config(text, &mut settings);
+
+        assert_eq!(settings.review_repeats, 2);
+        assert_eq!(settings.auto_trim, Some(0.02));
+        assert_eq!(settings.trim_pad_ms, 40.0);
+        assert_eq!(settings.min_segment_ms, Some(50.0));
+        assert!(settings.reject_short);
+        assert_eq!(settings.review_timeout, None);
+        assert_eq!(settings.review_default, ReviewAction::Reject);
+        assert_eq!(settings.input_channels, Some(vec![2, 3]));
+        assert_eq!(settings.prompt_template.as_deref(), Some("#{segs} {state}"));
+        assert_eq!(settings.export.dither_seed, Some(42));
+        assert_eq!(settings.playback.volume, 2.0);
+
+        // the unknown key and the bad value are reported, the rest still applied
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].contains("unknown key `colour`"));
+        assert!(warnings[1].contains("invalid value `wide`"));
+        assert!(warnings[2].contains("not a finite number"));
+        assert_eq!(settings.export.crossfade_ms, 0.0);
+        assert_eq!(settings.sparkline_width, Settings::default().sparkline_width);
+    }
+}
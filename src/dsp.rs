@@ -0,0 +1,1120 @@
+// Shared signal-processing primitives for the segment effects.
+// Nothing in here knows about segments or RecorderState, it's plain
+// sample-in/sample-out math so every effect can reuse the same filters.
+
+use std::f64::consts::PI;
+
+// ===== Block processing =====
+//
+// every effect here is a sample-in/sample-out state machine. process_in_blocks
+// walks interleaved audio in place, a block of frames at a time with one
+// processor per channel, and renders any tail a block at a time, so memory
+// stays bounded by the block size instead of needing a second whole-segment
+// buffer (or one per channel)
+
+pub const DEFAULT_BLOCK_SIZE: usize = 4096;
+
+pub trait Processor {
+    fn process(&mut self, x: f32) -> f32;
+
+    // samples the effect keeps sounding after the input ends (reverb/echo)
+    fn tail_samples(&self) -> usize { 0 }
+}
+
+impl<P: Processor + ?Sized> Processor for Box<P> {
+    fn process(&mut self, x: f32) -> f32 { (**self).process(x) }
+    fn tail_samples(&self) -> usize { (**self).tail_samples() }
+}
+
+// apply `procs` (one per channel, interleaved in that order) in place, then
+// append the longest tail. `block_size` is in frames. output is identical to
+// running each channel's samples through its processor one by one, whatever
+// the block size
+pub fn process_in_blocks<P: Processor>(procs: &mut [P], samples: &mut Vec<f32>, block_size: usize) {
+    let ch = procs.len();
+    if ch == 0 { return; }
+    let block_size = block_size.max(1);
+    for block in samples.chunks_mut(block_size * ch) {
+        for frame in block.chunks_mut(ch) {
+            for (s, p) in frame.iter_mut().zip(procs.iter_mut()) {
+                *s = p.process(*s);
+            }
+        }
+    }
+    let mut remaining = procs.iter().map(|p| p.tail_samples()).max().unwrap_or(0);
+    while remaining > 0 {
+        let n = remaining.min(block_size);
+        samples.reserve(n * ch);
+        for _ in 0..n {
+            samples.extend(procs.iter_mut().map(|p| p.process(0.0)));
+        }
+        remaining -= n;
+    }
+}
+
+// ===== Effect family =====
+
+// one effect and its parameters, as plain data so it can travel through a
+// Command and be applied to either a segment or a rendered copy of one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EffectSpec {
+    Gain(f32),                     // linear multiplier
+    Filter(FilterKind, f32, f32),  // (shape, freq_hz, q)
+    Reverb(f32, f32),              // (room_size, wet)
+    Delay(f32, f32, f32),          // (time_ms, feedback, wet)
+}
+
+impl EffectSpec {
+    // run the effect over interleaved audio. each channel gets its own
+    // processor so filter/echo state never leaks between left and right
+    pub fn apply(&self, samples: &mut Vec<f32>, sample_rate: u32, channels: u16, block_size: usize) {
+        let mut procs: Vec<Box<dyn Processor>> = (0..channels.max(1)).map(|_| self.processor(sample_rate)).collect();
+        process_in_blocks(&mut procs, samples, block_size);
+    }
+
+    pub fn processor(&self, sample_rate: u32) -> Box<dyn Processor> {
+        match *self {
+            EffectSpec::Gain(g)             => Box::new(Gain(g)),
+            EffectSpec::Filter(k, f, q)     => Box::new(Biquad::new(k, sample_rate, f, q)),
+            EffectSpec::Reverb(room, wet)   => Box::new(Reverb::new(sample_rate, room, wet)),
+            EffectSpec::Delay(ms, fb, wet)  => Box::new(Delay::new(sample_rate, ms, fb, wet)),
+        }
+    }
+
+    // short human-readable form for segment edit logs
+    pub fn describe(&self) -> String {
+        match self {
+            EffectSpec::Gain(g)             => format!("gain x{}", g),
+            EffectSpec::Filter(k, f, q)     => format!("filter {:?} {}Hz q {}", k, f, q),
+            EffectSpec::Reverb(room, wet)   => format!("reverb room {} wet {}", room, wet),
+            EffectSpec::Delay(ms, fb, wet)  => format!("delay {}ms feedback {} wet {}", ms, fb, wet),
+        }
+    }
+}
+
+// ===== Gain =====
+
+pub struct Gain(pub f32);
+
+impl Processor for Gain {
+    fn process(&mut self, x: f32) -> f32 { x * self.0 }
+}
+
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// ===== Test tone =====
+
+pub const REFERENCE_TONE_HZ: f32 = 1000.0;
+const TONE_FADE_MS: f32 = 10.0; // short ramps so the tone doesn't click on and off
+
+// sine whose peak sits at `level_db` dBFS
+pub fn sine_tone(freq: f32, level_db: f32, seconds: f32, sample_rate: u32) -> Vec<f32> {
+    let amp = db_to_linear(level_db);
+    let n = (seconds.max(0.0) * sample_rate as f32) as usize;
+    let fade = ((TONE_FADE_MS / 1000.0 * sample_rate as f32) as usize).clamp(1, (n / 2).max(1));
+    (0..n).map(|i| {
+        let ramp = (i.min(n - 1 - i) as f32 / fade as f32).min(1.0);
+        amp * ramp * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin()
+    }).collect()
+}
+
+// ===== Panning =====
+
+// left/right gains for `pan` (-1 hard left, 0 centre, 1 hard right) using the
+// equal-power law: angle θ = (pan + 1)·π/4, gains cos θ and sin θ, so
+// L² + R² stays constant and a sweep doesn't dip in the middle. scaled by √2
+// so the centre is unity and pan 0 sounds exactly as recorded
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let theta = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+    (theta.cos() * std::f32::consts::SQRT_2, theta.sin() * std::f32::consts::SQRT_2)
+}
+
+// place a mono take in the stereo field: each L/R frame is folded to one
+// mono sample and split back out with the pan gains. stereo only
+pub fn pan_stereo(samples: &mut [f32], pan: f32) {
+    let (l, r) = pan_gains(pan);
+    for frame in samples.chunks_exact_mut(2) {
+        let mono = (frame[0] + frame[1]) * 0.5;
+        frame[0] = mono * l;
+        frame[1] = mono * r;
+    }
+}
+
+// peak and RMS of a buffer from a single pass, for `stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LevelStats {
+    pub peak: f32, // max |sample|
+    pub rms: f32,
+    pub samples: usize,
+}
+
+impl LevelStats {
+    pub fn of(samples: &[f32]) -> Self {
+        let (peak, sum_sq) = samples.iter().fold((0.0_f32, 0.0_f64), |(peak, sum), &s| {
+            (peak.max(s.abs()), sum + (s as f64) * (s as f64))
+        });
+        let rms = if samples.is_empty() { 0.0 } else { (sum_sq / samples.len() as f64).sqrt() as f32 };
+        Self { peak, rms, samples: samples.len() }
+    }
+}
+
+// dBFS of a linear level, None for silence (log of 0 would be -inf)
+pub fn linear_to_db(level: f32) -> Option<f32> {
+    (level > 0.0 && level.is_finite()).then(|| 20.0 * level.log10())
+}
+
+// root-mean-square level, 0.0 for an empty buffer
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() { return 0.0; }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+// ===== Biquad =====
+//
+// second-order IIR filter using the RBJ "Audio EQ Cookbook" designs,
+// run as transposed direct form II. coefficients and state are f64 so
+// low cutoffs (e.g. 40Hz at 48kHz) stay stable; audio stays f32 in/out
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    b0: f64, b1: f64, b2: f64, // feed-forward (zeros)
+    a1: f64, a2: f64,          // feedback (poles), a0 normalised to 1
+    z1: f64, z2: f64,          // filter memory
+}
+
+// which cookbook shape to build, used by the `eq` command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterKind {
+    LowPass,
+    HighPass,
+    Notch,
+    Peaking(f32), // boost/cut in dB at the centre frequency
+}
+
+impl Biquad {
+    // shared cookbook setup: w0 and alpha for a centre/cutoff frequency.
+    // freq is clamped below Nyquist so a bad argument can't blow up the filter
+    fn design(sample_rate: u32, freq: f32, q: f32) -> (f64, f64) {
+        let nyquist = sample_rate as f64 / 2.0;
+        let f0 = (freq as f64).clamp(1.0, nyquist * 0.99);
+        let w0 = 2.0 * PI * f0 / sample_rate as f64;
+        let alpha = w0.sin() / (2.0 * (q as f64).max(0.01));
+        (w0, alpha)
+    }
+
+    fn from_coeffs(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0: b0 / a0, b1: b1 / a0, b2: b2 / a0,
+            a1: a1 / a0, a2: a2 / a0,
+            z1: 0.0, z2: 0.0,
+        }
+    }
+
+    pub fn lowpass(sample_rate: u32, freq: f32, q: f32) -> Self {
+        let (w0, alpha) = Self::design(sample_rate, freq, q);
+        let c = w0.cos();
+        Self::from_coeffs((1.0 - c) / 2.0, 1.0 - c, (1.0 - c) / 2.0, 1.0 + alpha, -2.0 * c, 1.0 - alpha)
+    }
+
+    pub fn highpass(sample_rate: u32, freq: f32, q: f32) -> Self {
+        let (w0, alpha) = Self::design(sample_rate, freq, q);
+        let c = w0.cos();
+        Self::from_coeffs((1.0 + c) / 2.0, -(1.0 + c), (1.0 + c) / 2.0, 1.0 + alpha, -2.0 * c, 1.0 - alpha)
+    }
+
+    pub fn notch(sample_rate: u32, freq: f32, q: f32) -> Self {
+        let (w0, alpha) = Self::design(sample_rate, freq, q);
+        let c = w0.cos();
+        Self::from_coeffs(1.0, -2.0 * c, 1.0, 1.0 + alpha, -2.0 * c, 1.0 - alpha)
+    }
+
+    pub fn peaking(sample_rate: u32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let (w0, alpha) = Self::design(sample_rate, freq, q);
+        let c = w0.cos();
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        Self::from_coeffs(1.0 + alpha * a, -2.0 * c, 1.0 - alpha * a, 1.0 + alpha / a, -2.0 * c, 1.0 - alpha / a)
+    }
+
+    pub fn new(kind: FilterKind, sample_rate: u32, freq: f32, q: f32) -> Self {
+        match kind {
+            FilterKind::LowPass      => Self::lowpass(sample_rate, freq, q),
+            FilterKind::HighPass     => Self::highpass(sample_rate, freq, q),
+            FilterKind::Notch        => Self::notch(sample_rate, freq, q),
+            FilterKind::Peaking(db)  => Self::peaking(sample_rate, freq, q, db),
+        }
+    }
+
+    // analytic magnitude |H(e^jw)| at `freq`, used to sanity-check designs
+    #[allow(unused)]
+    pub fn magnitude(&self, freq: f32, sample_rate: u32) -> f32 {
+        let w = 2.0 * PI * freq as f64 / sample_rate as f64;
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let num_re = self.b0 + self.b1 * c1 + self.b2 * c2;
+        let num_im = -(self.b1 * s1 + self.b2 * s2);
+        let den_re = 1.0 + self.a1 * c1 + self.a2 * c2;
+        let den_im = -(self.a1 * s1 + self.a2 * s2);
+        ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt() as f32
+    }
+}
+
+impl Processor for Biquad {
+    fn process(&mut self, x: f32) -> f32 {
+        let x = x as f64;
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y as f32
+    }
+}
+
+// ===== Reverb =====
+//
+// Schroeder/Freeverb-style reverb: eight damped feedback combs in
+// parallel build the dense tail, two allpasses in series diffuse it.
+// delay lengths are the classic Freeverb tunings (tuned at 44.1kHz)
+// scaled to the project rate
+
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+const REVERB_INPUT_GAIN: f32 = 0.015; // keeps eight summed combs near unity
+const REVERB_DAMP: f32 = 0.2;          // high-frequency loss per trip round a comb
+const MAX_REVERB_TAIL_SECS: f32 = 4.0;
+
+struct Comb {
+    buf: Vec<f32>,
+    idx: usize,
+    feedback: f32,
+    store: f32, // one-pole lowpass state for damping
+}
+
+impl Comb {
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.buf[self.idx];
+        self.store = y * (1.0 - REVERB_DAMP) + self.store * REVERB_DAMP;
+        self.buf[self.idx] = x + self.store * self.feedback;
+        self.idx = (self.idx + 1) % self.buf.len();
+        y
+    }
+}
+
+struct Allpass {
+    buf: Vec<f32>,
+    idx: usize,
+}
+
+impl Allpass {
+    fn process(&mut self, x: f32) -> f32 {
+        let delayed = self.buf[self.idx];
+        self.buf[self.idx] = x + delayed * 0.5;
+        self.idx = (self.idx + 1) % self.buf.len();
+        delayed - x
+    }
+}
+
+pub struct Reverb {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+    wet: f32,
+    tail: usize,
+}
+
+impl Reverb {
+    // room_size 0.0..=1.0 sets the comb feedback (decay time),
+    // wet 0.0..=1.0 is the reverb/dry balance
+    pub fn new(sample_rate: u32, room_size: f32, wet: f32) -> Self {
+        let scale = sample_rate as f32 / 44100.0;
+        let len = |n: usize| ((n as f32 * scale) as usize).max(1);
+        let feedback = 0.7 + room_size.clamp(0.0, 1.0) * 0.28;
+        // time for the longest comb to fall 60dB, capped so huge rooms stay sane
+        let longest = len(COMB_TUNING[7]) as f32;
+        let tail = (longest * (0.001_f32.ln() / feedback.ln()))
+            .min(MAX_REVERB_TAIL_SECS * sample_rate as f32) as usize;
+        Self {
+            combs: COMB_TUNING.iter()
+                .map(|&n| Comb { buf: vec![0.0; len(n)], idx: 0, feedback, store: 0.0 })
+                .collect(),
+            allpasses: ALLPASS_TUNING.iter()
+                .map(|&n| Allpass { buf: vec![0.0; len(n)], idx: 0 })
+                .collect(),
+            wet: wet.clamp(0.0, 1.0),
+            tail,
+        }
+    }
+
+}
+
+impl Processor for Reverb {
+    // how many samples the reverb keeps ringing after the input stops
+    fn tail_samples(&self) -> usize {
+        self.tail
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let input = x * REVERB_INPUT_GAIN;
+        let mut y: f32 = self.combs.iter_mut().map(|c| c.process(input)).sum();
+        for ap in self.allpasses.iter_mut() {
+            y = ap.process(y);
+        }
+        x * (1.0 - self.wet) + y * self.wet
+    }
+}
+
+// ===== Delay =====
+//
+// single feedback delay line (echo). each repeat is the previous one
+// scaled by `feedback`, which is held below 1.0 so it always dies out
+
+const MAX_DELAY_FEEDBACK: f32 = 0.95;
+const MAX_DELAY_TAIL_SECS: f32 = 10.0;
+// longest delay time, well inside the tail so at least a few repeats are heard
+pub const MAX_DELAY_MS: f32 = 2000.0;
+
+// the times `delay` accepts, above 0 and at most MAX_DELAY_MS (not inf or NaN)
+pub fn delay_time_ok(time_ms: f32) -> bool {
+    time_ms > 0.0 && time_ms <= MAX_DELAY_MS
+}
+
+pub struct Delay {
+    buf: Vec<f32>,
+    idx: usize,
+    feedback: f32,
+    wet: f32,
+    tail: usize,
+}
+
+impl Delay {
+    pub fn new(sample_rate: u32, time_ms: f32, feedback: f32, wet: f32) -> Self {
+        let time_ms = if time_ms.is_nan() { 1.0 } else { time_ms.clamp(1.0, MAX_DELAY_MS) };
+        let len = ((time_ms / 1000.0 * sample_rate as f32) as usize).max(1);
+        let feedback = feedback.clamp(0.0, MAX_DELAY_FEEDBACK);
+        // repeats until they're 60dB down (at least the first echo), capped
+        let repeats = if feedback > 0.0 { (0.001_f32.ln() / feedback.ln()).ceil() as usize } else { 1 };
+        let tail = (len * repeats.max(1)).min((MAX_DELAY_TAIL_SECS * sample_rate as f32) as usize);
+        Self { buf: vec![0.0; len], idx: 0, feedback, wet: wet.clamp(0.0, 1.0), tail }
+    }
+
+}
+
+impl Processor for Delay {
+    fn tail_samples(&self) -> usize {
+        self.tail
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let delayed = self.buf[self.idx];
+        self.buf[self.idx] = x + delayed * self.feedback;
+        self.idx = (self.idx + 1) % self.buf.len();
+        x * (1.0 - self.wet) + delayed * self.wet
+    }
+}
+
+// ===== Limiter =====
+//
+// look-ahead peak limiter for export: instead of letting hot samples hit
+// the integer clamp (flat-topped, harsh) the gain is eased down just before
+// each over and eased back up afterwards. it needs to see ahead so it works
+// on a whole buffer rather than as a Processor.
+//
+// peaks are "true peak" aware: besides each sample the curve half way to
+// the next one is estimated (cubic) since the reconstructed waveform can
+// overshoot between samples. interleaved channels share one gain so the
+// stereo image doesn't shift.
+
+const LIMITER_LOOKAHEAD_MS: f32 = 1.5;
+const LIMITER_RELEASE_MS: f32 = 50.0;
+
+pub fn limit(samples: &[f32], channels: u16, sample_rate: u32, ceiling: f32) -> Vec<f32> {
+    let ch = channels.max(1) as usize;
+    let frames = samples.len() / ch;
+    let at = |f: isize, c: usize| samples[(f.clamp(0, frames as isize - 1) as usize) * ch + c];
+
+    // gain each frame needs on its own to stay under the ceiling
+    let required: Vec<f32> = (0..frames as isize).map(|f| {
+        let peak = (0..ch).map(|c| {
+            let (p0, p1, p2, p3) = (at(f - 1, c), at(f, c), at(f + 1, c), at(f + 2, c));
+            // catmull-rom midpoint between f and f + 1
+            let mid = (-p0 + 9.0 * p1 + 9.0 * p2 - p3) / 16.0;
+            p1.abs().max(mid.abs())
+        }).fold(0.0_f32, f32::max);
+        if peak > ceiling { ceiling / peak } else { 1.0 }
+    }).collect();
+
+    // ramp linearly into every reduction over the look-ahead window so the
+    // gain is already down when the peak arrives
+    let lookahead = ((LIMITER_LOOKAHEAD_MS / 1000.0 * sample_rate as f32) as usize).max(1);
+    let mut gain = vec![1.0_f32; frames];
+    for (j, &req) in required.iter().enumerate() {
+        if req >= 1.0 { continue; }
+        for (k, g) in gain[j.saturating_sub(lookahead)..=j].iter_mut().rev().enumerate() {
+            *g = g.min(req + (1.0 - req) * k as f32 / lookahead as f32);
+        }
+    }
+
+    // exponential release, never above what the ramp allows
+    let release = 1.0 - (-1.0 / (LIMITER_RELEASE_MS / 1000.0 * sample_rate as f32)).exp();
+    let mut current = 1.0_f32;
+    for g in gain.iter_mut() {
+        current = (current + (1.0 - current) * release).min(*g);
+        *g = current;
+    }
+
+    let mut out = samples.to_vec();
+    for (frame, g) in out.chunks_exact_mut(ch).zip(&gain) {
+        frame.iter_mut().for_each(|s| *s *= g);
+    }
+    out
+}
+
+// ===== Declick =====
+//
+// clicks show up as a spike in the second difference (x[i+1] - 2x[i] + x[i-1]),
+// which stays tiny for anything smooth like speech or a sine. a sample is
+// suspect when that spike is far above the buffer's typical (median) value;
+// `sensitivity` scales how far (2.0 = twice as eager). each run of suspects,
+// widened by a sample either side, is redrawn as a straight line between
+// the good samples around it
+
+const DECLICK_RATIO: f32 = 10.0;   // spike / median at sensitivity 1.0
+const DECLICK_FLOOR: f32 = 1e-3;   // so digital silence doesn't flag everything
+
+// repair clicks in place, returns how many were found
+pub fn declick(samples: &mut [f32], sensitivity: f32) -> usize {
+    if samples.len() < 4 || sensitivity <= 0.0 { return 0; }
+    let curvature: Vec<f32> = samples.windows(3).map(|w| (w[2] - 2.0 * w[1] + w[0]).abs()).collect();
+    let mut sorted = curvature.clone();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    let threshold = (DECLICK_RATIO * median / sensitivity).max(DECLICK_FLOOR);
+
+    // curvature[i] belongs to sample i + 1
+    let suspect: Vec<bool> = (0..samples.len())
+        .map(|i| i > 0 && curvature.get(i - 1).is_some_and(|&c| c > threshold))
+        .collect();
+
+    let mut clicks = 0;
+    let mut i = 1;
+    while i < samples.len() - 1 {
+        if !suspect[i] { i += 1; continue; }
+        let start = i.saturating_sub(1).max(1);
+        let mut end = i;
+        while end + 1 < samples.len() - 1 && suspect[end + 1] { end += 1; }
+        let end = (end + 1).min(samples.len() - 2);
+        // straight line from the last good sample before to the first good one after
+        let (a, b) = (samples[start - 1], samples[end + 1]);
+        let span = (end + 2 - start) as f32;
+        for (k, s) in samples[start..=end].iter_mut().enumerate() {
+            *s = a + (b - a) * (k + 1) as f32 / span;
+        }
+        clicks += 1;
+        i = end + 2;
+    }
+    clicks
+}
+
+// ===== Noise gate =====
+//
+// capture-time gate for room hum: while the input stays under the threshold
+// the gain closes towards zero, and opens again as soon as it goes above.
+// opening is fast so word onsets aren't swallowed, closing waits out a short
+// hold and then fades so the gate doesn't chatter on a decaying word.
+// it keeps its state between buffers, so one gate lives as long as the
+// stream. interleaved channels share one gain like the limiter
+
+const GATE_ATTACK_MS: f32 = 1.0;
+const GATE_HOLD_MS: f32 = 50.0;
+const GATE_RELEASE_MS: f32 = 80.0;
+
+pub struct NoiseGate {
+    threshold: f32,
+    attack: f32,  // per-frame smoothing towards open
+    release: f32, // per-frame smoothing towards closed
+    hold: usize,  // quiet frames before it starts closing
+    gain: f32,
+    quiet: usize,
+}
+
+impl NoiseGate {
+    pub fn new(threshold: f32, sample_rate: u32) -> Self {
+        let coeff = |ms: f32| 1.0 - (-1.0 / (ms / 1000.0 * sample_rate as f32).max(1.0)).exp();
+        Self {
+            threshold,
+            attack: coeff(GATE_ATTACK_MS),
+            release: coeff(GATE_RELEASE_MS),
+            hold: (GATE_HOLD_MS / 1000.0 * sample_rate as f32) as usize,
+            gain: 1.0, // open, so the first word of a take is never cut
+            quiet: 0,
+        }
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub fn process_interleaved(&mut self, samples: &mut [f32], channels: u16) {
+        for frame in samples.chunks_mut(channels.max(1) as usize) {
+            let peak = frame.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+            let target = if peak > self.threshold {
+                self.quiet = 0;
+                1.0
+            } else {
+                self.quiet += 1;
+                if self.quiet > self.hold { 0.0 } else { 1.0 }
+            };
+            let coeff = if target > self.gain { self.attack } else { self.release };
+            self.gain += (target - self.gain) * coeff;
+            frame.iter_mut().for_each(|s| *s *= self.gain);
+        }
+    }
+}
+
+// ===== Crossfade =====
+
+// frames two neighbours overlap by: the fade length, but never more than
+// either of them has, so a short take is faded through rather than skipped
+pub fn join_overlap(prev_frames: usize, next_frames: usize, fade_frames: usize) -> usize {
+    fade_frames.min(prev_frames).min(next_frames)
+}
+
+// join interleaved parts end to end with a linear crossfade at each join:
+// the last frames of one part fade out while the first of the next fade in
+// over the same span, so the result is shorter by the overlaps.
+// fade_frames = 0 is a plain concatenation
+pub fn crossfade_concat(parts: &[&[f32]], fade_frames: usize, channels: u16) -> Vec<f32> {
+    let ch = channels.max(1) as usize;
+    let mut out: Vec<f32> = Vec::with_capacity(parts.iter().map(|p| p.len()).sum());
+    let mut prev_frames = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let frames = part.len() / ch;
+        let overlap = if i == 0 { 0 } else { join_overlap(prev_frames, frames, fade_frames) };
+        let start = out.len() - overlap * ch;
+        for f in 0..overlap {
+            let t = (f + 1) as f32 / (overlap + 1) as f32; // 0 < t < 1, both sides always audible
+            for c in 0..ch {
+                let o = &mut out[start + f * ch + c];
+                *o = *o * (1.0 - t) + part[f * ch + c] * t;
+            }
+        }
+        out.extend_from_slice(&part[overlap * ch..]);
+        prev_frames = frames;
+    }
+    out
+}
+
+// ===== Time stretch =====
+//
+// WSOLA (waveform-similarity overlap-add): the take is cut into 40ms
+// Hann-windowed frames that are laid down every 20ms in the output, so 50%
+// overlap sums back to unity. each frame is read from where the stretched
+// timeline says (output position / factor), nudged by up to ±10ms to the
+// spot that best lines up with how the previous frame carries on, which
+// keeps the waveform continuous and so the pitch untouched.
+//
+// artifacts: a slight phasiness/echo on long stretches (>1.5x), repeated or
+// skipped fragments of transients (plosives, clicks) and some smearing of
+// fast consonants. fine for nudging narration to length, not for music
+
+const STRETCH_FRAME_MS: f32 = 40.0;
+const STRETCH_SEEK_MS: f32 = 10.0;
+const STRETCH_CORR_STEP: usize = 4; // correlate every 4th sample, plenty for speech
+
+// interleaved in, interleaved out, about `factor` times as many frames
+// (2.0 = twice as long). channels share the frame positions (found on
+// their mix) so stereo doesn't drift apart. None when the take is shorter
+// than one analysis frame, there's nothing to overlap
+pub fn time_stretch(samples: &[f32], channels: u16, sample_rate: u32, factor: f32) -> Option<Vec<f32>> {
+    let ch = channels.max(1) as usize;
+    let in_frames = samples.len() / ch;
+    let out_frames = (in_frames as f64 * factor as f64).round() as usize;
+    let n = ((STRETCH_FRAME_MS / 1000.0 * sample_rate as f32) as usize).max(4) & !1;
+    if in_frames < n { return None; }
+    if factor == 1.0 { return Some(samples.to_vec()); }
+    let hop = n / 2;
+    let seek = (STRETCH_SEEK_MS / 1000.0 * sample_rate as f32) as usize;
+    let window: Vec<f32> = (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos())
+        .collect();
+    let mono: Vec<f32> = samples.chunks_exact(ch).map(|f| f.iter().sum::<f32>() / ch as f32).collect();
+    let last = in_frames - n; // furthest a frame can start
+
+    let mut out = vec![0.0f32; (out_frames + n) * ch];
+    let mut norm = vec![0.0f32; out_frames + n];
+    let mut prev: Option<usize> = None;
+    let mut k = 0;
+    while k * hop < out_frames {
+        let nominal = ((k * hop) as f64 / factor as f64).round() as usize;
+        let pos = match prev {
+            None => 0,
+            Some(p) => {
+                // what would naturally follow the previous frame
+                let target = (p + hop).min(last);
+                let (lo, hi) = (nominal.saturating_sub(seek).min(last), (nominal + seek).min(last));
+                let corr = |x: usize| (0..n).step_by(STRETCH_CORR_STEP).map(|i| mono[x + i] * mono[target + i]).sum::<f32>();
+                (lo..=hi).map(|x| (corr(x), x))
+                    .max_by(|a, b| a.0.total_cmp(&b.0))
+                    .map_or(nominal.min(last), |(_, x)| x)
+            }
+        };
+        let at = k * hop;
+        for i in 0..n {
+            let w = window[i];
+            norm[at + i] += w;
+            for c in 0..ch {
+                out[(at + i) * ch + c] += samples[(pos + i) * ch + c] * w;
+            }
+        }
+        prev = Some(pos);
+        k += 1;
+    }
+    out.truncate(out_frames * ch);
+    // the first and last half-frame have no overlap partner, undo their taper
+    for (f, frame) in out.chunks_exact_mut(ch).enumerate() {
+        if norm[f] > 1e-3 { frame.iter_mut().for_each(|s| *s /= norm[f]); }
+    }
+    Some(out)
+}
+
+// ===== Pitch shift =====
+//
+// resample-then-stretch: time_stretch the take by the pitch ratio
+// (2^(semitones/12)), then resample it back to the original length, which
+// raises or lowers every frequency by that ratio. cheap and fine for a
+// backing voice, but it inherits the WSOLA artifacts (a little phasiness,
+// more the further it shifts) and formants move with the pitch, so a big
+// shift up sounds smaller-voiced rather than the same voice sung higher.
+// a phase vocoder with formant correction would do better
+
+// None, like time_stretch, for a take too short to stretch
+pub fn pitch_shift(samples: &[f32], channels: u16, sample_rate: u32, semitones: f32) -> Option<Vec<f32>> {
+    let ch = channels.max(1) as usize;
+    let frames = samples.len() / ch;
+    let ratio = 2f32.powf(semitones / 12.0);
+    let stretched = time_stretch(samples, channels, sample_rate, ratio)?;
+    if semitones == 0.0 { return Some(stretched); }
+    // reading the stretched take `ratio` times faster: from rate*ratio to rate
+    let from = (sample_rate as f64 * ratio as f64).round() as u32;
+    let mut shifted = resample_interleaved(&stretched, channels, from, sample_rate);
+    // rounding in both steps can leave it a few frames off, the length must not change
+    shifted.resize(frames * ch, 0.0);
+    Some(shifted)
+}
+
+// ===== Boundary fades =====
+
+// short linear fades at the joins, a cheaper fix than crossfade_concat for
+// the click where one take's last sample and the next one's first don't
+// meet: the part ramps from/to zero over `fade_frames` at whichever ends
+// are joined to a neighbour. nothing overlaps, so the length is unchanged
+pub fn fade_edges(samples: &mut [f32], channels: u16, fade_frames: usize, fade_in: bool, fade_out: bool) {
+    let ch = channels.max(1) as usize;
+    let frames = samples.len() / ch;
+    let n = fade_frames.min(frames);
+    if n == 0 { return; }
+    for f in 0..n {
+        let gain = f as f32 / n as f32; // 0 right at the boundary
+        if fade_in {
+            samples[f * ch..(f + 1) * ch].iter_mut().for_each(|s| *s *= gain);
+        }
+        if fade_out {
+            let last = frames - 1 - f;
+            samples[last * ch..(last + 1) * ch].iter_mut().for_each(|s| *s *= gain);
+        }
+    }
+}
+
+// ===== Resampling =====
+
+// linear-interpolation sample-rate conversion. cheap and good enough for
+// speech, keeps duration (and so pitch) correct when rates differ
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+    let out_len = ((samples.len() as u64 * to_rate as u64) / from_rate as u64).max(1) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    (0..out_len).map(|i| {
+        let pos = i as f64 * step;
+        let idx = pos as usize;
+        let frac = (pos - idx as f64) as f32;
+        let a = samples[idx.min(samples.len() - 1)];
+        let b = samples[(idx + 1).min(samples.len() - 1)];
+        a + (b - a) * frac
+    }).collect()
+}
+
+// the same linear interpolation for audio arriving in buffers (the input
+// callback). resampling each buffer on its own would restart the timing at
+// every boundary (a click, and rounding that drifts the length), so this
+// carries the fractional read position and the last frame across calls
+pub struct StreamResampler {
+    channels: usize,
+    step: f64,      // input frames per output frame
+    pos: f64,       // next output position in input frames, -1.0 = the carried frame
+    prev: Vec<f32>, // last frame of the previous buffer
+}
+
+impl StreamResampler {
+    pub fn new(from_rate: u32, to_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self { channels, step: from_rate as f64 / to_rate.max(1) as f64, pos: 0.0, prev: vec![0.0; channels] }
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let ch = self.channels;
+        let frames = input.len() / ch;
+        if frames == 0 { return Vec::new(); }
+        let at = |i: isize, c: usize| if i < 0 { self.prev[c] } else { input[i as usize * ch + c] };
+        let mut out = Vec::with_capacity(((frames as f64 / self.step) as usize + 1) * ch);
+        while self.pos < (frames - 1) as f64 {
+            let idx = self.pos.floor();
+            let frac = (self.pos - idx) as f32;
+            for c in 0..ch {
+                let (a, b) = (at(idx as isize, c), at(idx as isize + 1, c));
+                out.push(a + (b - a) * frac);
+            }
+            self.pos += self.step;
+        }
+        self.pos -= frames as f64;
+        self.prev.copy_from_slice(&input[(frames - 1) * ch..frames * ch]);
+        out
+    }
+}
+
+// resample works on a single channel, so split, resample, re-interleave
+pub fn resample_interleaved(samples: &[f32], channels: u16, from: u32, to: u32) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    if channels == 1 {
+        return resample(samples, from, to);
+    }
+    let planes: Vec<Vec<f32>> = (0..channels)
+        .map(|c| {
+            let plane: Vec<f32> = samples.iter().skip(c).step_by(channels).copied().collect();
+            resample(&plane, from, to)
+        })
+        .collect();
+    let frames = planes.iter().map(|p| p.len()).min().unwrap_or(0);
+    (0..frames).flat_map(|i| planes.iter().map(move |p| p[i])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pan_is_equal_power() {
+        let (l, r) = pan_gains(0.0);
+        assert!((l - 1.0).abs() < 1e-6 && (r - 1.0).abs() < 1e-6); // centre untouched
+        for pan in [-1.0, -0.5, 0.25, 1.0] {
+            let (l, r) = pan_gains(pan);
+            assert!((l * l + r * r - 2.0).abs() < 1e-5); // same power everywhere
+        }
+        let mut frames = vec![0.5, 0.5, 0.2, 0.2];
+        pan_stereo(&mut frames, -1.0);
+        assert!((frames[0] - 0.5 * std::f32::consts::SQRT_2).abs() < 1e-6);
+        assert!(frames[1].abs() < 1e-6 && frames[3].abs() < 1e-6); // right is silent
+    }
+
+    const SR: u32 = 48000;
+    const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    fn close(a: f32, b: f32, tol: f32) -> bool { (a - b).abs() < tol }
+
+    #[test]
+    fn test_noise_gate_closes_on_hum_and_opens_on_speech() {
+        let mut gate = NoiseGate::new(0.05, 8000);
+        // a second of low hum: passes during the hold, then fades to nothing
+        let mut hum: Vec<f32> = (0..8000).map(|i| 0.02 * (i as f32 * 0.1).sin()).collect();
+        gate.process_interleaved(&mut hum, 1);
+        assert!(hum[..100].iter().any(|s| s.abs() > 0.01));
+        assert!(hum[4000..].iter().all(|s| s.abs() < 1e-4));
+
+        // speech opens it again within a couple of milliseconds
+        let mut speech = vec![0.5_f32; 400];
+        gate.process_interleaved(&mut speech, 1);
+        assert!(speech[40..].iter().all(|&s| s > 0.49));
+
+        // stereo frames share one gain
+        let mut stereo: Vec<f32> = [0.5, 0.0].repeat(100);
+        gate.process_interleaved(&mut stereo, 2);
+        assert!(stereo.chunks(2).all(|f| f[1] == 0.0 && f[0] > 0.49));
+    }
+
+    #[test]
+    fn test_pitch_shift_an_octave_doubles_frequency() {
+        let rate = 8000;
+        let tone: Vec<f32> = (0..rate).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / rate as f32).sin()).collect();
+        let rising = |s: &[f32]| s.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+
+        let up = pitch_shift(&tone, 1, rate, 12.0).unwrap();
+        assert_eq!(up.len(), tone.len()); // same duration
+        let crossings = rising(&up[1000..7000]); // 0.75s, away from the edges
+        assert!((325..=335).contains(&crossings), "{} crossings, 330 expected", crossings);
+
+        let down = pitch_shift(&tone, 1, rate, -12.0).unwrap();
+        assert_eq!(down.len(), tone.len());
+        let crossings = rising(&down[1000..7000]);
+        assert!((80..=85).contains(&crossings), "{} crossings, 82 expected", crossings);
+    }
+
+    #[test]
+    fn test_fade_edges_ramp_to_zero_at_the_joins() {
+        let mut stereo = vec![1.0_f32; 20]; // 10 frames
+        fade_edges(&mut stereo, 2, 4, true, true);
+        assert_eq!(&stereo[..8], &[0.0, 0.0, 0.25, 0.25, 0.5, 0.5, 0.75, 0.75]);
+        assert_eq!(&stereo[12..], &[0.75, 0.75, 0.5, 0.5, 0.25, 0.25, 0.0, 0.0]);
+        assert_eq!(&stereo[8..12], &[1.0; 4]); // middle untouched
+
+        let mut first = vec![1.0_f32; 6];
+        fade_edges(&mut first, 1, 3, false, true); // the project's first take keeps its start
+        assert_eq!(first, vec![1.0, 1.0, 1.0, 2.0 / 3.0, 1.0 / 3.0, 0.0]);
+    }
+
+    #[test]
+    fn test_level_stats() {
+        let stats = LevelStats::of(&[0.5, -0.5, 0.5, -0.5]);
+        assert_eq!(stats, LevelStats { peak: 0.5, rms: 0.5, samples: 4 });
+        assert!(close(linear_to_db(stats.peak).unwrap(), -6.02, 0.01));
+        // empty and silent buffers stay finite
+        assert_eq!(LevelStats::of(&[]), LevelStats::default());
+        assert_eq!(LevelStats::of(&[0.0; 8]).rms, 0.0);
+        assert_eq!(linear_to_db(0.0), None);
+    }
+
+    #[test]
+    fn test_time_stretch_keeps_pitch() {
+        let rate = 8000;
+        let tone: Vec<f32> = (0..rate).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / rate as f32).sin()).collect();
+        let long = time_stretch(&tone, 1, rate, 2.0).unwrap();
+        assert_eq!(long.len(), 16000);
+        let short = time_stretch(&tone, 1, rate, 0.5).unwrap();
+        assert_eq!(short.len(), 4000);
+
+        // still a 200Hz tone: count rising zero crossings over the middle second
+        let crossings = long[4000..12000].windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert!((195..=205).contains(&crossings), "{} crossings", crossings);
+        assert!(close(rms(&long[4000..12000]), rms(&tone), 0.05)); // level kept too
+
+        let stereo: Vec<f32> = tone.iter().flat_map(|&s| [s, -s]).collect();
+        let stretched = time_stretch(&stereo, 2, rate, 1.5).unwrap();
+        assert_eq!(stretched.len(), 2 * 12000);
+        assert!(stretched.chunks(2).all(|f| close(f[0], -f[1], 1e-6))); // channels stay aligned
+
+        assert!(time_stretch(&tone[..100], 1, rate, 2.0).is_none()); // under one 40ms frame
+        assert!(pitch_shift(&tone[..100], 1, rate, 2.0).is_none());
+    }
+
+    #[test]
+    fn test_resample_ramp() {
+        let ramp: Vec<f32> = (0..480).map(|i| i as f32 / 480.0).collect();
+
+        // 48k -> 44.1k keeps the duration: 10ms in, 10ms out
+        let down = resample(&ramp, 48000, 44100);
+        assert_eq!(down.len(), 441);
+        // a ramp stays a ramp, each output sample sits where its time says
+        for (i, &s) in down.iter().enumerate() {
+            assert!(close(s, i as f32 * 48000.0 / 44100.0 / 480.0, 1e-4), "sample {}", i);
+        }
+        assert_eq!(resample(&ramp, 22050, 44100).len(), 960);
+
+        // stereo channels are resampled apart, never into each other
+        let stereo: Vec<f32> = (0..200).flat_map(|_| [0.5, -0.5]).collect();
+        let out = resample_interleaved(&stereo, 2, 8000, 16000);
+        assert_eq!(out.len(), 800);
+        assert!(out.chunks(2).all(|f| f == [0.5, -0.5]));
+    }
+
+    #[test]
+    fn test_stream_resampler_matches_whole_buffer() {
+        let ramp: Vec<f32> = (0..4800).map(|i| i as f32 / 4800.0).collect();
+        let mut stream = StreamResampler::new(48000, 44100, 1);
+        // odd buffer sizes, the joins must not show
+        let out: Vec<f32> = ramp.chunks(333).flat_map(|b| stream.process(b)).collect();
+        assert!((out.len() as i64 - 4410).abs() <= 1, "{} samples", out.len());
+        for (i, &s) in out.iter().enumerate() {
+            assert!(close(s, i as f32 * 48000.0 / 44100.0 / 4800.0, 1e-4), "sample {}", i);
+        }
+
+        // upsampling stereo keeps the channels apart
+        let mut stream = StreamResampler::new(8000, 16000, 2);
+        let out: Vec<f32> = [0.5, -0.5].repeat(100).chunks(30).flat_map(|b| stream.process(b)).collect();
+        assert!(out.len() >= 396 && out.chunks(2).all(|f| f == [0.5, -0.5]));
+    }
+
+    #[test]
+    fn test_crossfade_concat() {
+        let (a, b) = ([1.0_f32; 10], [0.0_f32; 10]);
+        // 3-frame fade: 10 + 10 - 3 samples, ramp 0.75 / 0.5 / 0.25 between them
+        let out = crossfade_concat(&[&a, &b], 3, 1);
+        assert_eq!(out.len(), 17);
+        assert_eq!(&out[6..11], &[1.0, 0.75, 0.5, 0.25, 0.0]);
+
+        // no fade is plain concatenation
+        assert_eq!(crossfade_concat(&[&a, &b], 0, 1).len(), 20);
+
+        // stereo fades frame by frame, a short part limits the overlap
+        let out = crossfade_concat(&[&[1.0, -1.0, 1.0, -1.0], &[0.0, 0.0]], 4, 2);
+        assert_eq!(out, vec![1.0, -1.0, 0.5, -0.5]);
+        assert_eq!(join_overlap(2, 1, 4), 1);
+    }
+
+    #[test]
+    fn test_sine_tone_peak_matches_level() {
+        for db in [-18.0, -6.0, 0.0] {
+            let tone = sine_tone(REFERENCE_TONE_HZ, db, 1.0, SR);
+            assert_eq!(tone.len(), SR as usize);
+            let peak = tone.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+            assert!(close(20.0 * peak.log10(), db, 0.01), "{} dB asked, peak {}", db, peak);
+        }
+    }
+
+    #[test]
+    fn test_declick_repairs_a_spike_in_a_sine() {
+        let clean: Vec<f32> = (0..SR as usize / 10)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / SR as f32).sin())
+            .collect();
+        let mut x = clean.clone();
+        x[1000] += 0.8;
+
+        assert_eq!(declick(&mut x, 1.0), 1);
+        let worst = x.iter().zip(&clean).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(worst < 0.01, "residual {}", worst);
+        // nothing left to find, and a clean sine is never touched
+        assert_eq!(declick(&mut x, 1.0), 0);
+        let mut untouched = clean.clone();
+        assert_eq!(declick(&mut untouched, 1.0), 0);
+        assert_eq!(untouched, clean);
+    }
+
+    #[test]
+    fn test_limiter_keeps_peaks_under_ceiling_smoothly() {
+        // +3dB sine, would clip by ~40%
+        let amp = db_to_linear(3.0);
+        let x: Vec<f32> = (0..SR as usize / 4)
+            .map(|i| amp * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / SR as f32).sin())
+            .collect();
+        let y = limit(&x, 1, SR, 1.0);
+
+        assert_eq!(y.len(), x.len());
+        assert!(y.iter().all(|s| s.abs() <= 1.0));
+        // no flat tops or jumps: the output never moves faster than the input
+        let max_step = |v: &[f32]| v.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max);
+        assert!(max_step(&y) <= max_step(&x));
+        // quiet material passes through untouched
+        let quiet: Vec<f32> = x.iter().map(|s| s * 0.5).collect();
+        assert_eq!(limit(&quiet, 1, SR, 1.0), quiet);
+    }
+
+    // steady-state peak after running a sine through the filter
+    fn sine_gain(mut f: Biquad, freq: f32) -> f32 {
+        let n = SR as usize / 2;
+        let out: Vec<f32> = (0..n)
+            .map(|i| f.process((2.0 * std::f32::consts::PI * freq * i as f32 / SR as f32).sin()))
+            .collect();
+        out[n / 2..].iter().fold(0.0, |m, s| m.max(s.abs()))
+    }
+
+    #[test]
+    fn test_lowpass_response() {
+        let f = Biquad::lowpass(SR, 1000.0, BUTTERWORTH_Q);
+        assert!(close(f.magnitude(10.0, SR), 1.0, 0.01));
+        assert!(close(f.magnitude(1000.0, SR), BUTTERWORTH_Q, 0.01)); // -3dB at cutoff
+        assert!(f.magnitude(10000.0, SR) < 0.02);
+        assert!(close(sine_gain(f, 100.0), 1.0, 0.02));
+    }
+
+    #[test]
+    fn test_highpass_response() {
+        let f = Biquad::highpass(SR, 100.0, BUTTERWORTH_Q);
+        assert!(f.magnitude(10.0, SR) < 0.02);
+        assert!(close(f.magnitude(100.0, SR), BUTTERWORTH_Q, 0.01));
+        assert!(close(f.magnitude(5000.0, SR), 1.0, 0.01));
+        assert!(sine_gain(f, 10.0) < 0.05);
+    }
+
+    #[test]
+    fn test_notch_response() {
+        let f = Biquad::notch(SR, 60.0, 10.0);
+        assert!(f.magnitude(60.0, SR) < 1e-3);
+        assert!(close(f.magnitude(1000.0, SR), 1.0, 0.01));
+    }
+
+    #[test]
+    fn test_reverb_impulse_decays() {
+        let mut rv = Reverb::new(SR, 0.5, 1.0);
+        let n = rv.tail_samples();
+        let out: Vec<f32> = (0..n).map(|i| rv.process(if i == 0 { 1.0 } else { 0.0 })).collect();
+        let energy = |r: std::ops::Range<usize>| out[r].iter().map(|s| s * s).sum::<f32>();
+        // more than one non-zero sample: the impulse became a tail
+        assert!(out.iter().filter(|s| s.abs() > 1e-6).count() > 100);
+        // and that tail dies away
+        assert!(energy(0..n / 4) > energy(n / 4..n / 2));
+        assert!(energy(n / 4..n / 2) > energy(3 * n / 4..n));
+    }
+
+    #[test]
+    fn test_delay_impulse_repeats() {
+        // 10ms at 48kHz = 480 samples between repeats
+        let mut d = Delay::new(SR, 10.0, 0.5, 0.5);
+        let out: Vec<f32> = (0..2000).map(|i| d.process(if i == 0 { 1.0 } else { 0.0 })).collect();
+        let hits: Vec<usize> = (0..out.len()).filter(|&i| out[i].abs() > 1e-6).collect();
+        assert_eq!(hits, vec![0, 480, 960, 1440, 1920]);
+        assert!(close(out[480], 0.5, 1e-6));
+        assert!(close(out[960], 0.25, 1e-6));
+        assert!(out[1440] < out[960] && out[1920] < out[1440]);
+
+        // runaway feedback is clamped
+        assert!(Delay::new(SR, 10.0, 5.0, 1.0).feedback < 1.0);
+
+        // so is the time, an endless one would need an endless buffer
+        for time in [f32::INFINITY, 1e30, f32::NAN] {
+            let d = Delay::new(SR, time, 0.5, 0.5);
+            assert!(d.buf.len() <= (MAX_DELAY_MS / 1000.0 * SR as f32) as usize);
+            assert!(d.tail_samples() >= d.buf.len()); // the first echo is in the tail
+        }
+        assert!(!delay_time_ok(f32::INFINITY) && !delay_time_ok(MAX_DELAY_MS + 1.0) && !delay_time_ok(0.0));
+    }
+
+    #[test]
+    fn test_block_processing_matches_whole_buffer() {
+        let input: Vec<f32> = (0..10_000).map(|i| ((i * 7919) % 201) as f32 / 100.0 - 1.0).collect();
+
+        let mut whole = Biquad::highpass(SR, 150.0, BUTTERWORTH_Q);
+        let expected: Vec<f32> = input.iter().map(|&x| whole.process(x)).collect();
+        for block in [1, 64, 333, 4096, 100_000] {
+            let mut out = input.clone();
+            process_in_blocks(&mut [Biquad::highpass(SR, 150.0, BUTTERWORTH_Q)], &mut out, block);
+            assert_eq!(out, expected, "block size {}", block);
+        }
+
+        // effects with a tail get it rendered block by block too
+        let mut whole = Delay::new(SR, 5.0, 0.5, 0.5);
+        let tail = whole.tail_samples();
+        let expected: Vec<f32> = input.iter().copied().chain(std::iter::repeat_n(0.0, tail))
+            .map(|x| whole.process(x)).collect();
+        let mut out = input.clone();
+        process_in_blocks(&mut [Delay::new(SR, 5.0, 0.5, 0.5)], &mut out, 500);
+        assert_eq!(out, expected);
+
+        // stereo: each channel through its own processor, tails interleaved
+        let stereo: Vec<f32> = input.iter().flat_map(|&x| [x, -x]).collect();
+        let mut right = Delay::new(SR, 5.0, 0.5, 0.5);
+        let mirrored: Vec<f32> = input.iter().map(|&x| -x).chain(std::iter::repeat_n(0.0, tail))
+            .map(|x| right.process(x)).collect();
+        for block in [1, 333, 100_000] {
+            let mut out = stereo.clone();
+            EffectSpec::Delay(5.0, 0.5, 0.5).apply(&mut out, SR, 2, block);
+            let lanes: (Vec<f32>, Vec<f32>) = out.chunks(2).map(|f| (f[0], f[1])).unzip();
+            assert_eq!(lanes.0, expected, "block size {}", block);
+            assert_eq!(lanes.1, mirrored, "block size {}", block);
+        }
+    }
+
+    #[test]
+    fn test_peaking_response() {
+        let f = Biquad::peaking(SR, 2000.0, 1.0, 6.0);
+        let db = |m: f32| 20.0 * m.log10();
+        assert!(close(db(f.magnitude(2000.0, SR)), 6.0, 0.05));
+        assert!(close(db(f.magnitude(20.0, SR)), 0.0, 0.1));
+    }
+}
@@ -1,26 +1,586 @@
-use hound; // write wav files
-use crate::state::Project;
+use crate::dsp;
+use crate::state::{ms_to_samples, Project, ProjectMetadata, Segment};
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
 
 // iterate through every segment in the project
 // then convert the internal f32 samples to i16 (standard WAV format)
 // Write them sequentially to output.wav, which "appends" every chunk into one file
 
-pub fn export_wav(project: &Project, path: &str) {
+// sample format of the written WAV
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Int16,
+    Int24,
+    Float32, // the f32 samples as-is, overs above 1.0 survive
+}
+
+impl ExportFormat {
+    // `16`, `24` or `float`/`32`, as typed after `e`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "16" => Some(Self::Int16),
+            "24" => Some(Self::Int24),
+            "32" | "float" => Some(Self::Float32),
+            _ => None,
+        }
+    }
+
+    fn spec(self) -> (u16, hound::SampleFormat) {
+        match self {
+            Self::Int16 => (16, hound::SampleFormat::Int),
+            Self::Int24 => (24, hound::SampleFormat::Int),
+            Self::Float32 => (32, hound::SampleFormat::Float),
+        }
+    }
+}
+
+// largest 24-bit sample, what full scale maps to
+const I24_MAX: f32 = 8_388_607.0;
+
+// clamp to [-1, 1] and scale to an integer format whose largest value is
+// `max`. out-of-range input saturates instead of wrapping around, and the
+// negative side reaches the format's minimum (-max - 1) like the positive
+// side reaches max
+fn to_int_scale(sample: f32, max: f32) -> f32 {
+    let clamped = sample.clamp(-1.0, 1.0);
+    if clamped < 0.0 { clamped * (max + 1.0) } else { clamped * max }
+}
+
+// one sample of an integer format: clamped, scaled and, for 16-bit with
+// an rng, TPDF-dithered before rounding. Float32 isn't an integer format,
+// callers map it to 24-bit
+fn to_int_sample(sample: f32, format: ExportFormat, rng: Option<&mut DitherRng>) -> i32 {
+    match format {
+        ExportFormat::Int16 => {
+            let scaled = to_int_scale(sample, i16::MAX as f32);
+            match rng {
+                Some(rng) => (scaled + rng.tpdf()).round() as i16 as i32,
+                None => scaled as i16 as i32,
+            }
+        }
+        ExportFormat::Int24 | ExportFormat::Float32 => to_int_scale(sample, I24_MAX).round() as i32,
+    }
+}
+
+// how samples are turned into the file, Default is plain 16-bit truncation
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub dither: bool, // add TPDF noise before quantizing (see DitherRng)
+    // fixed seed = byte-identical exports of the same project,
+    // None = seeded from entropy so every export's noise differs
+    pub dither_seed: Option<u64>,
+    pub limit: bool, // per-segment look-ahead limiter so overs don't hit the clamp
+    // overlap each segment with the next by this much, linear fades (see
+    // dsp::crossfade_concat). 0 = hard cuts. `pa` plays the same joins
+    pub crossfade_ms: f32,
+    // a few ms of fade out/in either side of every join between segments,
+    // no overlap (see dsp::fade_edges). 0 = off, ignored under a crossfade
+    pub boundary_fade_ms: f32,
+}
+
+// a bad path or a full disk comes back as an error, the project is untouched
+pub fn export_wav(project: &Project, path: &str, opts: &ExportOptions) -> hound::Result<()> {
+    write_wav(project, path, project.segments.iter(), opts)?;
+    write_info_chunk(path, &project.metadata)?;
+    Ok(())
+}
+
+// segments start..=end (0-based) as one file, the rest of the project is
+// left out. an error when the range is backwards or runs past the end
+pub fn export_wav_range(project: &Project, path: &str, start: usize, end: usize, opts: &ExportOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let segments = project.segments.get(start..=end).filter(|_| start <= end)
+        .ok_or_else(|| format!("segments {}-{} aren't in the project ({} segments)", start + 1, end + 1, project.segments.len()))?;
+    write_wav(project, path, segments.iter(), opts)?;
+    write_info_chunk(path, &project.metadata)?;
+    Ok(())
+}
+
+// hound can't write tags, so the LIST/INFO chunk is appended to the finished
+// file and the RIFF size patched to cover it. readers that don't know the
+// chunk skip it. each tag is a NUL-terminated string padded to an even length
+fn write_info_chunk<P: AsRef<Path>>(path: P, metadata: &ProjectMetadata) -> std::io::Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+    let tags = metadata.info_tags();
+    if tags.is_empty() { return Ok(()); }
+
+    let mut info = b"INFO".to_vec();
+    for (id, text) in tags {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        info.extend_from_slice(id);
+        info.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        info.extend_from_slice(&data);
+        if data.len() % 2 == 1 { info.push(0); }
+    }
+
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    let end = file.seek(SeekFrom::End(0))?;
+    if end % 2 == 1 { file.write_all(&[0])?; } // chunks start on even offsets
+    file.write_all(b"LIST")?;
+    file.write_all(&(info.len() as u32).to_le_bytes())?;
+    file.write_all(&info)?;
+    let riff_size = file.seek(SeekFrom::End(0))? - 8;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(riff_size as u32).to_le_bytes())?;
+    Ok(())
+}
+
+// shared by the combined export and the per-segment files of a package
+fn write_wav<'a, P: AsRef<Path>>(
+    project: &Project,
+    path: P,
+    segments: impl Iterator<Item = &'a Segment>,
+    opts: &ExportOptions,
+) -> hound::Result<()> {
+    let (bits_per_sample, sample_format) = opts.format.spec();
     let spec = hound::WavSpec {
         channels: project.channels,
         sample_rate: project.sample_rate,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
+        bits_per_sample,
+        sample_format,
     };
 
-    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    // a 24-bit LSB is already far below the noise floor, dither is for 16-bit
+    let mut rng = (opts.dither && opts.format == ExportFormat::Int16).then(|| DitherRng::new(opts.dither_seed));
 
-    for seg in &project.segments {
-        for &sample in &seg.samples {
-            let s = (sample * i16::MAX as f32) as i16;
-            writer.write_sample(s).unwrap();
+    for chunk in render_chunks(project, segments, opts) {
+        for &sample in chunk.iter() {
+            // gain can push past full scale, the int formats clip rather than wrap
+            match opts.format {
+                ExportFormat::Int16 => writer.write_sample(to_int_sample(sample, opts.format, rng.as_mut()) as i16)?,
+                ExportFormat::Int24 => writer.write_sample(to_int_sample(sample, opts.format, None))?,
+                ExportFormat::Float32 => writer.write_sample(sample)?,
+            }
         }
     }
 
-    writer.finalize().unwrap();
+    writer.finalize()
+}
+
+// the segments as they end up in a file: muted ones left out, gain and pan
+// applied, faded or crossfaded at the joins and limited per chunk when asked to
+fn render_chunks<'a>(project: &Project, segments: impl Iterator<Item = &'a Segment>, opts: &ExportOptions) -> Vec<Cow<'a, [f32]>> {
+    let mut chunks: Vec<Cow<[f32]>> = segments.filter(|s| !s.muted).map(|s| s.heard(project.channels)).collect();
+    let fade = ms_to_samples(opts.crossfade_ms, project.sample_rate);
+    let boundary = ms_to_samples(opts.boundary_fade_ms, project.sample_rate);
+    if fade == 0 && boundary > 0 {
+        let last = chunks.len().saturating_sub(1);
+        for (i, chunk) in chunks.iter_mut().enumerate() {
+            if last > 0 { dsp::fade_edges(chunk.to_mut(), project.channels, boundary, i > 0, i < last); }
+        }
+    }
+    if fade > 0 && chunks.len() > 1 {
+        // the joins need both sides at once, so the whole export becomes one chunk
+        let parts: Vec<&[f32]> = chunks.iter().map(|c| &c[..]).collect();
+        chunks = vec![Cow::Owned(dsp::crossfade_concat(&parts, fade, project.channels))];
+    }
+    if opts.limit {
+        for chunk in &mut chunks {
+            *chunk = Cow::Owned(dsp::limit(chunk, project.channels, project.sample_rate, 1.0));
+        }
+    }
+    chunks
+}
+
+// the whole project as FLAC, lossless at 16 or 24 bits (float exports are
+// written as 24-bit, FLAC has no float samples). same channels, rate, gain,
+// crossfade, limiter and dither as the WAV export
+#[cfg(feature = "flac")]
+pub fn export_flac(project: &Project, path: &str, opts: &ExportOptions) -> std::io::Result<()> {
+    let format = if opts.format == ExportFormat::Int16 { ExportFormat::Int16 } else { ExportFormat::Int24 };
+    let mut rng = (opts.dither && format == ExportFormat::Int16).then(|| DitherRng::new(opts.dither_seed));
+    let pcm: Vec<i32> = render_chunks(project, project.segments.iter(), opts).iter()
+        .flat_map(|chunk| chunk.iter())
+        .map(|&sample| to_int_sample(sample, format, rng.as_mut()))
+        .collect();
+    let bits = if format == ExportFormat::Int16 { 16 } else { 24 };
+    std::fs::write(path, crate::flac::encode(&pcm, project.channels, project.sample_rate, bits))
+}
+
+// splitmix64: tiny, seedable and plenty random enough for dither noise
+struct DitherRng(u64);
+
+impl DitherRng {
+    fn new(seed: Option<u64>) -> Self {
+        // RandomState is seeded from OS entropy, a cheap way to get some without a crate
+        let seed = seed.unwrap_or_else(|| std::collections::hash_map::RandomState::new().build_hasher().finish());
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // triangular PDF in (-1, 1) LSB: the difference of two uniforms
+    fn tpdf(&mut self) -> f32 {
+        self.next_f32() - self.next_f32()
+    }
+}
+
+// delivery bundle: everything a downstream tool needs in one directory
+//   <dir>/combined.wav       the whole project, same as `e`
+//   <dir>/segment_NNN.wav    one file per segment
+//   <dir>/manifest.json      labels, files, offsets and durations in seconds
+// returns the manifest path
+pub fn export_package(project: &Project, dir: &str, opts: &ExportOptions) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = Path::new(dir);
+    std::fs::create_dir_all(dir)?;
+
+    let combined = dir.join("combined.wav");
+    write_wav(project, &combined, project.segments.iter(), opts)?;
+    write_info_chunk(&combined, &project.metadata)?; // tagged like an `e` export
+
+    let frames_per_sec = (project.sample_rate * project.channels.max(1) as u32) as f64;
+    let mut entries = Vec::new();
+    let (ch, fade) = (project.channels.max(1) as usize, ms_to_samples(opts.crossfade_ms, project.sample_rate));
+    let mut offset = 0.0;
+    let mut prev: Option<&Segment> = None; // last segment in the combined file
+    // muted segments aren't in combined.wav, so they get no file or entry either
+    for (i, seg) in project.segments.iter().enumerate().filter(|(_, s)| !s.muted) {
+        let label = format!("segment_{:03}", i + 1);
+        let file = format!("{}.wav", label);
+        write_wav(project, dir.join(&file), std::iter::once(seg), opts)?;
+
+        // a crossfade starts this segment before the previous one has ended
+        if let Some(prev) = prev {
+            offset -= dsp::join_overlap(prev.samples.len() / ch, seg.samples.len() / ch, fade) as f64 * ch as f64 / frames_per_sec;
+        }
+        prev = Some(seg);
+        let duration = seg.samples.len() as f64 / frames_per_sec;
+        let history: Vec<String> = seg.history.iter().map(|h| json_string(h)).collect();
+        entries.push(format!(
+            "    {{\"index\": {}, \"label\": {}, \"file\": {}, \"offset\": {:.6}, \"duration\": {:.6}, \"history\": [{}]}}",
+            i + 1, json_string(&label), json_string(&file), offset, duration, history.join(", ")
+        ));
+        offset += duration;
+    }
+
+    // written by hand, the shape is fixed and small enough not to need a JSON crate
+    let mut manifest = String::from("{\n");
+    writeln!(manifest, "  \"sample_rate\": {},", project.sample_rate)?;
+    writeln!(manifest, "  \"channels\": {},", project.channels)?;
+    writeln!(manifest, "  \"combined\": \"combined.wav\",")?;
+    writeln!(manifest, "  \"duration\": {:.6},", offset)?;
+    writeln!(manifest, "  \"segments\": [\n{}\n  ]", entries.join(",\n"))?;
+    manifest.push_str("}\n");
+
+    let path = dir.join("manifest.json");
+    std::fs::write(&path, manifest)?;
+    Ok(path)
+}
+
+// one WAV per segment in `dir` (created if missing, muted segments skipped),
+// for editing takes individually. a named segment is written as `<name>.wav`, the rest as
+// `<prefix>_NNN.wav` by position. returns the files written, in order
+pub fn export_segments(project: &Project, dir: &str, prefix: &str, opts: &ExportOptions) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dir = Path::new(dir);
+    std::fs::create_dir_all(dir)?;
+
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (i, seg) in project.segments.iter().enumerate().filter(|(_, s)| !s.muted) {
+        let numbered = format!("{}_{:03}", prefix, i + 1);
+        let stem = match seg.name.as_deref().map(file_stem) {
+            // two takes with the same name keep their number so neither is overwritten
+            Some(name) if !name.is_empty() => {
+                if written.iter().any(|p| p.file_stem().is_some_and(|s| *s == *name)) {
+                    format!("{}_{:03}", name, i + 1)
+                } else { name }
+            }
+            _ => numbered,
+        };
+        let path = dir.join(format!("{}.wav", stem));
+        write_wav(project, &path, std::iter::once(seg), opts)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// a segment name made safe to use as a file name on any platform
+fn file_stem(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect::<String>()
+        .trim_matches('.')
+        .to_string()
+}
+
+// quote and escape a string for the manifest
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// first `<stem>_NNN.<ext>` that doesn't exist yet, so unattended
+// sessions never overwrite each other e.g. session_001.wav, session_002.wav
+pub fn next_versioned_path(stem: &str, ext: &str) -> String {
+    (1..)
+        .map(|n| format!("{}_{:03}.{}", stem, n, ext))
+        .find(|p| !std::path::Path::new(p).exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Segment;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn project(segments: Vec<Segment>, sample_rate: u32, channels: u16) -> Project {
+        Project { segments, sample_rate, channels, editing_index: None, metadata: Default::default() }
+    }
+
+    // export_wav through a temp file of its own (tests run in parallel), the file's bytes back
+    fn export_bytes(project: &Project, opts: &ExportOptions) -> Vec<u8> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("pos_export_{}_{}.wav", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+        export_wav(project, path.to_str().unwrap(), opts).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        bytes
+    }
+
+    fn read_wav(bytes: Vec<u8>) -> hound::WavReader<std::io::Cursor<Vec<u8>>> {
+        hound::WavReader::new(std::io::Cursor::new(bytes)).unwrap()
+    }
+
+    fn export_samples<S: hound::Sample>(project: &Project, opts: &ExportOptions) -> Vec<S> {
+        read_wav(export_bytes(project, opts)).samples().map(|s| s.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_export_package_writes_files_and_manifest() {
+        let dir = std::env::temp_dir().join(format!("pos_package_{}", std::process::id()));
+        let mut first = Segment::new(vec![0.1; 500]); // 0.5s at 1kHz
+        first.log("said \"hello\"".into());
+        let mut project = project(vec![first, Segment::new(vec![0.2; 250])], 1000, 1);
+        project.metadata.title = Some("Chapter 1".into());
+
+        let manifest_path = export_package(&project, dir.to_str().unwrap(), &ExportOptions::default()).unwrap();
+        assert_eq!(manifest_path, dir.join("manifest.json"));
+        for file in ["combined.wav", "segment_001.wav", "segment_002.wav"] {
+            assert!(dir.join(file).exists(), "missing {}", file);
+        }
+        assert_eq!(hound::WavReader::open(dir.join("segment_002.wav")).unwrap().duration(), 250);
+        assert_eq!(hound::WavReader::open(dir.join("combined.wav")).unwrap().duration(), 750);
+        let combined = std::fs::read(dir.join("combined.wav")).unwrap();
+        let tag = b"INAM\x0a\0\0\0Chapter 1\0";
+        assert!(combined.windows(tag.len()).any(|w| w == tag));
+
+        let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(manifest.contains("\"combined\": \"combined.wav\""));
+        assert!(manifest.contains("\"file\": \"segment_001.wav\", \"offset\": 0.000000, \"duration\": 0.500000"));
+        assert!(manifest.contains("\"file\": \"segment_002.wav\", \"offset\": 0.500000, \"duration\": 0.250000"));
+        assert!(manifest.contains("\"duration\": 0.750000,"));
+        assert!(manifest.contains(r#"["said \"hello\""]"#));
+    }
+
+    #[test]
+    fn test_export_segments_uses_names() {
+        let dir = std::env::temp_dir().join(format!("pos_segments_{}", std::process::id()));
+        let mut named = Segment::new(vec![0.1; 10]);
+        named.name = Some("intro / take 2".into());
+        let mut same = Segment::new(vec![0.1; 10]);
+        same.name = named.name.clone();
+        let project = project(vec![named, Segment::new(vec![0.2; 20]), same], 1000, 1);
+
+        let files = export_segments(&project, dir.to_str().unwrap(), "take", &ExportOptions::default()).unwrap();
+        let names: Vec<_> = files.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(names, vec!["intro___take_2.wav", "take_002.wav", "intro___take_2_003.wav"]);
+        assert_eq!(hound::WavReader::open(&files[1]).unwrap().duration(), 20);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_dither_is_reproducible_and_16_bit_only() {
+        // a quiet fade, the case dither is for
+        let fade: Vec<f32> = (0..2000).map(|i| 0.001 * (1.0 - i as f32 / 2000.0)).collect();
+        let project = project(vec![Segment::new(fade)], 8000, 1);
+        let export = |format, dither, seed| {
+            export_bytes(&project, &ExportOptions { format, dither, dither_seed: Some(seed), ..Default::default() })
+        };
+
+        assert_eq!(export(ExportFormat::Int16, true, 7), export(ExportFormat::Int16, true, 7));
+        assert_ne!(export(ExportFormat::Int16, true, 7), export(ExportFormat::Int16, true, 8));
+
+        assert!(!ExportOptions::default().dither); // off unless asked for
+        assert_ne!(export(ExportFormat::Int16, true, 1), export(ExportFormat::Int16, false, 1));
+        assert_eq!(export(ExportFormat::Int24, true, 1), export(ExportFormat::Int24, false, 1));
+        assert_eq!(export(ExportFormat::Float32, true, 1), export(ExportFormat::Float32, false, 1));
+    }
+
+    #[test]
+    fn test_limited_export_stays_under_full_scale() {
+        // +3dB, 200Hz
+        let hot: Vec<f32> = (0..4000).map(|i| 1.41 * (i as f32 * 0.157).sin()).collect();
+        let out: Vec<i16> = export_samples(&project(vec![Segment::new(hot)], 8000, 1), &ExportOptions { limit: true, ..Default::default() });
+
+        assert_eq!(out.len(), 4000);
+        // a hard clip would leave runs of samples stuck at full scale
+        let full = i16::MAX as u16 - 1;
+        let pinned = out.windows(2).filter(|w| w[0].unsigned_abs() >= full && w[1].unsigned_abs() >= full).count();
+        assert_eq!(pinned, 0);
+        assert!(out.iter().any(|s| s.unsigned_abs() > i16::MAX as u16 / 2)); // still loud
+    }
+
+    #[test]
+    fn test_export_applies_gain_and_clips() {
+        let mut seg = Segment::new(vec![0.25, -0.75, 0.5]);
+        seg.gain = 2.0;
+        let project = project(vec![seg], 8000, 1);
+        let out: Vec<i16> = export_samples(&project, &ExportOptions::default());
+
+        assert_eq!(out, vec![i16::MAX / 2, i16::MIN, i16::MAX]); // -1.5 and 1.0 clipped to full scale
+        assert_eq!(project.segments[0].samples, vec![0.25, -0.75, 0.5]); // stored audio untouched
+    }
+
+    #[test]
+    fn test_muted_segments_are_left_out() {
+        let mut muted = Segment::new(vec![0.9; 300]);
+        muted.muted = true;
+        let project = project(vec![Segment::new(vec![0.1; 100]), muted, Segment::new(vec![0.2; 50])], 8000, 1);
+        assert_eq!(read_wav(export_bytes(&project, &ExportOptions::default())).len(), 150);
+
+        let dir = std::env::temp_dir().join(format!("pos_muted_es_{}", std::process::id()));
+        let files = export_segments(&project, dir.to_str().unwrap(), "take", &ExportOptions::default()).unwrap();
+        assert_eq!(files.iter().map(|f| f.file_name().unwrap().to_str().unwrap()).collect::<Vec<_>>(), ["take_001.wav", "take_003.wav"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_boundary_fade_at_joins() {
+        let project = project(vec![Segment::new(vec![0.5; 100]), Segment::new(vec![-0.5; 100])], 1000, 1);
+        let opts = ExportOptions { boundary_fade_ms: 5.0, format: ExportFormat::Float32, ..Default::default() };
+        let out: Vec<f32> = export_samples(&project, &opts);
+
+        assert_eq!(out.len(), 200); // no overlap
+        assert_eq!(out[0], 0.5); // the file's own start and end aren't joins
+        assert_eq!(out[199], -0.5);
+        // both sides ramp toward zero at the join, so there's no 1.0 jump
+        assert_eq!(&out[95..105], &[0.4, 0.3, 0.2, 0.1, 0.0, -0.0, -0.1, -0.2, -0.3, -0.4]);
+        assert_eq!(out[94], 0.5);
+    }
+
+    #[test]
+    fn test_out_of_range_samples_saturate() {
+        let project = project(vec![Segment::new(vec![1.5, -1.5, 1.0, -1.0])], 8000, 1);
+        let out: Vec<i16> = export_samples(&project, &ExportOptions::default());
+
+        // no wraparound to the opposite sign
+        assert_eq!(out, vec![i16::MAX, i16::MIN, i16::MAX, i16::MIN]);
+    }
+
+    #[test]
+    fn test_export_to_a_bad_path_is_an_error() {
+        let project = project(vec![Segment::new(vec![0.5])], 8000, 1);
+        let path = std::env::temp_dir().join("pos_no_such_dir").join("nested").join("out.wav");
+        assert!(export_wav(&project, path.to_str().unwrap(), &ExportOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_export_writes_metadata_tags() {
+        let mut project = project(vec![Segment::new(vec![0.5; 100])], 8000, 1);
+        project.metadata.title = Some("Chapter 1".into());
+        project.metadata.artist = Some("Narrator".into());
+        let bytes = export_bytes(&project, &ExportOptions::default());
+
+        assert_eq!(read_wav(bytes.clone()).duration(), 100); // still a valid WAV
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize, bytes.len() - 8);
+        let find = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+        assert!(find(b"LIST"));
+        assert!(find(b"INAM\x0a\0\0\0Chapter 1\0"));
+        assert!(find(b"IART\x09\0\0\0Narrator\0\0")); // padded to even
+        assert!(!find(b"ICMT")); // unset tags are left out
+    }
+
+    #[test]
+    fn test_export_range() {
+        let project = project((1..=4).map(|i| Segment::new(vec![i as f32 / 10.0; 10])).collect(), 8000, 1);
+        let path = std::env::temp_dir().join(format!("pos_range_{}.wav", std::process::id()));
+        let path = path.to_str().unwrap();
+        export_wav_range(&project, path, 1, 2, &ExportOptions { format: ExportFormat::Float32, ..Default::default() }).unwrap();
+        let out: Vec<f32> = hound::WavReader::open(path).unwrap().samples().map(|s| s.unwrap()).collect();
+        std::fs::remove_file(path).ok();
+
+        assert_eq!(out.len(), 20); // segments 2 and 3 only
+        assert_eq!((out[0], out[19]), (0.2, 0.3));
+        assert!(export_wav_range(&project, path, 2, 4, &ExportOptions::default()).is_err());
+        assert!(export_wav_range(&project, path, 2, 1, &ExportOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let project = project(vec![Segment::new(vec![0.5, -1.0, 1.5])], 8000, 1);
+        let export = |format| read_wav(export_bytes(&project, &ExportOptions { format, ..Default::default() }));
+
+        let mut r = export(ExportFormat::Int24);
+        assert_eq!(r.spec().bits_per_sample, 24);
+        let out: Vec<i32> = r.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(out, vec![4_194_304, -8_388_608, 8_388_607]);
+
+        let mut r = export(ExportFormat::Float32);
+        assert_eq!(r.spec().sample_format, hound::SampleFormat::Float);
+        let out: Vec<f32> = r.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(out, vec![0.5, -1.0, 1.5]); // written as-is, no clamp
+
+        assert_eq!(ExportFormat::parse("float"), Some(ExportFormat::Float32));
+        assert_eq!(ExportFormat::parse("8"), None);
+    }
+
+    #[cfg(feature = "flac")]
+    #[test]
+    fn test_flac_round_trip() {
+        use rodio::Source;
+        let frames = 10_000; // a few FLAC blocks, the last one short
+        let samples: Vec<f32> = (0..frames)
+            .flat_map(|i| {
+                let t = i as f32 / 8000.0;
+                [0.6 * (2.0 * std::f32::consts::PI * 220.0 * t).sin(), if i % 7 == 0 { -1.0 } else { 0.25 }]
+            })
+            .collect();
+        let project = project(vec![Segment::new(samples[..6000].to_vec()), Segment::new(samples[6000..].to_vec())], 8000, 2);
+        for format in [ExportFormat::Int16, ExportFormat::Int24] {
+            let path = std::env::temp_dir().join(format!("pos_flac_{:?}_{}.flac", format, std::process::id()));
+            let opts = ExportOptions { format, ..Default::default() };
+            export_flac(&project, path.to_str().unwrap(), &opts).unwrap();
+
+            let decoder = rodio::Decoder::try_from(std::fs::File::open(&path).unwrap()).unwrap();
+            assert_eq!((decoder.channels().get(), decoder.sample_rate().get()), (2, 8000));
+            let decoded: Vec<f32> = decoder.collect();
+            assert_eq!(decoded.len(), samples.len());
+            let scale = if format == ExportFormat::Int16 { 32768.0 } else { 8_388_608.0 };
+            for (i, (&d, &s)) in decoded.iter().zip(&samples).enumerate() {
+                let expected = to_int_sample(s, format, None) as f32 / scale;
+                assert!((d - expected).abs() < 1e-6, "sample {}: {} vs {}", i, d, expected);
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+    }
 }
@@ -0,0 +1,274 @@
+// Minimal FLAC encoder, enough for lossless archives of a project:
+//   - fixed 4096-frame blocks, every channel coded on its own
+//   - per channel and block the cheapest of CONSTANT, VERBATIM or a FIXED
+//     predictor (order 0-4) with a single Rice partition
+//   - STREAMINFO only, the MD5 is left zero ("not computed", which the
+//     format allows) so decoders skip the check
+// no LPC and no stereo decorrelation, so files come out a bit larger than
+// `flac -8` would make them, but any decoder reads them.
+// it lives here rather than behind an encoder crate because nothing in the
+// dependency set encodes FLAC (symphonia, via rodio, only decodes it), and
+// the tests below check every path against that decoder.
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_RICE_PARAM: u32 = 14; // 15 is the escape code
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32, // pending bits in acc, always < 8 between writes
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self { bytes: Vec::new(), acc: 0, bits: 0 }
+    }
+
+    // the low `n` bits of `value`, most significant first (n <= 32)
+    fn write(&mut self, value: u64, n: u32) {
+        if n == 0 { return; }
+        self.acc = (self.acc << n) | (value & ((1u64 << n) - 1));
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1u64 << self.bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i64, n: u32) {
+        self.write(value as u64, n);
+    }
+
+    // q zeros and a closing one
+    fn write_unary(&mut self, mut q: u64) {
+        while q >= 32 {
+            self.write(0, 32);
+            q -= 32;
+        }
+        self.write(1, q as u32 + 1);
+    }
+
+    // zero-pad to the next byte boundary
+    fn align(&mut self) {
+        if self.bits > 0 { self.write(0, 8 - self.bits); }
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &b| {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+// interleaved integer samples (already scaled to `bits`, 16 or 24) to a
+// complete .flac file
+pub fn encode(samples: &[i32], channels: u16, sample_rate: u32, bits: u8) -> Vec<u8> {
+    let ch = channels.max(1) as usize;
+    let frames = samples.len() / ch;
+    let block = BLOCK_SIZE.min(frames).max(16);
+
+    let mut out = BitWriter::new();
+    out.bytes.extend_from_slice(b"fLaC");
+    // metadata block header: last block, type 0 (STREAMINFO), 34 bytes
+    out.write(1, 1);
+    out.write(0, 7);
+    out.write(34, 24);
+    out.write(block as u64, 16); // min block size
+    out.write(block as u64, 16); // max block size
+    out.write(0, 24); // min frame size, unknown
+    out.write(0, 24); // max frame size, unknown
+    out.write(sample_rate as u64, 20);
+    out.write(ch as u64 - 1, 3);
+    out.write(bits as u64 - 1, 5);
+    out.write(frames as u64, 36);
+    for _ in 0..4 { out.write(0, 32); } // MD5, not computed
+
+    let mut bytes = out.bytes;
+    for (n, chunk) in samples[..frames * ch].chunks(BLOCK_SIZE * ch).enumerate() {
+        bytes.extend_from_slice(&encode_frame(n as u64, chunk, ch, bits));
+    }
+    bytes
+}
+
+fn encode_frame(number: u64, interleaved: &[i32], ch: usize, bits: u8) -> Vec<u8> {
+    let len = interleaved.len() / ch;
+    let mut w = BitWriter::new();
+    w.write(0b11_1111_1111_1110, 14); // sync
+    w.write(0, 1); // reserved
+    w.write(0, 1); // fixed block size, the header carries the frame number
+    w.write(0b0111, 4); // block size - 1 follows as 16 bits
+    w.write(0b0000, 4); // sample rate from STREAMINFO
+    w.write(ch as u64 - 1, 4); // independent channels
+    w.write(if bits == 24 { 0b110 } else { 0b100 }, 3);
+    w.write(0, 1); // reserved
+    write_utf8_number(&mut w, number);
+    w.write(len as u64 - 1, 16);
+    let crc = crc8(&w.bytes);
+    w.write(crc as u64, 8);
+
+    for c in 0..ch {
+        let channel: Vec<i64> = interleaved.iter().skip(c).step_by(ch).map(|&s| s as i64).collect();
+        write_subframe(&mut w, &channel, bits as u32);
+    }
+    w.align();
+    let crc = crc16(&w.bytes);
+    w.write(crc as u64, 16);
+    w.bytes
+}
+
+// frame numbers use the same variable-length scheme as UTF-8
+fn write_utf8_number(w: &mut BitWriter, n: u64) {
+    if n < 0x80 {
+        w.write(n, 8);
+        return;
+    }
+    let extra = match n {
+        _ if n < 0x800 => 1,
+        _ if n < 0x1_0000 => 2,
+        _ if n < 0x20_0000 => 3,
+        _ if n < 0x400_0000 => 4,
+        _ => 5,
+    };
+    let lead = (0xFF00u64 >> (extra + 1)) & 0xFF; // 110xxxxx, 1110xxxx, ...
+    w.write(lead | (n >> (6 * extra)), 8);
+    for i in (0..extra).rev() {
+        w.write(0x80 | ((n >> (6 * i)) & 0x3F), 8);
+    }
+}
+
+// prediction error of the order-0..=4 fixed polynomial predictors
+fn fixed_residual(x: &[i64], order: usize) -> Vec<i64> {
+    (order..x.len()).map(|i| match order {
+        0 => x[i],
+        1 => x[i] - x[i - 1],
+        2 => x[i] - 2 * x[i - 1] + x[i - 2],
+        3 => x[i] - 3 * x[i - 1] + 3 * x[i - 2] - x[i - 3],
+        _ => x[i] - 4 * x[i - 1] + 6 * x[i - 2] - 4 * x[i - 3] + x[i - 4],
+    }).collect()
+}
+
+fn zigzag(r: i64) -> u64 {
+    ((r << 1) ^ (r >> 63)) as u64
+}
+
+// cheapest Rice parameter for the residual and what it costs in bits
+fn best_rice(residual: &[i64]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAM)
+        .map(|k| (k, residual.iter().map(|&r| (zigzag(r) >> k) + 1 + k as u64).sum::<u64>()))
+        .min_by_key(|&(_, cost)| cost)
+        .unwrap_or((0, 0))
+}
+
+fn write_subframe(w: &mut BitWriter, x: &[i64], bps: u32) {
+    if x.iter().all(|&s| s == x[0]) {
+        w.write(0b0000_0000, 8); // CONSTANT
+        w.write_signed(x[0], bps);
+        return;
+    }
+    let verbatim = x.len() as u64 * bps as u64;
+    let fixed = (0..=4usize)
+        .filter(|&order| order < x.len())
+        .map(|order| {
+            let residual = fixed_residual(x, order);
+            let (k, bits) = best_rice(&residual);
+            (order, residual, k, order as u64 * bps as u64 + 10 + bits)
+        })
+        .min_by_key(|f| f.3);
+
+    match fixed {
+        Some((order, residual, k, cost)) if cost < verbatim => {
+            w.write(0b0001_0000 | (order as u64) << 1, 8); // FIXED, order in the low bits
+            for &s in &x[..order] { w.write_signed(s, bps); } // warm-up
+            w.write(0b00, 2); // Rice, 4-bit parameters
+            w.write(0, 4); // partition order 0, one partition
+            w.write(k as u64, 4);
+            for &r in &residual {
+                let u = zigzag(r);
+                w.write_unary(u >> k);
+                w.write(u, k);
+            }
+        }
+        _ => {
+            w.write(0b0000_0010, 8); // VERBATIM
+            for &s in x { w.write_signed(s, bps); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crcs_match_the_spec() {
+        // check values for "123456789" with each polynomial
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        assert_eq!(crc16(b"123456789"), 0xFEE8);
+    }
+
+    // what a real decoder (symphonia, through rodio) makes of the bytes
+    fn decode(bytes: Vec<u8>) -> (u16, u32, Vec<f32>) {
+        use rodio::Source;
+        let decoder = rodio::Decoder::try_from(std::io::Cursor::new(bytes)).unwrap();
+        (decoder.channels().get(), decoder.sample_rate().get(), decoder.collect())
+    }
+
+    // STREAMINFO total samples, the low 36 bits of bytes 21..26
+    fn total_frames(bytes: &[u8]) -> u64 {
+        bytes[21..26].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64) & 0xF_FFFF_FFFF
+    }
+
+    #[test]
+    fn test_empty_project_is_a_valid_stream() {
+        let bytes = encode(&[], 2, 44100, 16);
+        assert_eq!(&bytes[..4], b"fLaC");
+        assert_eq!(bytes.len(), 4 + 4 + 34); // header and STREAMINFO, no frames
+        assert_eq!(total_frames(&bytes), 0);
+    }
+
+    #[test]
+    fn test_fewer_frames_than_the_minimum_block() {
+        let samples = [100, -100, 32767, -32768, 0, 1, -1, 5, 12, -12]; // 5 stereo frames
+        let bytes = encode(&samples, 2, 8000, 16);
+        assert_eq!(total_frames(&bytes), 5);
+        let (channels, rate, decoded) = decode(bytes);
+        assert_eq!((channels, rate), (2, 8000));
+        let expected: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_constant_block_uses_a_constant_subframe() {
+        let samples = vec![-1_000_000; BLOCK_SIZE]; // one full block of DC, 24-bit
+        let bytes = encode(&samples, 1, 48000, 24);
+        // a single sample per subframe, not 4096 of them
+        assert!(bytes.len() < 4 + 4 + 34 + 32, "{} bytes", bytes.len());
+        let (_, _, decoded) = decode(bytes);
+        assert_eq!(decoded.len(), BLOCK_SIZE);
+        assert!(decoded.iter().all(|&d| (d - -1_000_000.0 / 8_388_608.0).abs() < 1e-7));
+    }
+
+    #[test]
+    fn test_frame_numbers_are_utf8_coded() {
+        let mut w = BitWriter::new();
+        write_utf8_number(&mut w, 0x7F);
+        write_utf8_number(&mut w, 0x80);
+        write_utf8_number(&mut w, 0x20AC);
+        assert_eq!(w.bytes, vec![0x7F, 0xC2, 0x80, 0xE2, 0x82, 0xAC]);
+    }
+}
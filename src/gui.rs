@@ -59,6 +59,9 @@ impl eframe::App for RecorderApp {
 }
 
 // -- Helpers -------------------------------------------------------------------
+// (label, colour, command builder) for the per-row action buttons
+type RowButton = (&'static str, Color32, fn(usize) -> Command);
+
 fn blend(a: Color32, b: Color32, t: f32) -> Color32 {
     let t = t.clamp(0.0, 1.0);
     Color32::from_rgba_unmultiplied(
@@ -117,12 +120,13 @@ impl RecorderApp {
         let t = ui.input(|i| i.time) as f32;
         let rec = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
         let (text, col) = match (&rec.state, &rec.playback_state) {
+            (AppState::Recording, _) if rec.paused => ("PAUSED", AMBER),
             (AppState::Recording, _)    => ("REC",    REC),
             (_, PlaybackState::Playing) => ("PLAY",   PLAY),
             (AppState::Reviewing, _)    => ("REVIEW", AMBER),
             _                           => ("IDLE",   MUTED),
         };
-        let alpha = if matches!(rec.state, AppState::Recording) {
+        let alpha = if matches!(rec.state, AppState::Recording) && !rec.paused {
             ((t * 2.8).sin() * 0.42 + 0.58).clamp(0.0, 1.0)
         } else { 1.0 };
         let col = Color32::from_rgba_unmultiplied(col.r(), col.g(), col.b(), (alpha * 255.0) as u8);
@@ -191,9 +195,11 @@ impl RecorderApp {
                     state_str == "idle" && !is_playing, REC,
                     || self.handle_command(Command::StartRecording));
                 ui.add_space(gap);
+                // STOP ends a recording, or cuts off whatever is playing
                 self.transport_btn(ui, ctx, "STOP", w, h,
-                    state_str == "recording", MUTED,
-                    || self.handle_command(Command::StopRecording));
+                    state_str == "recording" || is_playing, MUTED,
+                    || if is_playing { self.handle_command(Command::StopPlayback) }
+                       else { self.handle_command(Command::StopRecording) });
                 ui.add_space(gap);
                 let listen_lbl = if state_str == "reviewing" { "LISTEN" } else { "PLAY" };
                 self.transport_btn(ui, ctx, listen_lbl, w, h,
@@ -226,7 +232,7 @@ impl RecorderApp {
                 ui.add_space(gap);
                 self.transport_btn(ui, ctx, "PLAY ALL", w, h,
                     seg_count > 0 && !is_playing && state_str == "idle", MUTED,
-                    || self.handle_command(Command::PlayAll));
+                    || self.handle_command(Command::PlayAll(0.0)));
                 ui.add_space(gap);
                 self.transport_btn(ui, ctx, "<< UNDO", w, h,
                     can_undo && state_str == "idle" && !is_playing, MUTED,
@@ -240,6 +246,7 @@ impl RecorderApp {
     }
 
     // transport button text only (no separate icon), works at any height
+    #[allow(clippy::too_many_arguments)]
     fn transport_btn(
         &self, ui: &mut egui::Ui, ctx: &egui::Context,
         label: &str, w: f32, h: f32,
@@ -263,12 +270,12 @@ impl RecorderApp {
     fn draw_segment_list(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         let (seg_count, is_playing, is_idle, total_dur, meta) = {
             let rec = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
-            let sr  = rec.project.sample_rate;
+            let (sr, ch) = (rec.project.sample_rate, rec.project.channels);
             let ip  = rec.playback_state == PlaybackState::Playing;
             let ii  = matches!(rec.state, AppState::Idle);
-            let td: f32 = rec.project.segments.iter().map(|s| s.duration_seconds(sr)).sum();
+            let td: f32 = rec.project.segments.iter().map(|s| s.duration_seconds(sr, ch)).sum();
             let meta: Vec<(usize, usize, f32)> = rec.project.segments.iter().enumerate()
-                .map(|(i, s)| (i, s.samples.len(), s.duration_seconds(sr)))
+                .map(|(i, s)| (i, s.samples.len(), s.duration_seconds(sr, ch)))
                 .collect();
             (rec.get_segment_count(), ip, ii, td, meta)
         }; //  mutex released here, drawing happens with no lock held
@@ -386,7 +393,7 @@ impl RecorderApp {
         let mut pending: Option<Command> = None;
         if is_idle && !is_playing {
             // left-to-right order: INSERT  PLAY  RETRY  DEL (rightmost = most destructive last)
-            let specs: &[(&str, Color32, fn(usize) -> Command)] = &[
+            let specs: &[RowButton] = &[
                 ("INSERT", BLUE,  Command::InsertAfter   as fn(usize) -> Command),
                 ("PLAY",   PLAY,  Command::PlaySegment   as fn(usize) -> Command),
                 ("RETRY",  AMBER, Command::RetrySegment  as fn(usize) -> Command),
@@ -503,45 +510,43 @@ impl RecorderApp {
             };
 
             // pass ui as the first argument to every call
-            if footer_btn(ui, "LOAD PROJECT", can_interact, 120.0) {
-                if let Some(path) = rfd::FileDialog::new()
+            if footer_btn(ui, "LOAD PROJECT", can_interact, 120.0)
+                && let Some(path) = rfd::FileDialog::new()
                     .add_filter("Project File", &["bin"])
                     .pick_file()
-                {
-                    self.handle_command(Command::LoadProject(path.to_string_lossy().to_string()));
-                    ctx.request_repaint();
-                }
+            {
+                self.handle_command(Command::LoadProject(path.to_string_lossy().to_string()));
+                ctx.request_repaint();
             }
             ui.add_space(8.0);
 
             // save only active once a path has been set via save as or load
             let can_save = can_interact && current_save_path.is_some();
-            if footer_btn(ui, "SAVE", can_save, 70.0) {
-                if let Some(ref path) = current_save_path {
-                    self.handle_command(Command::SaveProjectAs(path.clone()));
-                    ctx.request_repaint();
-                }
+            if footer_btn(ui, "SAVE", can_save, 70.0)
+                && let Some(ref path) = current_save_path
+            {
+                self.handle_command(Command::SaveProjectAs(path.clone()));
+                ctx.request_repaint();
             }
             ui.add_space(8.0);
 
-            if footer_btn(ui, "SAVE AS...", can_interact, 100.0) {
-                if let Some(path) = rfd::FileDialog::new()
+            if footer_btn(ui, "SAVE AS...", can_interact, 100.0)
+                && let Some(path) = rfd::FileDialog::new()
                     .add_filter("Project File", &["bin"])
                     .save_file()
-                {
-                    self.handle_command(Command::SaveProjectAs(path.to_string_lossy().to_string()));
-                    ctx.request_repaint();
-                }
+            {
+                self.handle_command(Command::SaveProjectAs(path.to_string_lossy().to_string()));
+                ctx.request_repaint();
             }
             ui.add_space(8.0);
 
             if footer_btn(ui, "EXPORT WAV", can_export, 110.0) {
                 let mut dialog = rfd::FileDialog::new().add_filter("WAV Audio", &["wav"]);
 
-                if let Some(p) = current_save_path {
-                    if let Some(parent) = std::path::Path::new(&p).parent() {
-                        dialog = dialog.set_directory(parent);
-                    }
+                if let Some(p) = current_save_path
+                    && let Some(parent) = std::path::Path::new(&p).parent()
+                {
+                    dialog = dialog.set_directory(parent);
                 }
 
                 if let Some(path) = dialog.save_file() {
@@ -593,7 +598,7 @@ impl RecorderApp {
 
                     let keys: &[(&str, &str, Color32)] = &[
                         ("R",                     "Start recording",             REC),
-                        ("S",                     "Stop recording",              MUTED),
+                        ("S",                     "Stop recording / playback",   MUTED),
                         ("C",                     "Confirm / approve take",      PLAY),
                         ("X",                     "Reject take",                 REC),
                         ("T",                     "Try again (re-record slot)",  AMBER),
@@ -675,6 +680,8 @@ impl RecorderApp {
             }
             if i.key_pressed(egui::Key::S) && state_str == "recording" {
                 self.handle_command(Command::StopRecording);
+            } else if i.key_pressed(egui::Key::S) && playing {
+                self.handle_command(Command::StopPlayback);
             }
             if i.key_pressed(egui::Key::C) && state_str == "reviewing" && !playing {
                 self.handle_command(Command::Approve);
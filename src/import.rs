@@ -0,0 +1,108 @@
+use crate::dsp;
+use crate::state::Segment;
+use std::error::Error;
+
+// read a WAV file into a segment shaped like the project: the file's own
+// channel count and rate are reconciled here so the interleaved samples are
+// never misread (a stereo file read as mono plays at half speed)
+
+pub fn import_wav(path: &str, channels: u16, sample_rate: u32) -> Result<Segment, Box<dyn Error>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|s| s.map(|v| v as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let mut seg = Segment::new(samples);
+    seg.log(format!("imported {} ({}ch, {}Hz)", path, spec.channels, spec.sample_rate));
+
+    if spec.channels != channels {
+        seg.samples = remix(&seg.samples, spec.channels, channels);
+        let verb = if spec.channels > channels { "down-mixed" } else { "up-mixed" };
+        seg.log(format!("{} {}ch -> {}ch", verb, spec.channels, channels));
+    }
+    if spec.sample_rate != sample_rate {
+        seg.samples = dsp::resample_interleaved(&seg.samples, channels, spec.sample_rate, sample_rate);
+        seg.log(format!("resampled {}Hz -> {}Hz", spec.sample_rate, sample_rate));
+    }
+    Ok(seg)
+}
+
+// convert interleaved audio between channel counts
+// mono fans out to every channel, anything else is averaged per frame
+// and then fanned out (so 2 -> 1 is a plain L/R average)
+pub fn remix(samples: &[f32], from: u16, to: u16) -> Vec<f32> {
+    let (from, to) = (from.max(1) as usize, to.max(1) as usize);
+    if from == to {
+        return samples.to_vec();
+    }
+    samples.chunks_exact(from)
+        .flat_map(|frame| {
+            let mono = frame.iter().sum::<f32>() / from as f32;
+            std::iter::repeat_n(mono, to)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_stereo_into_mono_project() {
+        let path = std::env::temp_dir().join(format!("pos_import_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        // one second of L = 0.5, R = -0.25
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..8000 {
+            writer.write_sample((0.5 * 32768.0) as i16).unwrap();
+            writer.write_sample((-0.25 * 32768.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let seg = import_wav(path.to_str().unwrap(), 1, 8000).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // one mono sample per stereo frame, so the duration is still 1s
+        assert_eq!(seg.samples.len(), 8000);
+        assert!((seg.duration_seconds(8000, 1) - 1.0).abs() < 1e-6);
+        assert!(seg.samples.iter().all(|&s| (s - 0.125).abs() < 1e-3));
+        assert!(seg.history.iter().any(|h| h.contains("down-mixed 2ch -> 1ch")));
+    }
+
+    #[test]
+    fn test_import_converts_sample_rate() {
+        let path = std::env::temp_dir().join(format!("pos_import_rate_{}.wav", std::process::id()));
+        let spec = hound::WavSpec { channels: 1, sample_rate: 16000, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..16000 {
+            writer.write_sample(0.5f32).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        // a 16kHz second into an 8kHz project is still a second, not two
+        let seg = import_wav(path.to_str().unwrap(), 1, 8000).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(seg.samples.len(), 8000);
+        assert!(seg.samples.iter().all(|&s| s == 0.5));
+        assert!(seg.history.iter().any(|h| h == "resampled 16000Hz -> 8000Hz"));
+    }
+
+    #[test]
+    fn test_remix_up_and_down() {
+        assert_eq!(remix(&[0.2, 0.4], 1, 2), vec![0.2, 0.2, 0.4, 0.4]);
+        assert_eq!(remix(&[1.0, 0.0, 0.5, 0.5], 2, 1), vec![0.5, 0.5]);
+        assert_eq!(remix(&[0.1, 0.2], 1, 1), vec![0.1, 0.2]);
+    }
+}
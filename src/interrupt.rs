@@ -0,0 +1,38 @@
+// Ctrl+C in the CLI. The default action kills the process on the spot and
+// every take not yet exported goes with it, so instead the handler only
+// raises a flag (the one thing that's safe inside a signal handler). The
+// prompt in main.rs stops waiting when it sees it, and the CLI leaves through
+// its normal shutdown (input stream stopped and settled) after writing a
+// recovery snapshot of the project. The next start offers to restore it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// route SIGINT to the flag, false when the handler couldn't be installed
+// (or on platforms without signals, where Ctrl+C keeps its default)
+#[cfg(unix)]
+pub fn install() -> bool {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe { libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t) != libc::SIG_ERR }
+}
+
+#[cfg(not(unix))]
+pub fn install() -> bool {
+    false
+}
+
+// whether Ctrl+C came in, without clearing it, so every prompt gives up
+pub fn pending() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+// true once per Ctrl+C
+pub fn take() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
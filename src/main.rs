@@ -3,11 +3,21 @@ mod audio_input;
 mod audio_output;
 mod export;
 mod gui;
+mod visualize;
+mod dsp;
+mod import;
+mod config;
+mod priority;
+mod args;
+mod project_io;
+mod interrupt;
+#[cfg(feature = "flac")]
+mod flac;
 
 use std::sync::{Arc, Mutex};
 use cpal::traits::StreamTrait;
-use state::{RecorderState, Command, dispatch_command, PlaybackState};
-use audio_output::{play_segment_async, play_project_async, ProjectSnapshot};
+use state::{RecorderState, Command, dispatch_command, PlaybackState, Settings, ReviewAction};
+use audio_output::{play_segment_async, play_segment_from, play_segment_loop, play_project_async, ProjectSnapshot};
 use colored::*;
 
 // ** input **
@@ -33,18 +43,20 @@ use colored::*;
 // - Bad: Type `x`. reject() deletes current. State becomes Idle. You can type r to try again
 // Finish: Type `e`. export.rs combines all project.segments into one WAV file
 
+// said when a structural edit is refused because a take is still open
+const TAKE_PENDING: &str = "Finish the take in progress first (c to keep it, x to reject it)";
+
 
 // RecorderApp is the App struct which owns all long-lived resources
 // and implements eframe::App
 //
 // Owned resources:
-//   _stream:  must live as long as the app; dropping it silences the mic
+//   stream:   must live as long as the app; taking it out and stopping it
+//             silences the mic (see shutdown)
 //   recorder: Arc<Mutex<RecorderState>> shared with the audio thread
-// `_stream` field name starts with `_` so Rust knows the drop is
-// intentional (not a bug) and won't emit an unused-variable warning.
 pub struct RecorderApp {
     pub recorder:          Arc<Mutex<RecorderState>>,
-    pub _stream:           cpal::Stream,
+    pub stream:            Option<cpal::Stream>, // None once shut down, or when no input device could be opened
     // GUI state, not visible to audio threads
     pub selected_segment:  Option<usize>,
     pub trim_amount:       f32,
@@ -52,44 +64,111 @@ pub struct RecorderApp {
 }
 
 impl RecorderApp {
-    pub fn new(on_new_data: impl Fn() + Send + 'static) -> Self {
+    pub fn new(settings: Settings, on_new_data: impl Fn() + Send + 'static) -> Self {
         // run_gui passes ctx.request_repaint(), while CLI passes || {}
-        let recorder = Arc::new(Mutex::new(RecorderState::new(48000, 1)));
-        let stream = audio_input::start_input_stream(recorder.clone(), on_new_data);
-        stream.play().unwrap();
+        let mut state = RecorderState::new(48000, 1);
+        state.settings = settings; // before the stream starts, it reads input settings
+        let recorder = Arc::new(Mutex::new(state));
+        // a --device that isn't plugged in falls back to the default input
+        let device = recorder.lock().unwrap().settings.input_device.clone()
+            .and_then(|name| audio_input::resolve_input_device_name(&name)
+                .inspect_err(|e| eprintln!("{}, using the default input", e)).ok());
+        // no microphone isn't fatal: saved projects can still be opened, played and exported
+        let stream = audio_input::start_input_stream_with_device(recorder.clone(), device.as_deref(), on_new_data)
+            .and_then(|stream| stream.play().map(|_| stream).map_err(|e| format!("Failed to start input stream: {}", e)))
+            .inspect_err(|e| eprintln!("{} — recording is unavailable, playback and export still work", e))
+            .ok();
+        if stream.is_none() {
+            recorder.lock().unwrap().device_sample_rate = None;
+        }
+        spawn_auto_stop_watcher(Arc::downgrade(&recorder));
         Self {
             recorder,
-            _stream: stream,
+            stream,
             selected_segment: None,
             trim_amount:      0.10,
             show_keybindings: false,
         }
     }
 
+    // stop playback and the input stream in order on the way out.
+    // safe to call more than once, Drop calls it again as a fallback
+    pub fn shutdown(&mut self) {
+        self.recorder.lock().unwrap_or_else(|e| e.into_inner()).request_stop_playback();
+        if let Some(stream) = self.stream.take() {
+            audio_input::stop_input_stream(stream, audio_input::SHUTDOWN_SETTLE);
+        }
+    }
+
     // rec.current which is the pending take that hasn't been approved yet, this is 
     // separate from PlaySegment(idx) because 'current' hasn't been commited to project.segments
     // used in auto-play on stop, and "listen again" during review
     fn play_current_segment(&self) {
+        let repeats = self.recorder.lock().unwrap().settings.review_repeats;
+        self.preview_current(repeats);
+    }
+
+    // play the take under review `repeats` times in a row. the samples are
+    // cloned out first so the lock is released for the whole playback
+    fn preview_current(&self, repeats: u32) {
+        preview_take(&self.recorder, repeats);
+    }
+
+    // play just the window of the take under review, cutting off whatever
+    // preview is still going so each adjustment is heard straight away
+    fn preview_trim_window(&self, window: state::TrimWindow) {
+        self.recorder.lock().unwrap().request_stop_playback();
+        for _ in 0..50 { // the player thread notices the stop flag within ~20ms
+            if self.recorder.lock().unwrap().playback_state != PlaybackState::Playing { break; }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
         let rec = self.recorder.lock().unwrap();
         if rec.playback_state == PlaybackState::Playing { return; }
-        if let Some(seg) = &rec.current { // current recording
-            let seg_clone = seg.clone();
-            let sample_rate = rec.project.sample_rate;
+        if let Some(seg) = rec.trim_window_preview(window) {
+            let (sample_rate, channels, opts) = (rec.project.sample_rate, rec.project.channels, rec.settings.playback);
             drop(rec);
-            play_segment_async(seg_clone, sample_rate, self.recorder.clone(), || {});
+            play_segment_async(seg, sample_rate, channels, opts, self.recorder.clone(), || {});
+        }
+    }
+
+    // write the project to `path`, reporting either way. false on failure
+    // so the CLI can stay open instead of exiting with nothing written
+    fn export(&self, path: &str) -> bool {
+        let rec = self.recorder.lock().unwrap();
+        match export::export_wav(&rec.project, path, &rec.settings.export) {
+            Ok(()) => { println!("Exported to {}", path); true }
+            Err(e) => { eprintln!("Failed to export to {}: {}", path, e); false }
         }
     }
 
     // PlaySegment / PlayAll / Export are handled here because they need either
     // thread-spawning (playback) or file I/O (export) — not pure state mutation.
     pub fn handle_command(&self, cmd: Command) {
+        if cmd.arms_recording() && self.stream.is_none() {
+            println!("No input device, recording is unavailable.");
+            return;
+        }
+        // refuse to open a take the input can't lay out for this project
+        if cmd.arms_recording()
+            && let Err(e) = self.recorder.lock().unwrap().capture_plan()
+        {
+            println!("{}", e);
+            return;
+        }
         match cmd {
             Command::StopRecording => {
-                {
-                    let mut rec = self.recorder.lock().unwrap();
-                    rec.stop_recording(); // change to reviewing
+                self.recorder.lock().unwrap().stop_recording(); // change to reviewing
+                start_review(&self.recorder);
+            }
+
+            Command::RestoreRejected => {
+                let restored = self.recorder.lock().unwrap().restore_rejected();
+                if restored {
+                    println!("Restored the last rejected take, c to keep it or x to reject it again");
+                    start_review(&self.recorder);
+                } else {
+                    println!("No rejected take to restore (or a take is in progress).");
                 }
-                self.play_current_segment(); // auto-play after stopping
             }
 
             // *** dispatch commands
@@ -101,8 +180,7 @@ impl RecorderApp {
                     return;
                 }
                 drop(rec);
-                let mut rec = self.recorder.lock().unwrap();
-                dispatch_command(&mut rec, Command::Approve);
+                approve_take(&self.recorder);
             }
 
             Command::Reject => { // gated by playback state
@@ -133,37 +211,99 @@ impl RecorderApp {
                 let rec = self.recorder.lock().unwrap();
                 if rec.playback_state == PlaybackState::Playing { return; } // already playing
 
-                if let Some(seg) = rec.get_segment(idx) {
-                    let seg_clone = seg.clone();
-                    let sample_rate = rec.project.sample_rate;
+                // with the neighbours' edges when settings.context_ms is set
+                if let Some(seg) = rec.segment_playback(idx) {
+                    let (sample_rate, channels, opts) = (rec.project.sample_rate, rec.project.channels, rec.settings.playback);
                     drop(rec);
-                    play_segment_async(seg_clone, sample_rate, self.recorder.clone(), || {});
+                    play_segment_async(seg, sample_rate, channels, opts, self.recorder.clone(), || {});
                 }
             }
 
-            Command::PlayAll => {
+            Command::PlaySegmentFrom(idx, start) => {
+                let rec = self.recorder.lock().unwrap();
+                if rec.playback_state == PlaybackState::Playing { return; }
+                let Some(seg) = rec.get_segment(idx).cloned() else { return; };
+                let (sample_rate, channels, opts) = (rec.project.sample_rate, rec.project.channels, rec.settings.playback);
+                let length = seg.duration_seconds(sample_rate, channels);
+                drop(rec);
+                if !play_segment_from(seg, sample_rate, channels, start, opts, self.recorder.clone(), || {}) {
+                    println!("Segment {} is only {} long", idx + 1, format_seconds(length));
+                }
+            }
+
+            Command::LoopSegment(idx, count) => {
+                let rec = self.recorder.lock().unwrap();
+                if rec.playback_state == PlaybackState::Playing { return; }
+                if let Some(seg) = rec.get_segment(idx).cloned() {
+                    let (sample_rate, channels, opts) = (rec.project.sample_rate, rec.project.channels, rec.settings.playback);
+                    drop(rec);
+                    play_segment_loop(seg, sample_rate, channels, opts, count, self.recorder.clone(), || {});
+                }
+            }
+
+            Command::PlayRange(start, end) => {
+                let rec = self.recorder.lock().unwrap();
+                if rec.playback_state == PlaybackState::Playing { return; }
+                let Some(mut snapshot) = ProjectSnapshot::from_range(&rec.project, start, end) else { return; };
+                snapshot.crossfade_ms = rec.settings.export.crossfade_ms;
+                snapshot.boundary_fade_ms = rec.settings.export.boundary_fade_ms;
+                let opts = rec.settings.playback;
+                drop(rec);
+                play_project_async(snapshot, opts, self.recorder.clone(), || {});
+            }
+
+            Command::PlayReference(db, idx) => {
+                let rec = self.recorder.lock().unwrap();
+                if rec.playback_state == PlaybackState::Playing { return; }
+                if let Some(seg) = rec.reference_playback(db, idx) {
+                    let (sample_rate, channels, opts) = (rec.project.sample_rate, rec.project.channels, rec.settings.playback);
+                    drop(rec);
+                    play_segment_async(seg, sample_rate, channels, opts, self.recorder.clone(), || {});
+                }
+            }
+
+            Command::PlayAll(gap) => {
                 let rec = self.recorder.lock().unwrap();
                 if rec.playback_state == PlaybackState::Playing { return; }
                 if rec.project.segments.is_empty() { return; }
 
-                let snapshot = ProjectSnapshot::from_project(&rec.project);
+                let mut snapshot = ProjectSnapshot::from_project(&rec.project);
+                snapshot.crossfade_ms = rec.settings.export.crossfade_ms;
+                snapshot.boundary_fade_ms = rec.settings.export.boundary_fade_ms;
+                snapshot.gap_seconds = gap;
+                let opts = rec.settings.playback;
                 drop(rec);
-                play_project_async(snapshot, self.recorder.clone(), || {});
+                play_project_async(snapshot, opts, self.recorder.clone(), || {});
             }
 
             Command::Export(custom_path) => {
-                let rec = self.recorder.lock().unwrap();
                 // Use the provided path, or fallback to output.wav
                 let path = custom_path.unwrap_or_else(|| "output.wav".into());
-                export::export_wav(&rec.project, &path);
-                println!("Exported to {}", path);
+                self.export(&path);
+            }
+
+            Command::ExportPackage(dir) => {
+                let rec = self.recorder.lock().unwrap();
+                match export::export_package(&rec.project, &dir, &rec.settings.export) {
+                    Ok(manifest) => println!("Package written, manifest at {}", manifest.display()),
+                    Err(e) => eprintln!("Failed to export package: {}", e),
+                }
+            }
+
+            Command::ExportSegments(dir, prefix) => {
+                let rec = self.recorder.lock().unwrap();
+                match export::export_segments(&rec.project, &dir, &prefix, &rec.settings.export) {
+                    Ok(files) => println!("Wrote {} segment file(s) to {}", files.len(), dir),
+                    Err(e) => eprintln!("Failed to export segments: {}", e),
+                }
             }
 
             Command::SaveProjectAs(path) => {
                 let mut rec = self.recorder.lock().unwrap();
-                rec.set_save_path(path.clone()); // Remember where we saved it
-                rec.save_to_disk();
-                println!("Project saved to {}", path);
+                match rec.save_project_as(path.clone()) {
+                    Ok(()) => println!("Project saved to {}", path),
+                    Err(e) => eprintln!("Failed to save project: {}", e),
+                }
             }
 
             Command::LoadProject(path) => {
@@ -175,23 +315,403 @@ impl RecorderApp {
                 }
             }
 
+            Command::AppendProject(path) => {
+                let mut rec = self.recorder.lock().unwrap();
+                match rec.append_project(&path) {
+                    Ok(n) => println!("Appended {} segment(s) from {}", n, path),
+                    Err(e) => eprintln!("Failed to append project: {}", e),
+                }
+            }
+
+            Command::ImportWav(path) => {
+                let mut rec = self.recorder.lock().unwrap();
+                match rec.import_wav(&path) {
+                    // history says whether the file had to be re-mixed or resampled
+                    Ok(seg) => match &seg.history[1..] {
+                        [] => println!("Imported {}", path),
+                        steps => println!("Imported {} ({})", path, steps.join(", ")),
+                    },
+                    Err(e) => eprintln!("Failed to import WAV: {}", e),
+                }
+            }
+
             // All other commands change state.rs which are delegated to dispatch_command
             other => {
                 let mut rec = self.recorder.lock().unwrap();
+                if other.restructures() && !rec.can_restructure() {
+                    println!("{}", TAKE_PENDING);
+                    return;
+                }
+                let was_idle = rec.state == state::AppState::Idle;
                 dispatch_command(&mut rec, other);
+                // undo can bring a rejected take back, review it like a fresh one
+                let reviewing = was_idle && rec.state == state::AppState::Reviewing;
+                drop(rec);
+                if reviewing {
+                    println!("Rejected take is back, c to keep it or x to reject it again");
+                    start_review(&self.recorder);
+                }
             }
 
         }
     }
 }
 
+// the GUI has no quit path of its own, eframe just drops the app
+impl Drop for RecorderApp {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// approve the take under review, print its warnings and run the
+// auto-export if it closed a session. shared by the `c` command and the
+// review timer thread, which only has the Arc (cpal::Stream isn't Send)
+fn approve_take(recorder: &Arc<Mutex<RecorderState>>) {
+    let mut rec = recorder.lock().unwrap();
+    // approve directly (not via dispatch) to surface its warnings
+    let result = rec.approve();
+    if !result.discarded { rec.save_state(); } // a discarded take went to reject, undo brings it back
+    // export the session before anything is reset, so a failed export
+    // leaves it in memory and on disk
+    let session = result.session_due.then(|| (rec.project.clone(), rec.settings.export.clone()));
+    drop(rec);
+    if result.discarded {
+        println!("⚠️  take discarded — shorter than the minimum segment length");
+        return;
+    }
+    if result.too_short {
+        println!("⚠️  this take is very short — accidental tap?");
+    }
+    if result.silent {
+        println!("⚠️  this take is silent — mic muted?");
+    }
+    if result.clipped > 0 {
+        println!("⚠️  {} sample(s) clipped — lower the input gain and retry?", result.clipped);
+    }
+    if let Some((session, export_opts)) = session {
+        let path = export::next_versioned_path("session", "wav");
+        match export::export_wav(&session, &path, &export_opts) {
+            Ok(()) => {
+                recorder.lock().unwrap().start_new_session();
+                println!("Session limit reached, exported to {} and started a new (unsaved) session", path);
+            }
+            Err(e) => eprintln!("Session limit reached but exporting to {} failed: {} (session kept)", path, e),
+        }
+    }
+}
+
+// play the take under review `repeats` times in a row. the samples are
+// cloned out first so the lock is released for the whole playback
+fn preview_take(recorder: &Arc<Mutex<RecorderState>>, repeats: u32) {
+    let rec = recorder.lock().unwrap();
+    if rec.playback_state == PlaybackState::Playing { return; }
+    if let Some(seg) = rec.review_playback(repeats) {
+        let (sample_rate, channels, opts) = (rec.project.sample_rate, rec.project.channels, rec.settings.playback);
+        drop(rec);
+        play_segment_async(seg, sample_rate, channels, opts, recorder.clone(), || {});
+    }
+}
+
+// what follows a stop, whether from `s` or from auto-stop: auto-play the
+// take and start the review timer if one is set
+fn start_review(recorder: &Arc<Mutex<RecorderState>>) {
+    let (repeats, review) = {
+        let rec = recorder.lock().unwrap();
+        (rec.settings.review_repeats, rec.settings.review_timeout.map(|secs| (rec.review_id, secs)))
+    };
+    preview_take(recorder, repeats);
+    if let Some((id, secs)) = review {
+        spawn_review_timer(recorder.clone(), id, secs);
+    }
+}
+
+// the input callback can stop a take (silence, length cap) but can't start
+// playback, so this picks up its auto_stopped flag. ends with the app (weak ref)
+fn spawn_auto_stop_watcher(recorder: std::sync::Weak<Mutex<RecorderState>>) {
+    std::thread::spawn(move || {
+        while let Some(recorder) = recorder.upgrade() {
+            let stopped = recorder.lock().unwrap().auto_stopped.take();
+            if let Some(reason) = stopped {
+                match reason {
+                    state::AutoStop::Silence => println!("\nSilence detected, recording stopped."),
+                    state::AutoStop::MaxLength => {
+                        let secs = recorder.lock().unwrap().settings.max_record_seconds.unwrap_or(0.0);
+                        println!("\nMaximum take length ({}s) reached, recording stopped.", secs);
+                    }
+                }
+                start_review(&recorder);
+            }
+            drop(recorder);
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    });
+}
+
+// stdin is read on a thread of its own and handed over line by line, so a
+// prompt can stop waiting when Ctrl+C comes in (see interrupt.rs).
+// None = end of input or interrupted
+fn read_line() -> Option<String> {
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    static LINES: std::sync::OnceLock<Mutex<mpsc::Receiver<String>>> = std::sync::OnceLock::new();
+    let lines = LINES.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || loop {
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 || tx.send(line).is_err() { return; }
+        });
+        Mutex::new(rx)
+    });
+    let lines = lines.lock().unwrap();
+    loop {
+        if interrupt::pending() { return None; }
+        match lines.recv_timeout(std::time::Duration::from_millis(50)) {
+            Ok(line) => return Some(line),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+// hands-free review: after `secs` apply the default decision if nobody has
+// decided on this review yet. waits out any playback still running since
+// approve/reject are gated on it
+fn spawn_review_timer(recorder: Arc<Mutex<RecorderState>>, review_id: u64, secs: f32) {
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs_f32(secs.max(0.0)));
+        loop {
+            let mut rec = recorder.lock().unwrap();
+            let Some(action) = rec.review_timeout_action(review_id) else { return; };
+            if rec.playback_state == PlaybackState::Playing {
+                drop(rec);
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                continue;
+            }
+            match action {
+                ReviewAction::Approve => {
+                    drop(rec);
+                    approve_take(&recorder);
+                    println!("\nNo decision after {}s, take approved.", secs);
+                }
+                ReviewAction::Reject => {
+                    dispatch_command(&mut rec, Command::Reject);
+                    println!("\nNo decision after {}s, take rejected.", secs);
+                }
+            }
+            return;
+        }
+    });
+}
+
+// status line shown before the ❯, the user's template if one is set
+fn build_prompt(rec: &RecorderState) -> String {
+    if let Some(template) = &rec.settings.prompt_template {
+        return format!(" {}", format_prompt(rec, template));
+    }
+    let count = rec.get_segment_count();
+    let total_time = rec.total_duration();
+    let playing = rec.playback_state == PlaybackState::Playing;
+
+    match rec.state {
+        state::AppState::Recording if rec.paused =>
+            format!(" {} {} {} ", "‖".yellow(), "PAUSED".yellow().bold(), take_elapsed(rec)),
+        state::AppState::Recording =>
+            format!(" {} {} {} {} ", "●".red().blink(), "RECORDING".red().bold(), take_elapsed(rec), meter_bars(rec).green()),
+        state::AppState::Reviewing => 
+            format!(" {} {} ", "▶".blue(), "REVIEWING".blue().bold()),
+        state::AppState::Idle if playing => 
+            format!(" {} {} ({} segs)", "".green(), "PLAYING".green(), count),
+        state::AppState::Idle => 
+            format!(" {} {} ({} segs, {})", "○".dimmed(), "IDLE".dimmed(), count, total_time),
+    }
+}
+
+// running length of the take, against the limit when maxlen is set
+fn take_elapsed(rec: &RecorderState) -> String {
+    let elapsed = format_seconds(rec.take_seconds());
+    match rec.settings.max_record_seconds {
+        Some(max) => format!("{} / {}", elapsed, format_seconds(max)),
+        None => elapsed,
+    }
+}
+
+// one bar per live input channel (L/R for stereo)
+fn meter_bars(rec: &RecorderState) -> String {
+    let levels = rec.meter.levels();
+    let names: &[&str] = if levels.len() == 2 { &["L", "R"] } else { &["IN"] };
+    let bars: Vec<String> = names.iter().zip(&levels)
+        .map(|(n, l)| format!("{} {}", n, visualize::meter_bar(*l, 10)))
+        .collect();
+    bars.join(" ")
+}
+
+// how long ago a take was recorded, coarse on purpose: it only has to
+// tell the newest of several retakes apart
+fn format_age(age: std::time::Duration) -> String {
+    match age.as_secs() {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86400),
+    }
+}
+
+// ask a yes/no question on stdin, anything but y/yes (or EOF, Ctrl+C) is no
+fn confirm(question: &str) -> bool {
+    use std::io::Write;
+    print!("  {} [y/N] ", question);
+    std::io::stdout().flush().unwrap();
+    let Some(answer) = read_line() else { return false; };
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+// size of the `waveform <n>` graph
+const WAVEFORM_COLUMNS: usize = 60;
+const WAVEFORM_ROWS: usize = 8;
+
+// a length for the `q` listing and the take timer: seconds, or m:ss.ss past a minute
+fn format_seconds(secs: f32) -> String {
+    // rounded to hundredths before splitting, so 59.996 is 1:00.00 and not 60.00s
+    let hundredths = (secs * 100.0).round() as u64;
+    if hundredths < 6000 {
+        format!("{}.{:02}s", hundredths / 100, hundredths % 100)
+    } else {
+        format!("{}:{:02}.{:02}", hundredths / 6000, hundredths % 6000 / 100, hundredths % 100)
+    }
+}
+
+// expand a prompt template, e.g. "{state} {segs} segs {total}". placeholders:
+//   {state} IDLE/RECORDING/REVIEWING/PLAYING   {segs}  segment count
+//   {total} project length (0m:00s)            {take}  current take seconds
+//   {mem}   MB of audio held in memory         {meter} input level bars
+// unknown text is copied through as-is
+fn format_prompt(rec: &RecorderState, template: &str) -> String {
+    let state = match rec.state {
+        state::AppState::Recording if rec.paused => "PAUSED",
+        state::AppState::Recording => "RECORDING",
+        state::AppState::Reviewing => "REVIEWING",
+        state::AppState::Idle if rec.playback_state == PlaybackState::Playing => "PLAYING",
+        state::AppState::Idle => "IDLE",
+    };
+    let take_secs = rec.take_seconds();
+    let samples: usize = rec.project.segments.iter().map(|s| s.samples.len()).sum::<usize>()
+        + rec.current.as_ref().map(|s| s.samples.len()).unwrap_or(0);
+    let mem_mb = (samples * std::mem::size_of::<f32>()) as f32 / (1024.0 * 1024.0);
+    template
+        .replace("{state}", state)
+        .replace("{segs}", &rec.get_segment_count().to_string())
+        .replace("{total}", &rec.total_duration())
+        .replace("{take}", &format!("{:.1}s", take_secs))
+        .replace("{mem}", &format!("{:.1}MB", mem_mb))
+        .replace("{meter}", &meter_bars(rec))
+}
+
+// how a bad command argument is reported, see args.rs
+fn arg_error(e: &str) {
+    println!("  {} {}", "×".red(), e);
+}
+
+// `tw`: careful trimming of the take under review. the in/out points are
+// moved in seconds, absolute ("in 0.4") or relative ("out -0.25"), and the
+// trimmed take is played after every change. `ok` keeps the trim (undoable),
+// `x` leaves the take as it was
+fn trim_review_loop(app: &RecorderApp) {
+    let (len, sample_rate) = {
+        let rec = app.recorder.lock().unwrap();
+        match (&rec.state, &rec.current) {
+            // the window counts frames, see TrimWindow
+            (state::AppState::Reviewing, Some(seg)) =>
+                (seg.samples.len() / rec.project.channels.max(1) as usize, rec.project.sample_rate),
+            _ => { println!("Nothing to trim, stop a recording first."); return; }
+        }
+    };
+    if len < 2 { println!("Take is too short to trim."); return; }
+    let secs = |samples: usize| samples as f32 / sample_rate as f32;
+    let mut window = state::TrimWindow::new(len);
+    println!("  {}  in|out <sec> or +/-<sec>, p (play), ok, x (cancel)", "TRIM TAKE".underline());
+
+    loop {
+        print!("  in {:.2}s  out {:.2}s  ({:.2}s of {:.2}s) {} ",
+            secs(window.start), secs(window.end), secs(window.end - window.start), secs(len), "✂".yellow());
+        use std::io::Write;
+        std::io::stdout().flush().unwrap();
+
+        let Some(input) = read_line() else { return; };
+        let parts: Vec<&str> = input.split_whitespace().collect();
+        // "+0.1" / "-0.1" move the point, a bare number sets it
+        let amount = parts.get(1).and_then(|a| {
+            let samples = (a.parse::<f32>().ok()? * sample_rate as f32).round() as i64;
+            Some((a.starts_with(['+', '-']), samples))
+        });
+        match (parts.first().copied(), amount) {
+            (Some("in"), Some((true, delta))) => window.nudge_start(delta),
+            (Some("in"), Some((false, at))) => window.set_start(at),
+            (Some("out"), Some((true, delta))) => window.nudge_end(delta),
+            (Some("out"), Some((false, at))) => window.set_end(at),
+            (Some("p"), _) => {}
+            (Some("ok"), _) => {
+                app.recorder.lock().unwrap().apply_trim_window(window);
+                return;
+            }
+            (Some("x"), _) => return,
+            _ => { println!("  in|out <sec> | in|out +/-<sec> | p | ok | x"); continue; }
+        }
+        app.preview_trim_window(window);
+    }
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--list-devices") {
+        for name in audio_input::input_device_names() { println!("{}", name); }
+        return;
+    }
     let use_gui = args.iter().any(|a| a == "--gui");
-    if use_gui { run_gui(); } else { run_cli(); }
+    let settings = settings_from_args(&args);
+    if use_gui { run_gui(settings); } else { run_cli(settings); }
 }
 
-fn run_gui() {
+// startup flags that have to be known before the input stream is opened
+//   --config booth.toml   load settings from a file (see config.rs)
+//   --channels 3,4        capture (and down-mix) only hardware inputs 3 and 4
+//   --realtime            ask for real-time scheduling of the input thread
+//   --device "USB Audio"  record from this input device (--list-devices shows them)
+//   --stereo              keep two input channels as stereo instead of down-mixing
+//   --rate 44100          record at this rate whatever the device runs at
+// the config file is applied first so flags on the command line win
+fn settings_from_args(args: &[String]) -> Settings {
+    let mut settings = Settings::default();
+    if let Some(path) = args.iter().position(|a| a == "--config").and_then(|i| args.get(i + 1)) {
+        match config::load_config(path, &mut settings) {
+            Ok(warnings) => for w in warnings { eprintln!("{}: {}", path, w); },
+            Err(e) => eprintln!("Couldn't read config {}: {}", path, e),
+        }
+    }
+    let mut it = args.iter().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--config" => { it.next(); } // already loaded
+            "--realtime" => settings.realtime_priority = true,
+            "--stereo" => settings.stereo = true,
+            "--device" => match it.next() {
+                Some(name) => settings.input_device = Some(name.clone()),
+                None => eprintln!("--device expects a device name, see --list-devices"),
+            },
+            "--rate" => match it.next().and_then(|v| v.parse::<u32>().ok()).filter(|&r| r > 0) {
+                Some(rate) => settings.target_sample_rate = Some(rate),
+                None => eprintln!("--rate expects a sample rate in Hz, e.g. --rate 44100"),
+            },
+            "--channels" => match it.next().and_then(|v| config::parse_channel_list(v)) {
+                Some(chans) => settings.input_channels = Some(chans),
+                None => eprintln!("--channels expects 1-based channel numbers, e.g. --channels 3,4"),
+            },
+            _ => {}
+        }
+    }
+    settings
+}
+
+fn run_gui(settings: Settings) {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_title("Parts Of Speech")
@@ -207,16 +727,73 @@ fn run_gui() {
         Box::new(|cc| {
             let ctx = cc.egui_ctx.clone();
             let on_new_data = move || ctx.request_repaint();
-            let app = RecorderApp::new(on_new_data);
+            let app = RecorderApp::new(settings, on_new_data);
             Ok(Box::new(app))
         }),
     ).expect("Failed to launch GUI");
 }
 
-fn run_cli() {
-    let app = RecorderApp::new(|| {});
+// the command list under the CLI banner, also what `h` prints
+fn print_help() {
+    println!("\n{}", "  COMMANDS".underline());
+    let commands = [
+        ("r",  "Record segment",   "s",  "Stop & Auto-play"),
+        ("p",  "Play (last/#n)",   "pa", "Play full project"),
+        ("c",  "Confirm take",     "x",  "Reject take"),
+        ("t",  "Try again",        "q",  "List segments"),
+        ("u",  "Undo",             "z",  "Redo"),
+        ("pc", "Preview take [xN]", "repeats", "Review repeat count"),
+        ("stop", "Stop playback",  "tw", "Trim take by ear"),
+        ("pause", "Pause take",    "resume", "Resume take"),
+        ("restore", "Undo a reject", "h", "This list"),
+    ];
+
+    for (cmd1, desc1, cmd2, desc2) in commands {
+        println!("    {:>2} {:<18} {:>6} {:<18}", 
+            cmd1.bright_green(), desc1.dimmed(),
+            cmd2.bright_green(), desc2.dimmed()
+        );
+    }
+    println!("\n  {}  {} <secs> | {} #n", "TRIM:".dimmed(), "trim start|end".yellow(), "delete".red());
+    println!("  {}  {}", "EDIT:".dimmed(), "split | autosplit | merge | copy | paste | move | dup | rev | name | gain | pan | mute | norm | hpf | stretch | pitch | dc | sil | clear".yellow());
+    println!("  {}  {}", "FILE:".dimmed(), "save | open | import | es <dir> | ef <path> | package <dir>".yellow());
+    println!("  {}  {}", "VIEW:".dimmed(), "waveform <n> | stats <n> | spark <cols> (q sparkline width) | h (this list)".yellow());
+    println!("  {}  {}", "EXIT:".dimmed(), "e (export) | quit".red());
+    println!("{}", "-".repeat(60).cyan());
+}
+
+fn run_cli(settings: Settings) {
+    let mut app = RecorderApp::new(settings, || {});
     let mut clear = true;
 
+    // a snapshot left behind means that session never exited cleanly.
+    // only one can be restored, the others stay for a later start
+    let recovery_dir = std::env::temp_dir();
+    app.recorder.lock().unwrap().recovery_dir = Some(recovery_dir.clone());
+    for leftover in project_io::leftover_recoveries(&recovery_dir) {
+        let name = leftover.file_name().unwrap_or_default().to_string_lossy().to_string();
+        clear = false; // keep the outcome on screen
+        if !confirm(&format!("A session didn't exit cleanly ({}). Restore its unexported segments?", name)) {
+            let _ = std::fs::remove_file(&leftover);
+            continue;
+        }
+        let restored = app.recorder.lock().unwrap().restore_recovery(&leftover);
+        match restored {
+            Ok(n) => {
+                let _ = std::fs::remove_file(&leftover); // now in this session's snapshot
+                println!("Restored {} segment(s)", n);
+                break;
+            }
+            // kept under another name, left for a look instead of offered again
+            Err(e) => match project_io::set_aside(&leftover) {
+                Ok(aside) => eprintln!("Failed to restore {}: {} (kept as {})", name, e, aside.display()),
+                Err(_) => eprintln!("Failed to restore {}: {}", name, e),
+            },
+        }
+    }
+    if !clear { print_help(); }
+    interrupt::install();
+
     loop {
         // Clear the screen and move cursor to home position
         if clear {
@@ -226,63 +803,64 @@ fn run_cli() {
             println!("  {} — {}", "PARTS OF SPEECH".bold().bright_white(), "CLI Mode".italic());
             println!("{}", "  (run with --gui for the graphical interface)".dimmed());
             println!("{}", "=".repeat(60).cyan());
-            println!("\n{}", "  COMMANDS".underline());
-            let commands = [
-                ("r",  "Record segment",   "s",  "Stop & Auto-play"),
-                ("p",  "Play (last/#n)",   "pa", "Play full project"),
-                ("c",  "Confirm take",     "x",  "Reject take"),
-                ("t",  "Try again",        "q",  "List segments"),
-                ("u",  "Undo",             "z",  "Redo"),
-            ];
-
-            for (cmd1, desc1, cmd2, desc2) in commands {
-                println!("    {:>2} {:<18} {:>6} {:<18}", 
-                    cmd1.bright_green(), desc1.dimmed(),
-                    cmd2.bright_green(), desc2.dimmed()
-                );
-            }
-            println!("\n  {}  {} <secs> | {} #n", "TRIM:".dimmed(), "trim start|end".yellow(), "delete".red());
-            println!("  {}  {}", "EXIT:".dimmed(), "e (export) | quit".red());
-            println!("{}", "-".repeat(60).cyan());
+            print_help();
         }
         clear = true;
 
-        let prompt = {
-            let rec = app.recorder.lock().unwrap();
-            let count = rec.get_segment_count();
-            let total_time = rec.total_duration();
-            let playing = rec.playback_state == PlaybackState::Playing;
-            
-            match rec.state {
-                state::AppState::Recording => 
-                    format!(" {} {} ", "●".red().blink(), "RECORDING".red().bold()),
-                state::AppState::Reviewing => 
-                    format!(" {} {} ", "▶".blue(), "REVIEWING".blue().bold()),
-                state::AppState::Idle if playing => 
-                    format!(" {} {} ({} segs)", "".green(), "PLAYING".green(), count),
-                state::AppState::Idle => 
-                    format!(" {} {} ({} segs, {})", "○".dimmed(), "IDLE".dimmed(), count, total_time),
-            }
-        };
+        let prompt = build_prompt(&app.recorder.lock().unwrap());
 
         print!("{} {} ", prompt, "❯".bright_cyan());
         use std::io::Write;
         std::io::stdout().flush().unwrap();
 
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input).unwrap();
-        let parts: Vec<&str> = input.trim().split_whitespace().collect();
+        let Some(input) = read_line() else { break; }; // Ctrl+D quits, Ctrl+C below
+        let parts: Vec<&str> = input.split_whitespace().collect();
         if parts.is_empty() { continue; }
 
+        // edit commands that map straight onto a Command
+        if let Some(parsed) = args::parse_command(&parts) {
+            match parsed {
+                // deleting can lose a good take, a plain `delete` asks first
+                Ok(Command::DeleteSegment(i)) if parts[0] == "delete" && !parts.contains(&"--force") => {
+                    let samples = app.recorder.lock().unwrap().get_segment(i).map(|s| s.samples.len());
+                    match samples {
+                        Some(n) if confirm(&format!("Delete segment {} ({} samples)?", i + 1, n)) =>
+                            app.handle_command(Command::DeleteSegment(i)),
+                        Some(_) => { println!("Kept segment {}", i + 1); clear = false; }
+                        None => { println!("  {} no segment {}", "×".red(), i + 1); clear = false; }
+                    }
+                }
+                Ok(Command::SetPan(..)) if app.recorder.lock().unwrap().project.channels != 2 => {
+                    println!("  {} pan needs a stereo project, mono takes play centred", "×".red());
+                    clear = false;
+                }
+                Ok(cmd) => app.handle_command(cmd),
+                Err(e) => { arg_error(&e); clear = false; }
+            }
+            continue;
+        }
+
         match parts[0] {
             "r"  => app.handle_command(Command::StartRecording),
             "s"  => app.handle_command(Command::StopRecording),
+            "restore" => app.handle_command(Command::RestoreRejected),
+            "pause"  => app.handle_command(Command::PauseRecording),
+            "resume" => app.handle_command(Command::ResumeRecording),
             "c"  => app.handle_command(Command::Approve),
             "x"  => app.handle_command(Command::Reject),
             "t"  => app.handle_command(Command::RetryCurrentTake),
-            "u"  => app.handle_command(Command::Undo),
-            "z"  => app.handle_command(Command::Redo),
-            "pa" => app.handle_command(Command::PlayAll),
+            "u" | "undo" => app.handle_command(Command::Undo),
+            "z" | "redo" => app.handle_command(Command::Redo),
+            "pa" => {
+                // pa [gap], seconds of silence between takes to hear where each ends
+                match args::opt_num::<f32>(&parts, 1, "gap (seconds)") {
+                    Ok(gap) if (0.0..=audio_output::MAX_GAP_SECONDS).contains(&gap.unwrap_or(0.0)) =>
+                        app.handle_command(Command::PlayAll(gap.unwrap_or(0.0))),
+                    Ok(_) => { println!("  {} gap must be between 0 and {}s", "×".red(), audio_output::MAX_GAP_SECONDS); clear = false; }
+                    Err(e) => { arg_error(&e); clear = false; }
+                }
+            }
+            "stop" => app.handle_command(Command::StopPlayback),
 
             // "p" is context-sensitive, during Reviewing it calls play_current_segment()
             // directly (listen again), during Idle it plays a specific or last committed segment
@@ -295,37 +873,321 @@ fn run_cli() {
                 );
                 if is_reviewing {
                     app.play_current_segment();
-                } else if let Some(idx_str) = parts.get(1) {
-                    if let Ok(n) = idx_str.parse::<usize>() {
-                        app.handle_command(Command::PlaySegment(n - 1));
+                } else {
+                    match args::opt_segment(&parts, 1) {
+                        // p <n> @<seconds>, start partway in
+                        Ok(Some(idx)) if parts.get(2).is_some_and(|a| a.starts_with('@')) => {
+                            match parts[2][1..].parse::<f32>() {
+                                Ok(secs) if secs >= 0.0 => app.handle_command(Command::PlaySegmentFrom(idx, secs)),
+                                _ => arg_error(&format!("`{}` isn't a start time, e.g. @12.5", parts[2])),
+                            }
+                            clear = false;
+                        }
+                        Ok(Some(idx)) => app.handle_command(Command::PlaySegment(idx)),
+                        // not reviewing and index isn't passed, just play last segment
+                        Ok(None) => {
+                            let count = app.recorder.lock().unwrap().get_segment_count();
+                            if count > 0 {
+                                app.handle_command(Command::PlaySegment(count - 1));
+                            } else {
+                                println!("No segments recorded yet.");
+                            }
+                        }
+                        Err(e) => { arg_error(&e); clear = false; }
+                    }
+                }
+            }
+            "seek" => {
+                // seek <n> <seconds>, same as p <n> @<seconds>
+                match (|| Ok::<_, String>((args::segment(&parts, 1)?, args::num::<f32>(&parts, 2, "seconds")?)))() {
+                    Ok((_, secs)) if secs < 0.0 => arg_error("seconds can't be negative"),
+                    Ok((idx, secs)) => app.handle_command(Command::PlaySegmentFrom(idx, secs)),
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "loop" => {
+                // loop <n> [times], without a count it repeats until `stop`
+                let count = app.recorder.lock().unwrap().get_segment_count();
+                let parsed = (|| Ok::<_, String>((args::segment(&parts, 1)?, args::opt_num::<u32>(&parts, 2, "times")?)))();
+                match parsed {
+                    Ok((idx, _)) if idx >= count => println!("  {} only {} segment(s)", "×".red(), count),
+                    Ok((idx, times)) => {
+                        if times.is_none() { println!("Looping segment {}, `stop` to end it", idx + 1); }
+                        app.handle_command(Command::LoopSegment(idx, times));
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "pr" => {
+                // pr <start> <end>: segments start..=end back to back
+                let count = app.recorder.lock().unwrap().get_segment_count();
+                let parsed = (|| Ok::<_, String>((args::segment(&parts, 1)?, args::segment(&parts, 2)?)))();
+                match parsed {
+                    Ok((start, end)) if start > end => println!("  {} start must not be after end", "×".red()),
+                    Ok((_, end)) if end >= count => println!("  {} only {} segment(s)", "×".red(), count),
+                    Ok((start, end)) => app.handle_command(Command::PlayRange(start, end)),
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            // repeatable review playback, `preview-current 3` plays the take three times,
+            // without a count it falls back to the review_repeats setting
+            "preview-current" | "pc" => {
+                let default = app.recorder.lock().unwrap().settings.review_repeats;
+                match args::opt_num::<u32>(&parts, 1, "repeats").map(|n| n.unwrap_or(default)) {
+                    Ok(n) if app.recorder.lock().unwrap().state == state::AppState::Reviewing =>
+                        app.preview_current(n),
+                    Ok(_) => println!("Nothing to preview, stop a recording first."),
+                    Err(e) => { arg_error(&e); clear = false; }
+                }
+            }
+            "tw" => { trim_review_loop(&app); }
+            "roll" => {
+                // roll <ms>: pre/post roll from the neighbours when playing `p <n>`, 0 = off
+                match args::ms(&parts, 1, "milliseconds") {
+                    Ok(ms) => app.recorder.lock().unwrap().settings.context_ms = ms,
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "ingain" => {
+                // input boost for a quiet mic, takes effect on the next input buffer
+                match args::num::<f32>(&parts, 1, "gain") {
+                    Ok(g) if g > 0.0 => {
+                        app.recorder.lock().unwrap().settings.input_gain = g;
+                        println!("Input gain x{} ({:+.1} dB)", g, 20.0 * g.log10());
                     }
-                } else { // not reviewing and index isn't passed, just play last segment
-                    let count = app.recorder.lock().unwrap().get_segment_count();
-                    if count > 0 {
-                        app.handle_command(Command::PlaySegment(count - 1));
-                    } else {
-                        println!("No segments recorded yet.");
+                    Ok(_) => println!("  {} gain must be above 0", "×".red()),
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "gate" => {
+                // gate <threshold>, 0 turns it off. applied as takes are captured
+                match args::num::<f32>(&parts, 1, "threshold") {
+                    Ok(t) => {
+                        let t = t.abs();
+                        app.recorder.lock().unwrap().settings.noise_gate = t;
+                        if t > 0.0 { println!("Noise gate at {}", t); } else { println!("Noise gate off"); }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "copy" => {
+                // copy <n> <start> <end>, called directly for the feedback, `paste` uses it
+                let parsed = (|| Ok::<_, String>((
+                    args::segment(&parts, 1)?,
+                    args::num::<f32>(&parts, 2, "start")?,
+                    args::num::<f32>(&parts, 3, "end")?,
+                )))();
+                match parsed {
+                    Ok((i, start, end)) => match app.recorder.lock().unwrap().copy_range(i, start, end) {
+                        Some(_) => println!("Copied {:.2}s of segment {}", end - start, i + 1),
+                        None => println!("  {} {}s-{}s isn't a range inside segment {}", "×".red(), start, end, i + 1),
+                    },
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "meta" => {
+                // meta <field> [text...]  sets a tag, no text removes it; meta alone lists them
+                match parts.get(1).copied() {
+                    None => {
+                        let rec = app.recorder.lock().unwrap();
+                        let tags = rec.project.metadata.info_tags();
+                        let m = &rec.project.metadata;
+                        if tags.is_empty() { println!("No tags set (meta title|artist|comment|date <text>)"); }
+                        for (name, value) in state::ProjectMetadata::FIELDS.iter().zip([&m.title, &m.artist, &m.comment, &m.date]) {
+                            if let Some(v) = value { println!("  {:<8} {}", name, v); }
+                        }
+                    }
+                    Some(field) if state::ProjectMetadata::FIELDS.contains(&field) => {
+                        let text = (parts.len() > 2).then(|| parts[2..].join(" "));
+                        app.handle_command(Command::SetMetadata(field.to_string(), text));
                     }
+                    Some(field) => println!("  {} unknown tag `{}`, use title, artist, comment or date", "×".red(), field),
                 }
+                clear = false;
+            }
+            "xfade" => {
+                // xfade <ms>, 0 for hard cuts between segments
+                match args::ms(&parts, 1, "ms") {
+                    Ok(ms) => {
+                        app.recorder.lock().unwrap().settings.export.crossfade_ms = ms;
+                        if ms > 0.0 { println!("Crossfade {}ms between segments", ms); } else { println!("Hard cuts between segments"); }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "bfade" => {
+                // bfade <ms>, a short fade either side of each join, 0 = off
+                match args::ms(&parts, 1, "ms") {
+                    Ok(ms) => {
+                        app.recorder.lock().unwrap().settings.export.boundary_fade_ms = ms;
+                        if ms > 0.0 { println!("{}ms fades at the joins between segments", ms); } else { println!("No fades at the joins"); }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
             }
-            "retry"  => {
-                if let Some(n) = parts.get(1).and_then(|s| s.parse::<usize>().ok()) {
-                    app.handle_command(Command::RetrySegment(n - 1));
+            "vol" => {
+                // playback volume, the recording and export aren't touched
+                match args::num::<f32>(&parts, 1, "volume") {
+                    Ok(v) => {
+                        let v = v.clamp(0.0, audio_output::MAX_VOLUME);
+                        app.recorder.lock().unwrap().settings.playback.volume = v;
+                        println!("Playback volume {:.2}", v);
+                    }
+                    Err(e) => arg_error(&e),
                 }
+                clear = false;
+            }
+            "speed" => {
+                // faster (or slower) playback for skimming, pitch follows the speed
+                match args::num::<f32>(&parts, 1, "speed") {
+                    Ok(x) if audio_output::SPEED_RANGE.contains(&x) => {
+                        app.recorder.lock().unwrap().settings.playback.speed = x;
+                        println!("Playback speed {}x", x);
+                    }
+                    Ok(_) => println!("  {} speed must be between {} and {}", "×".red(),
+                        audio_output::SPEED_RANGE.start(), audio_output::SPEED_RANGE.end()),
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "repeats" => {
+                // default repeat count for `p` / `pc` while reviewing
+                match args::num::<u32>(&parts, 1, "count") {
+                    Ok(n) => app.recorder.lock().unwrap().settings.review_repeats = n.max(1),
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "odev" => {
+                // odev lists output devices, odev <name> plays through one, odev default resets
+                let name = parts[1..].join(" ");
+                let mut rec = app.recorder.lock().unwrap();
+                if name.is_empty() {
+                    let current = rec.settings.output_device.clone();
+                    for dev in audio_output::output_device_names() {
+                        let mark = if current.as_deref() == Some(dev.as_str()) { "*" } else { " " };
+                        println!("  {} {}", mark.bright_green(), dev);
+                    }
+                    if current.is_none() { println!("  (playing through the default device)"); }
+                } else if name == "default" {
+                    rec.settings.output_device = None;
+                    println!("Playing through the default output device");
+                } else {
+                    match audio_output::resolve_output_device_name(&name) {
+                        Ok(full) => {
+                            println!("Playing through {}", full);
+                            rec.settings.output_device = Some(full);
+                        }
+                        Err(e) => println!("  {} {}", "×".red(), e),
+                    }
+                }
+                clear = false;
+            }
+            "stats" => {
+                // stats <n>: levels as heard (gain included), to decide on norm or gain
+                match args::segment(&parts, 1) {
+                    Ok(i) => {
+                        let rec = app.recorder.lock().unwrap();
+                        match rec.get_segment(i) {
+                            Some(seg) => {
+                                let stats = dsp::LevelStats::of(&seg.gained());
+                                let db = |level: f32| dsp::linear_to_db(level)
+                                    .map_or("silent".to_string(), |db| format!("{:.1} dBFS", db));
+                                println!("  Segment {}: {} samples ({})", i + 1, stats.samples,
+                                    format_seconds(seg.duration_seconds(rec.project.sample_rate, rec.project.channels)));
+                                println!("    peak {:.4}  {}", stats.peak, db(stats.peak));
+                                println!("    rms  {:.4}  {}", stats.rms, db(stats.rms));
+                            }
+                            None => println!("No segment {}", i + 1),
+                        }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "mute" => {
+                // mute <n> toggles: muted takes stay in the project but not in `pa` or exports
+                match args::segment(&parts, 1) {
+                    Ok(i) => {
+                        let mut rec = app.recorder.lock().unwrap();
+                        match rec.get_segment(i).map(|s| s.muted) {
+                            Some(true) => { rec.unmute_segment(i); rec.save_state(); println!("Segment {} unmuted", i + 1); }
+                            Some(false) => { rec.mute_segment(i); rec.save_state(); println!("Segment {} muted", i + 1); }
+                            None => println!("No segment {}", i + 1),
+                        }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
             }
-            "insert" => {
-                if let Some(n) = parts.get(1).and_then(|s| s.parse::<usize>().ok()) {
-                    app.handle_command(Command::InsertAfter(n - 1));
+            "autosplit" => {
+                // autosplit <n> [threshold] [min_silence_sec], cut a long take at its pauses
+                let parsed = (|| Ok::<_, String>((
+                    args::segment(&parts, 1)?,
+                    args::opt_num(&parts, 2, "threshold")?.unwrap_or(0.01),
+                    args::opt_num(&parts, 3, "min silence (seconds)")?.unwrap_or(0.5),
+                )))();
+                match parsed {
+                    Ok((i, threshold, min_silence)) => {
+                        let mut rec = app.recorder.lock().unwrap();
+                        match rec.auto_split(i, threshold, min_silence) {
+                            0 if !rec.can_restructure() => println!("{}", TAKE_PENDING),
+                            0 => println!("No segment {}", i + 1),
+                            1 => println!("Segment {} has no pause of {}s or more, left as is", i + 1, min_silence),
+                            n => {
+                                rec.save_state();
+                                println!("Segment {} split into {} (now {}-{})", i + 1, n, i + 1, i + n);
+                            }
+                        }
+                    }
+                    Err(e) => arg_error(&e),
                 }
+                clear = false;
             }
-            "delete" => {
-                if let Some(n) = parts.get(1).and_then(|s| s.parse::<usize>().ok()) {
-                    app.handle_command(Command::DeleteSegment(n - 1));
+            // "trim <n> [threshold] [pad_ms]" strips silence from both ends
+            "trim" if parts.get(1).is_some_and(|a| a.parse::<usize>().is_ok()) => {
+                // called directly (not via dispatch) so a take that was left alone says so
+                let default_pad = app.recorder.lock().unwrap().settings.trim_pad_ms;
+                let parsed = (|| Ok::<_, String>((
+                    args::segment(&parts, 1)?,
+                    args::opt_num(&parts, 2, "threshold")?.unwrap_or(0.01),
+                    args::opt_ms(&parts, 3, "pad (ms)")?.unwrap_or(default_pad),
+                )))();
+                match parsed {
+                    Ok((i, threshold, pad_ms)) => {
+                        let mut rec = app.recorder.lock().unwrap();
+                        let (rate, ch) = (rec.project.sample_rate, rec.project.channels);
+                        let Some(before) = rec.get_segment(i).map(|s| s.duration_seconds(rate, ch)) else {
+                            println!("No segment {}", i + 1);
+                            clear = false;
+                            continue;
+                        };
+                        if rec.trim_silence(i, threshold, pad_ms) {
+                            rec.save_state();
+                            let after = rec.project.segments[i].duration_seconds(rate, ch);
+                            println!("Segment {} trimmed: {:.2}s -> {:.2}s", i + 1, before, after);
+                        } else {
+                            // never above the threshold: a quiet take, not an empty one
+                            println!("Segment {} never rises above {}, left as is", i + 1, threshold);
+                        }
+                    }
+                    Err(e) => arg_error(&e),
                 }
+                clear = false;
             }
             "trim" => {
                 if parts.len() < 3 { // requires minimum 3 parts trim + pos + ...
                     println!("Usage: trim start|end [segment_number] seconds");
+                    println!("       trim <segment_number> [threshold] [pad_ms]");
                     println!("Examples: trim start 0.5  (trim current segment)");
                     println!("          trim end 2 0.3  (trim segment #2)");
                     continue;
@@ -333,36 +1195,33 @@ fn run_cli() {
 
                 let trim_type = parts[1]; // start or end
                 let mut segment_index: Option<usize> = None;
-                let seconds_str: &str;
 
                 // could be "trim start 0.5"(current segment) or "trim start 2 0.5"
-                if parts.len() == 3 { // current segment
-                    seconds_str = parts[2];
+                let seconds_at = if parts.len() == 3 { // current segment
+                    2
                 } else if parts.len() == 4 { // idx passed in
-                    if let Ok(idx) = parts[2].parse::<usize>() {
-                        segment_index = Some(idx - 1); // Convert to 0-based
-                        seconds_str = parts[3];
-                    } else {
-                        println!("Invalid segment number.");
-                        continue;
+                    match args::segment(&parts, 2) {
+                        Ok(idx) => { segment_index = Some(idx); 3 }
+                        Err(e) => { arg_error(&e); clear = false; continue; }
                     }
                 } else {
                     println!("Too many arguments.");
                     continue;
-                }
+                };
 
-                if let Ok(secs) = seconds_str.parse::<f32>() {
-                    let cmd = match trim_type { // get specific command
-                        "start" => Command::TrimStart(segment_index, secs),
-                        "end" => Command::TrimEnd(segment_index, secs),
-                        _ => {
-                            println!("Unknown trim type. Use 'start' or 'end'.");
-                            continue;
-                        }
-                    };
-                    app.handle_command(cmd);
-                } else {
-                    println!("Invalid seconds value.");
+                match args::num::<f32>(&parts, seconds_at, "seconds") {
+                    Ok(secs) => {
+                        let cmd = match trim_type { // get specific command
+                            "start" => Command::TrimStart(segment_index, secs),
+                            "end" => Command::TrimEnd(segment_index, secs),
+                            _ => {
+                                println!("Unknown trim type. Use 'start' or 'end'.");
+                                continue;
+                            }
+                        };
+                        app.handle_command(cmd);
+                    }
+                    Err(e) => { arg_error(&e); clear = false; }
                 }
             }
             "q" => {
@@ -371,28 +1230,494 @@ fn run_cli() {
                     println!("  {}", "No segments recorded yet.".italic().dimmed());
                 } else {
                     println!("\n  {}", "PROJECT SEGMENTS".underline());
+                    let width = rec.settings.sparkline_width;
                     for (i, seg) in rec.project.segments.iter().enumerate() {
-                        let dur = seg.duration_seconds(rec.project.sample_rate);
+                        let dur = seg.duration_seconds(rec.project.sample_rate, rec.project.channels);
+                        let gain = if seg.gain != 1.0 { format!(" x{:.2}", seg.gain) } else { String::new() };
+                        let clipped = if seg.clipped_samples() > 0 { " clipped" } else { "" };
+                        let muted = if seg.muted { " muted" } else { "" };
+                        let age = seg.recorded_at
+                            .and_then(|t| t.elapsed().ok())
+                            .map(|d| format!(" {}", format_age(d)))
+                            .unwrap_or_default();
                         println!(
-                            "  {:>2}. [{}] {:>5.2}s  {}", 
+                            "  {:>2}. [{}] {:>7}  {}{}{}{}{} {}", 
                             (i + 1).to_string().bright_white(),
-                            "■".repeat((dur as usize).min(10)).green(), // simple "sparkline"
-                            dur,
-                            format!("({} samples)", seg.samples.len()).dimmed()
+                            visualize::sparkline(&seg.waveform(width)).green(),
+                            format_seconds(dur),
+                            format!("({} samples)", seg.samples.len()).dimmed(),
+                            age.dimmed(),
+                            gain.yellow(),
+                            clipped.red(),
+                            muted.magenta(),
+                            seg.name.as_deref().unwrap_or("").cyan()
                         );
                     }
+                    println!("  {} {}", "Total:".dimmed(), rec.total_duration().bright_white());
                     println!();
                 }
                 clear = false;
             }
+            "waveform" => {
+                // waveform <n>: a rough picture of where the loud parts are, full-scale columns in red
+                match args::segment(&parts, 1) {
+                    Ok(i) => {
+                        let rec = app.recorder.lock().unwrap();
+                        match rec.get_segment(i) {
+                            Some(seg) => {
+                                let peaks: Vec<f32> = seg.waveform(WAVEFORM_COLUMNS).iter().map(|p| p * seg.gain).collect();
+                                for row in visualize::waveform_rows(&peaks, WAVEFORM_ROWS) {
+                                    let line: String = row.chars().zip(&peaks)
+                                        .map(|(c, &p)| if p >= 1.0 { c.to_string().red().to_string() } else { c.to_string().green().to_string() })
+                                        .collect();
+                                    println!("  │{}", line);
+                                }
+                                let dur = seg.duration_seconds(rec.project.sample_rate, rec.project.channels);
+                                println!("  └{}", "─".repeat(WAVEFORM_COLUMNS));
+                                println!("   0s{:>width$}", format_seconds(dur), width = WAVEFORM_COLUMNS - 2);
+                            }
+                            None => println!("  {} no segment {}", "×".red(), i + 1),
+                        }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "reference" => {
+                // reference <db> [n]: 1kHz tone at <db> dBFS, then segment n (default last)
+                let count = app.recorder.lock().unwrap().get_segment_count();
+                let parsed = (|| Ok::<_, String>((
+                    args::num::<f32>(&parts, 1, "level (dBFS)")?,
+                    args::opt_segment(&parts, 2)?.unwrap_or(count.saturating_sub(1)),
+                )))();
+                match parsed {
+                    Ok(_) if count == 0 => println!("No segments recorded yet."),
+                    Ok((db, _)) if db > 0.0 => println!("  {} level must be 0 dBFS or below, e.g. -18", "×".red()),
+                    Ok((db, i)) => app.handle_command(Command::PlayReference(db, i)),
+                    Err(e) => { arg_error(&e); clear = false; }
+                }
+            }
+            "declick" => {
+                // declick <n> [sensitivity], called directly (not via dispatch) to report the count
+                let parsed = (|| Ok::<_, String>((
+                    args::segment(&parts, 1)?,
+                    args::opt_num(&parts, 2, "sensitivity")?.unwrap_or(1.0),
+                )))();
+                match parsed {
+                    Ok((i, sensitivity)) => {
+                        let mut rec = app.recorder.lock().unwrap();
+                        let repaired = rec.declick(i, sensitivity);
+                        if repaired > 0 { rec.save_state(); }
+                        println!("Repaired {} click(s) in segment {}", repaired, i + 1);
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "dups" => {
+                // segments with byte-identical audio, e.g. an approve pressed twice
+                let groups = app.recorder.lock().unwrap().project.duplicate_groups();
+                if groups.is_empty() {
+                    println!("  {}", "No duplicate segments.".italic().dimmed());
+                }
+                for group in groups {
+                    let numbers: Vec<String> = group.iter().map(|i| format!("#{}", i + 1)).collect();
+                    println!("  {} identical: {}", "⚠".yellow(), numbers.join(", "));
+                }
+                clear = false;
+            }
+            "gaps" => {
+                // silence at each join, for checking the pacing of a take sequence
+                let threshold = match args::opt_num(&parts, 1, "threshold") {
+                    Ok(t) => t.unwrap_or(0.01),
+                    Err(e) => { arg_error(&e); clear = false; continue; }
+                };
+                let rec = app.recorder.lock().unwrap();
+                let gaps = rec.project.inter_segment_gaps(threshold);
+                if gaps.is_empty() {
+                    println!("  {}", "Need at least two segments.".italic().dimmed());
+                } else {
+                    println!("\n  {}", "GAPS".underline());
+                    for (i, gap) in gaps.iter().enumerate() {
+                        println!("  {:>2} → {:<2} {:>5.2}s", i + 1, i + 2, gap);
+                    }
+                    let avg = gaps.iter().sum::<f32>() / gaps.len() as f32;
+                    println!("  {}\n", format!("avg {:.2}s", avg).dimmed());
+                }
+                clear = false;
+            }
+            "log" => {
+                // edit audit for one segment, oldest first
+                let rec = app.recorder.lock().unwrap();
+                match args::segment(&parts, 1).map(|i| rec.get_segment(i)) {
+                    Ok(Some(seg)) if seg.history.is_empty() =>
+                        println!("  {}", "No edits applied to this segment.".italic().dimmed()),
+                    Ok(Some(seg)) => {
+                        for (i, entry) in seg.history.iter().enumerate() {
+                            println!("  {:>2}. {}", i + 1, entry);
+                        }
+                    }
+                    Ok(None) => println!("  {} no such segment", "×".red()),
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "autoexport" => {
+                // autoexport segs <n> | autoexport mins <m> | autoexport off
+                let mut rec = app.recorder.lock().unwrap();
+                match parts.get(1).copied() {
+                    Some("segs") => match args::num::<usize>(&parts, 2, "count") {
+                        Ok(n) => rec.settings.auto_export_segments = Some(n.max(1)),
+                        Err(e) => arg_error(&e),
+                    },
+                    Some("mins") => match args::num::<f32>(&parts, 2, "minutes") {
+                        Ok(m) if m > 0.0 => rec.settings.auto_export_seconds = Some(m * 60.0),
+                        Ok(_) => println!("  {} minutes must be above 0", "×".red()),
+                        Err(e) => arg_error(&e),
+                    },
+                    Some("off") => {
+                        rec.settings.auto_export_segments = None;
+                        rec.settings.auto_export_seconds = None;
+                    }
+                    _ => println!("Usage: autoexport segs <count> | mins <minutes> | off"),
+                }
+                clear = false;
+            }
+            "trimpad" => {
+                // default silence kept either side of `trim <n>`
+                match args::ms(&parts, 1, "milliseconds") {
+                    Ok(ms) => app.recorder.lock().unwrap().settings.trim_pad_ms = ms,
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
+            "minlen" => {
+                // minlen <ms> [warn|reject] | minlen off
+                let mut rec = app.recorder.lock().unwrap();
+                match (parts.get(1).copied(), parts.get(2).copied()) {
+                    (Some("off"), _) => rec.settings.min_segment_ms = None,
+                    (Some(_), mode @ (None | Some("warn") | Some("reject"))) => match args::ms(&parts, 1, "ms") {
+                        Ok(ms) => {
+                            rec.settings.min_segment_ms = Some(ms);
+                            rec.settings.reject_short = mode == Some("reject");
+                        }
+                        Err(e) => arg_error(&e),
+                    },
+                    _ => println!("Usage: minlen <ms> [warn|reject] | minlen off"),
+                }
+                clear = false;
+            }
+            "dither" => {
+                // dither on [seed] | dither off, a seed makes exports byte-identical
+                let mut rec = app.recorder.lock().unwrap();
+                match parts.get(1).copied() {
+                    Some("on") => match args::opt_num::<u64>(&parts, 2, "seed") {
+                        Ok(seed) => {
+                            rec.settings.export.dither = true;
+                            rec.settings.export.dither_seed = seed;
+                        }
+                        Err(e) => arg_error(&e),
+                    },
+                    Some("off") => rec.settings.export.dither = false,
+                    _ => println!("Usage: dither on [seed] | dither off"),
+                }
+                clear = false;
+            }
+            "limit" => {
+                // limit on|off: soften overs on export instead of clipping them
+                match parts.get(1).copied() {
+                    Some(v @ ("on" | "off")) => app.recorder.lock().unwrap().settings.export.limit = v == "on",
+                    _ => println!("Usage: limit on|off"),
+                }
+                clear = false;
+            }
+            "autotrim" => {
+                // autotrim <threshold> | autotrim off, pad comes from `trimpad`
+                let mut rec = app.recorder.lock().unwrap();
+                match parts.get(1).copied() {
+                    Some("off") => rec.settings.auto_trim = None,
+                    Some(_) => match args::num::<f32>(&parts, 1, "threshold") {
+                        Ok(t) if t > 0.0 => rec.settings.auto_trim = Some(t),
+                        Ok(_) => println!("  {} threshold must be above 0", "×".red()),
+                        Err(e) => arg_error(&e),
+                    },
+                    None => println!("Usage: autotrim <threshold> | autotrim off"),
+                }
+                clear = false;
+            }
+            "maxlen" => {
+                // maxlen <seconds> | maxlen off
+                let mut rec = app.recorder.lock().unwrap();
+                if parts.get(1) == Some(&"off") {
+                    rec.settings.max_record_seconds = None;
+                    println!("No limit on take length");
+                } else {
+                    match args::num::<f32>(&parts, 1, "seconds") {
+                        Ok(secs) if secs > 0.0 => {
+                            rec.settings.max_record_seconds = Some(secs);
+                            println!("Takes stop after {}s", secs);
+                        }
+                        Ok(_) => println!("  {} seconds must be above 0", "×".red()),
+                        Err(e) => arg_error(&e),
+                    }
+                }
+                clear = false;
+            }
+            "autostop" => {
+                // autostop <secs> [threshold] | autostop off
+                let mut rec = app.recorder.lock().unwrap();
+                if parts.get(1) == Some(&"off") {
+                    rec.settings.auto_stop = None;
+                } else {
+                    let parsed = (|| Ok::<_, String>((
+                        args::num::<f32>(&parts, 1, "seconds")?,
+                        args::opt_num::<f32>(&parts, 2, "threshold")?,
+                    )))();
+                    match parsed {
+                        Ok((secs, _)) if secs <= 0.0 => println!("  {} seconds must be above 0", "×".red()),
+                        Ok((secs, threshold)) => {
+                            rec.settings.auto_stop = Some(secs);
+                            if let Some(t) = threshold { rec.settings.auto_stop_threshold = t.abs(); }
+                            println!("Recording stops after {}s below {}", secs, rec.settings.auto_stop_threshold);
+                        }
+                        Err(e) => arg_error(&e),
+                    }
+                }
+                clear = false;
+            }
+            "autodecide" => {
+                // autodecide <secs> approve|reject | autodecide off
+                let mut rec = app.recorder.lock().unwrap();
+                match (parts.get(1).copied(), parts.get(2).copied()) {
+                    (Some("off"), _) => rec.settings.review_timeout = None,
+                    (Some(_), action @ (None | Some("approve") | Some("reject"))) => match args::num::<f32>(&parts, 1, "seconds") {
+                        Ok(secs) if secs > 0.0 => {
+                            rec.settings.review_timeout = Some(secs);
+                            rec.settings.review_default = match action {
+                                Some("reject") => ReviewAction::Reject,
+                                _ => ReviewAction::Approve,
+                            };
+                        }
+                        Ok(_) => println!("  {} seconds must be above 0", "×".red()),
+                        Err(e) => arg_error(&e),
+                    },
+                    _ => println!("Usage: autodecide <seconds> [approve|reject] | autodecide off"),
+                }
+                clear = false;
+            }
+            "save" => {
+                if let Some(path) = parts.get(1) {
+                    app.handle_command(Command::SaveProjectAs(path.to_string()));
+                } else {
+                    println!("Usage: save <path>");
+                }
+                clear = false;
+            }
+            "open" => {
+                if let Some(path) = parts.get(1) {
+                    app.handle_command(Command::LoadProject(path.to_string()));
+                } else {
+                    println!("Usage: open <path>");
+                }
+            }
+            "appendproject" => {
+                if let Some(path) = parts.get(1) {
+                    app.handle_command(Command::AppendProject(path.to_string()));
+                } else {
+                    println!("Usage: appendproject <path>");
+                }
+                clear = false;
+            }
+            "package" => {
+                if let Some(dir) = parts.get(1) {
+                    app.handle_command(Command::ExportPackage(dir.to_string()));
+                } else {
+                    println!("Usage: package <directory>");
+                }
+                clear = false;
+            }
+            "es" => {
+                // es <dir> [prefix], unnamed segments become <prefix>_NNN.wav
+                if let Some(dir) = parts.get(1) {
+                    let prefix = parts.get(2).copied().unwrap_or("segment");
+                    app.handle_command(Command::ExportSegments(dir.to_string(), prefix.to_string()));
+                } else {
+                    println!("Usage: es <directory> [prefix]");
+                }
+                clear = false;
+            }
+            "import" => {
+                if let Some(path) = parts.get(1) {
+                    app.handle_command(Command::ImportWav(path.to_string()));
+                } else {
+                    println!("Usage: import <file.wav>");
+                }
+                clear = false;
+            }
+            "chanmode" => {
+                // what to do when the project's channel count doesn't match the input
+                let policy = match parts.get(1).copied() {
+                    Some("dup") => Some(state::ChannelPolicy::Duplicate),
+                    Some("refuse") => Some(state::ChannelPolicy::Refuse),
+                    _ => None,
+                };
+                match policy {
+                    Some(p) => app.recorder.lock().unwrap().settings.channel_mismatch = p,
+                    None => println!("Usage: chanmode dup|refuse"),
+                }
+                clear = false;
+            }
+            "prompt" => {
+                // prompt <template> | prompt default
+                let mut rec = app.recorder.lock().unwrap();
+                match parts.get(1).copied() {
+                    Some("default") => rec.settings.prompt_template = None,
+                    Some(_) => rec.settings.prompt_template = Some(parts[1..].join(" ")),
+                    None => {
+                        println!("Usage: prompt <template> | prompt default");
+                        println!("  placeholders: {{state}} {{segs}} {{total}} {{take}} {{mem}} {{meter}}");
+                    }
+                }
+                clear = false;
+            }
+            "spark" => {
+                // sparkline width for the `q` listing, 0 hides it
+                match args::num::<usize>(&parts, 1, "columns") {
+                    Ok(cols) => app.recorder.lock().unwrap().settings.sparkline_width = cols.min(80),
+                    Err(e) => println!("  {} {}  (spark 0 hides the sparkline)", "×".red(), e),
+                }
+                clear = false;
+            }
+            "ef" => {
+                // ef <path>, lossless archive of the project, stays in the app
+                match parts.get(1) {
+                    #[cfg(feature = "flac")]
+                    Some(path) => {
+                        let rec = app.recorder.lock().unwrap();
+                        match export::export_flac(&rec.project, path, &rec.settings.export) {
+                            Ok(()) => println!("Exported to {}", path),
+                            Err(e) => eprintln!("Failed to export to {}: {}", path, e),
+                        }
+                    }
+                    #[cfg(not(feature = "flac"))]
+                    Some(_) => println!("  {} built without FLAC support (the `flac` feature)", "×".red()),
+                    None => arg_error("ef needs a path, e.g. ef session.flac"),
+                }
+                clear = false;
+            }
+            // a path and one number is a range export missing its end, not a format
+            "e" if parts.len() > 3 || (parts.len() == 3 && export::ExportFormat::parse(parts[1]).is_none()) => {
+                // e <path> <start> <end>: bounce segments start..=end (1-based) to a file and carry on
+                let range = (|| Ok::<_, String>((args::segment(&parts, 2)?, args::segment(&parts, 3)?)))();
+                match range {
+                    Err(_) if parts.len() == 3 => arg_error("e <path> <start> <end> needs an end segment, e.g. e part.wav 2 5"),
+                    Ok((start, end)) => {
+                        let rec = app.recorder.lock().unwrap();
+                        match export::export_wav_range(&rec.project, parts[1], start, end, &rec.settings.export) {
+                            Ok(()) => println!("Exported segments {}-{} to {}", start + 1, end + 1, parts[1]),
+                            Err(e) => eprintln!("Failed to export to {}: {}", parts[1], e),
+                        }
+                    }
+                    Err(e) => arg_error(&e),
+                }
+                clear = false;
+            }
             "e" => {
+                // e [16|24|float], without a format the configured one (16-bit unless set)
+                if let Some(f) = parts.get(1) {
+                    match export::ExportFormat::parse(f) {
+                        Some(format) => app.recorder.lock().unwrap().settings.export.format = format,
+                        None => {
+                            println!("  {} unknown format `{}` (16, 24 or float)", "×".red(), f);
+                            clear = false;
+                            continue;
+                        }
+                    }
+                }
                 println!("{} Exporting to output.wav...", "✔".green());
-                app.handle_command(Command::Export(Some("output.wav".into()))); 
-                break; 
+                // only leave once the file is written, a failed export keeps the session
+                if app.export("output.wav") { break; }
+                clear = false;
+            }
+            "clear" => {
+                // start over, asks first since it drops every take at once
+                let (count, idle) = {
+                    let rec = app.recorder.lock().unwrap();
+                    (rec.get_segment_count(), rec.can_restructure())
+                };
+                if count == 0 {
+                    println!("The project is already empty.");
+                } else if !idle {
+                    println!("{}", TAKE_PENDING);
+                } else if confirm(&format!("Clear all {} segment(s)? (u undoes it)", count)) {
+                    app.handle_command(Command::ClearProject);
+                    println!("Project cleared");
+                } else {
+                    println!("Kept the project");
+                }
+                clear = false;
             }
             "quit" => { print!("\x1B[2J\x1B[H"); break; }
-            _ => println!("  {} Unknown command.", "×".red()),
+            "h" | "help" => { print_help(); clear = false; }
+            _ => { println!("  {} Unknown command. Type 'h' for help.", "×".red()); clear = false; }
         }
     }
+    // Ctrl+C keeps the session, including a take still being recorded, for
+    // the next start. otherwise it was exported or quit on purpose
+    let interrupted = interrupt::take();
+    {
+        let rec = app.recorder.lock().unwrap_or_else(|e| e.into_inner());
+        if interrupted {
+            match rec.write_recovery_with_take() {
+                0 => println!("\nInterrupted, nothing to save."),
+                n => println!("\nInterrupted, {} segment(s) kept for recovery, the next start offers them back.", n),
+            }
+        } else {
+            rec.clear_recovery();
+        }
+    }
+    app.shutdown();
+    if interrupted {
+        std::process::exit(130); // the shell's code for "ended by Ctrl+C"
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_prompt_template() {
+        let mut rec = RecorderState::new(10, 1);
+        rec.project.segments.push(state::Segment::new(vec![0.0; 650])); // 65s
+        rec.project.segments.push(state::Segment::new(vec![0.0; 10]));
+        assert_eq!(format_prompt(&rec, "{state} | {segs} segs | {total}"), "IDLE | 2 segs | 1m:06s");
+
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.0; 25]);
+        assert_eq!(format_prompt(&rec, "[{state} {take}]"), "[RECORDING 2.5s]");
+        assert_eq!(take_elapsed(&rec), "2.50s");
+        rec.settings.max_record_seconds = Some(90.0);
+        assert_eq!(take_elapsed(&rec), "2.50s / 1:30.00");
+        assert_eq!(format_prompt(&rec, "no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn test_format_age() {
+        use std::time::Duration;
+        assert_eq!(format_age(Duration::from_secs(5)), "5s ago");
+        assert_eq!(format_age(Duration::from_secs(150)), "2m ago");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h ago");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400)), "3d ago");
+    }
+
+    #[test]
+    fn test_format_seconds() {
+        assert_eq!(format_seconds(4.5), "4.50s");
+        assert_eq!(format_seconds(59.99), "59.99s");
+        assert_eq!(format_seconds(65.25), "1:05.25");
+        assert_eq!(format_seconds(600.0), "10:00.00");
+        assert_eq!(format_seconds(59.996), "1:00.00");
+        assert_eq!(format_seconds(119.996), "2:00.00");
+    }
+}
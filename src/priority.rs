@@ -0,0 +1,75 @@
+// Best-effort scheduling boost for the audio input thread.
+// On a loaded machine the capture callback can be pre-empted long enough to
+// drop buffers; asking the OS for real-time (or at least higher) priority
+// makes that much less likely. Nothing here is required to record, so every
+// failure is reported and the thread simply carries on at normal priority.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PriorityOutcome {
+    Realtime,            // SCHED_FIFO granted
+    Elevated,            // no real-time, but a better nice value
+    Unavailable(String), // left at normal priority, with the reason
+}
+
+// SCHED_FIFO priority to ask for: above ordinary real-time helpers but well
+// below the kernel's own threads (1..=99 on Linux)
+#[cfg(unix)]
+const REALTIME_PRIORITY: libc::c_int = 20;
+#[cfg(target_os = "linux")]
+const ELEVATED_NICE: libc::c_int = -10;
+
+// raise the priority of the calling thread, so call it from the audio thread
+#[cfg(unix)]
+pub fn raise_current_thread() -> PriorityOutcome {
+    // SAFETY: plain libc calls on the current thread with a zeroed, then filled, param struct
+    unsafe {
+        let mut param: libc::sched_param = std::mem::zeroed();
+        let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+        param.sched_priority = REALTIME_PRIORITY.min(max.max(1));
+        let err = libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param);
+        if err == 0 {
+            return PriorityOutcome::Realtime;
+        }
+        let reason = std::io::Error::from_raw_os_error(err).to_string();
+        raise_nice(reason)
+    }
+}
+
+// linux schedules threads individually, so the nice value of just this
+// thread (its tid) can be lowered. needs CAP_SYS_NICE or a RLIMIT_NICE
+#[cfg(target_os = "linux")]
+unsafe fn raise_nice(realtime_err: String) -> PriorityOutcome {
+    // SAFETY: gettid/setpriority only touch the calling thread
+    let tid = unsafe { libc::syscall(libc::SYS_gettid) } as libc::id_t;
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, ELEVATED_NICE) } == 0 {
+        PriorityOutcome::Elevated
+    } else {
+        PriorityOutcome::Unavailable(format!("real-time: {}, nice: {}", realtime_err, std::io::Error::last_os_error()))
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+unsafe fn raise_nice(realtime_err: String) -> PriorityOutcome {
+    PriorityOutcome::Unavailable(format!("real-time: {}", realtime_err))
+}
+
+#[cfg(not(unix))]
+pub fn raise_current_thread() -> PriorityOutcome {
+    PriorityOutcome::Unavailable("not supported on this platform".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raise_priority_reports_an_outcome() {
+        // on its own thread so the test runner's threads are left alone
+        let outcome = std::thread::spawn(raise_current_thread).join().unwrap();
+        match outcome {
+            PriorityOutcome::Realtime | PriorityOutcome::Elevated => {}
+            // CI and containers usually refuse, that must come back as a reason, not a panic
+            PriorityOutcome::Unavailable(reason) => assert!(!reason.is_empty()),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::state::{Project, Segment};
+use serde::Deserialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Project files: the whole Project (segments with their samples, names and
+// edit logs, sample rate, channels) bincode-encoded, the same format the
+// auto-save writes, so any auto-saved file can be opened with `open`.
+// bincode is positional, so the file starts with a magic and a layout
+// version. files without the header come from before segments carried
+// anything but samples and are read through the old shape.
+
+const MAGIC: &[u8; 4] = b"POSP";
+const VERSION: u32 = 1;
+
+// the layout written before the header existed
+#[derive(Deserialize)]
+struct LegacySegment {
+    samples: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct LegacyProject {
+    segments: Vec<LegacySegment>,
+    sample_rate: u32,
+    channels: u16,
+    editing_index: Option<usize>,
+}
+
+impl From<LegacyProject> for Project {
+    fn from(old: LegacyProject) -> Self {
+        Project {
+            segments: old.segments.into_iter().map(|s| Segment::new(s.samples)).collect(),
+            sample_rate: old.sample_rate,
+            channels: old.channels,
+            editing_index: old.editing_index,
+            metadata: Default::default(),
+        }
+    }
+}
+
+pub fn save_project(project: &Project, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let mut encoded = MAGIC.to_vec();
+    encoded.extend_from_slice(&VERSION.to_le_bytes());
+    bincode::serialize_into(&mut encoded, project)?;
+    std::fs::write(path, encoded)?;
+    Ok(())
+}
+
+pub fn load_project(path: impl AsRef<Path>) -> Result<Project, Box<dyn Error>> {
+    let data = std::fs::read(path)?;
+    decode_project(&data)
+}
+
+fn decode_project(data: &[u8]) -> Result<Project, Box<dyn Error>> {
+    match data.strip_prefix(MAGIC) {
+        Some(rest) if rest.len() >= 4 => {
+            let version = u32::from_le_bytes(rest[..4].try_into()?);
+            if version > VERSION {
+                return Err(format!("project file version {} is newer than this build reads ({})", version, VERSION).into());
+            }
+            Ok(bincode::deserialize(&rest[4..])?)
+        }
+        _ => Ok(bincode::deserialize::<LegacyProject>(data)?.into()),
+    }
+}
+
+// crash-recovery snapshots, one per running session, rewritten after every
+// edit and removed on a clean exit, so one still lying around means that
+// session died. named parts-of-speech-<user>-<project>-<pid>.recovery:
+// <project> is the save file's name ("unsaved" before there is one) so the
+// startup prompt can say what it is, and the pid keeps two sessions, even
+// on the same project, from overwriting or deleting each other's
+const RECOVERY_PREFIX: &str = "parts-of-speech-";
+const RECOVERY_EXT: &str = "recovery";
+
+pub fn recovery_file_name(save_path: Option<&str>) -> String {
+    let project = save_path
+        .and_then(|p| Path::new(p).file_stem())
+        .map_or("unsaved".to_string(), |stem| file_safe(&stem.to_string_lossy()));
+    format!("{}{}-{}-{}.{}", RECOVERY_PREFIX, file_safe(&user_name()), project, std::process::id(), RECOVERY_EXT)
+}
+
+// snapshots in `dir` this user's earlier sessions left behind, newest
+// first. ones whose session is still running aren't leftovers
+pub fn leftover_recoveries(dir: &Path) -> Vec<PathBuf> {
+    let prefix = format!("{}{}-", RECOVERY_PREFIX, file_safe(&user_name()));
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new(); };
+    let mut found: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let name = path.file_name()?.to_str()?;
+            let stem = name.strip_prefix(&prefix)?.strip_suffix(&format!(".{}", RECOVERY_EXT))?;
+            let pid: u32 = stem.rsplit_once('-')?.1.parse().ok()?;
+            if session_running(pid) { return None; }
+            Some((e.metadata().and_then(|m| m.modified()).ok()?, path))
+        })
+        .collect();
+    found.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+// a snapshot that couldn't be restored is renamed out of the way (and out of
+// leftover_recoveries) rather than deleted, it may still open elsewhere
+pub fn set_aside(path: &Path) -> std::io::Result<PathBuf> {
+    let aside = path.with_extension("failed");
+    std::fs::rename(path, &aside)?;
+    Ok(aside)
+}
+
+fn user_name() -> String {
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "user".into())
+}
+
+// letters, digits, '_' and '.' as they are, anything else as '_'
+fn file_safe(s: &str) -> String {
+    s.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '.' { c } else { '_' }).collect()
+}
+
+#[cfg(unix)]
+fn session_running(pid: u32) -> bool {
+    if pid == std::process::id() { return true; }
+    // 0 and negative pids address process groups, not a session
+    let Ok(pid) = libc::pid_t::try_from(pid) else { return false; };
+    if pid <= 0 { return false; }
+    // SAFETY: signal 0 only checks that the process exists, nothing is sent
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn session_running(pid: u32) -> bool {
+    pid == std::process::id()
+}
+
+// a project can only be opened as-is when the device runs at its rate,
+// otherwise every segment would play back at the wrong speed
+pub fn check_sample_rate(project: &Project, device_rate: u32) -> Result<(), String> {
+    if project.sample_rate == device_rate {
+        Ok(())
+    } else {
+        Err(format!(
+            "project was recorded at {}Hz but the device runs at {}Hz (use appendproject to convert it)",
+            project.sample_rate, device_rate
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = std::env::temp_dir().join("pos_test_project_io.bin");
+        let path = path.to_string_lossy().to_string();
+
+        let mut project = Project { segments: Vec::new(), sample_rate: 48000, channels: 2, editing_index: None, metadata: Default::default() };
+        let mut seg = Segment::new(vec![0.25, -0.5, 1.0, 0.0]);
+        seg.name = Some("intro".into());
+        project.segments.push(seg);
+        save_project(&project, &path).unwrap();
+
+        let loaded = load_project(&path).unwrap();
+        assert_eq!(loaded.sample_rate, 48000);
+        assert_eq!(loaded.channels, 2);
+        assert_eq!(loaded.segments[0].samples, vec![0.25, -0.5, 1.0, 0.0]);
+        assert_eq!(loaded.segments[0].name.as_deref(), Some("intro"));
+
+        assert!(check_sample_rate(&loaded, 48000).is_ok());
+        assert!(check_sample_rate(&loaded, 44100).is_err());
+
+        let _ = std::fs::remove_file(&path);
+        assert!(load_project(&path).is_err());
+    }
+
+    #[test]
+    fn test_loads_headerless_legacy_files() {
+        // the pre-header layout: segments were samples only, no metadata
+        #[derive(Serialize)]
+        struct OldSegment { samples: Vec<f32> }
+        #[derive(Serialize)]
+        struct OldProject { segments: Vec<OldSegment>, sample_rate: u32, channels: u16, editing_index: Option<usize> }
+
+        let old = OldProject {
+            segments: vec![OldSegment { samples: vec![0.5, -0.5] }, OldSegment { samples: vec![0.25] }],
+            sample_rate: 44100,
+            channels: 1,
+            editing_index: None,
+        };
+        let path = std::env::temp_dir().join("pos_test_project_io_legacy.bin");
+        let path = path.to_string_lossy().to_string();
+        std::fs::write(&path, bincode::serialize(&old).unwrap()).unwrap();
+
+        let loaded = load_project(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.sample_rate, 44100);
+        assert_eq!(loaded.segments.len(), 2);
+        assert_eq!(loaded.segments[0].samples, vec![0.5, -0.5]);
+        assert_eq!(loaded.segments[1].gain, 1.0);
+        assert!(loaded.segments[1].name.is_none());
+    }
+
+    #[test]
+    fn test_refuses_newer_versions() {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(decode_project(&data).is_err());
+    }
+
+    #[test]
+    fn test_recovery_files_are_per_session() {
+        let name = recovery_file_name(Some("/tmp/book/chapter one.bin"));
+        assert!(name.contains("-chapter_one-"), "{}", name);
+        assert!(name.ends_with(&format!("-{}.recovery", std::process::id())));
+        assert!(recovery_file_name(None).contains("-unsaved-"));
+
+        let dir = std::env::temp_dir().join(format!("pos_test_leftovers_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ours = dir.join(recovery_file_name(None));
+        // above linux's pid_max, never a live session
+        let ours_suffix = format!("-{}.recovery", std::process::id());
+        let dead = dir.join(recovery_file_name(Some("intro.bin")).replace(&ours_suffix, "-2147483647.recovery"));
+        std::fs::write(&ours, b"").unwrap();
+        std::fs::write(&dead, b"").unwrap();
+        std::fs::write(dir.join("unrelated.recovery"), b"").unwrap();
+
+        assert_eq!(leftover_recoveries(&dir), vec![dead.clone()]); // not the running session's
+        let aside = set_aside(&dead).unwrap();
+        assert!(aside.exists() && leftover_recoveries(&dir).is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,13 @@
 use serde::{Serialize, Deserialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use crate::dsp::{self, EffectSpec, FilterKind};
+use crate::audio_output::PlaybackOptions;
+use crate::export::ExportOptions;
+use crate::project_io;
 
 // This module is the data model that holds audio
 // segments linearly. Nothing outside of this module
@@ -10,15 +19,131 @@ use serde::{Serialize, Deserialize};
 pub struct Segment { // a single recording take
     // the actual audio numbers
     pub samples: Vec<f32>, // raw audio data (32-bit float samples)
+    pub history: Vec<String>, // append-only log of edits applied to this take
+    pub name: Option<String>, // user label, the list number is used when None
+    pub gain: f32, // playback/export multiplier, samples stay untouched (1.0 = as recorded)
+    pub recorded_at: Option<SystemTime>, // when the take was started, None for imports/silence
+    pub pan: f32, // -1.0 left .. 1.0 right, stereo projects only (mono ignores it)
+    pub muted: bool, // left out of `pa` and every export, kept in the project
 }
 // a segment is one recorded chunk
 // for recording replacements (retry)
 
 impl Segment {
+    pub fn new(samples: Vec<f32>) -> Self {
+        Self { samples, history: Vec::new(), name: None, gain: 1.0, recorded_at: None, pan: 0.0, muted: false }
+    }
+
+    // an empty take about to be recorded into, stamped with the time
+    pub fn recording() -> Self {
+        Self { recorded_at: Some(SystemTime::now()), ..Self::new(Vec::new()) }
+    }
+
+    // the samples as heard: raw samples times gain. borrowed at unity gain
+    // so playing or exporting an untouched take doesn't copy it
+    pub fn gained(&self) -> std::borrow::Cow<'_, [f32]> {
+        if self.gain == 1.0 {
+            std::borrow::Cow::Borrowed(&self.samples)
+        } else {
+            std::borrow::Cow::Owned(self.samples.iter().map(|s| s * self.gain).collect())
+        }
+    }
+
+    // what playback and export use: gained(), then panned when the project
+    // is stereo. the pan is a mix setting like gain, the samples keep it out
+    pub fn heard(&self, channels: u16) -> std::borrow::Cow<'_, [f32]> {
+        let gained = self.gained();
+        if channels != 2 || self.pan == 0.0 { return gained; }
+        let mut panned = gained.into_owned();
+        crate::dsp::pan_stereo(&mut panned, self.pan);
+        std::borrow::Cow::Owned(panned)
+    }
+
+    // stable fingerprint of the audio (FNV-1a over the raw sample bytes),
+    // equal for identical takes and the same on every run and machine, so
+    // it can spot duplicates or prove an operation left the samples alone.
+    // name and history don't count, only what you'd hear
+    pub fn content_hash(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+        self.samples.iter()
+            .flat_map(|s| s.to_le_bytes())
+            .fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+    }
+
+    // cut the take in two at sample `at` (0 < at < len), for splitting a
+    // take that holds two sentences. metadata is carried over so neither
+    // half loses track of where it came from:
+    //   samples  [..at] / [at..]
+    //   name     "A" -> "A.1" / "A.2", unnamed stays unnamed
+    //   gain     same on both halves (pan and mute too)
+    //   history  both halves keep every earlier edit (effects were applied
+    //            to the whole take) followed by a "split" entry of their own
+    pub fn split_at(&self, at: usize) -> Option<(Segment, Segment)> {
+        if at == 0 || at >= self.samples.len() { return None; }
+        let half = |part: usize, samples: &[f32]| {
+            let mut seg = Segment {
+                samples: samples.to_vec(),
+                history: self.history.clone(),
+                name: self.name.as_ref().map(|n| format!("{}.{}", n, part)),
+                gain: self.gain,
+                recorded_at: self.recorded_at,
+                pan: self.pan,
+                muted: self.muted,
+            };
+            seg.log(format!("split at sample {} (part {}/2)", at, part));
+            seg
+        };
+        Some((half(1, &self.samples[..at]), half(2, &self.samples[at..])))
+    }
+
+    // record an edit in the take's audit log, viewable with `log <n>`
+    pub fn log(&mut self, entry: String) {
+        self.history.push(entry);
+    }
+
     // 1 second of pub samples = 48000 indexes (sample_rate)
     #[allow(unused)]
-    pub fn duration_seconds(&self, sample_rate: u32) -> f32 {
-        self.samples.len() as f32 / sample_rate as f32
+    pub fn duration_seconds(&self, sample_rate: u32, channels: u16) -> f32 {
+        self.samples.len() as f32 / (sample_rate as f32 * channels.max(1) as f32)
+    }
+
+    // loudest absolute sample, 0.0 for an empty take
+    pub fn peak(&self) -> f32 {
+        self.samples.iter().fold(0.0, |m, s| m.max(s.abs()))
+    }
+
+    // cut leading/trailing audio quieter than `threshold`, keeping `pad`
+    // frames of the silence on each side so the cut doesn't sound abrupt.
+    // cuts land on whole frames so interleaved channels stay in place.
+    // a take that never crosses the threshold is left alone (false)
+    pub fn trim_silence(&mut self, threshold: f32, pad: usize, channels: u16) -> bool {
+        let Some((first, last)) = speech_bounds(&self.samples, threshold) else { return false; };
+        let ch = channels.max(1) as usize;
+        let start = (first / ch).saturating_sub(pad) * ch;
+        let end = (last / ch + 1).saturating_add(pad).saturating_mul(ch).min(self.samples.len());
+        self.samples.truncate(end);
+        self.samples.drain(..start);
+        true
+    }
+
+    // samples at or past full scale, they'll be flattened on export
+    pub fn clipped_samples(&self) -> usize {
+        self.samples.iter().filter(|s| s.abs() >= 1.0).count()
+    }
+
+    // downsample into `buckets` peak values (max abs per bucket, 0.0..=1.0+)
+    // used by the listing sparkline, empty buckets stay at 0.0
+    pub fn waveform(&self, buckets: usize) -> Vec<f32> {
+        let mut peaks = vec![0.0_f32; buckets];
+        if buckets == 0 || self.samples.is_empty() { return peaks; }
+        let len = self.samples.len();
+        for (b, peak) in peaks.iter_mut().enumerate() {
+            let start = b * len / buckets;
+            let end = ((b + 1) * len / buckets).max(start + 1).min(len);
+            *peak = self.samples[start..end].iter().fold(0.0, |m, s| m.max(s.abs()));
+        }
+        peaks
     }
 }
 
@@ -29,11 +154,358 @@ pub struct Project {
     pub sample_rate: u32, // 44100 or 48000 Hz
     pub channels: u16,    // 1: mono, 2: stereo
     pub editing_index: Option<usize>, // which segment we're editing (for retry/insert)
+    pub metadata: ProjectMetadata, // tags written into the exported WAV
+}
+
+// catalog tags for the export (RIFF LIST/INFO), None = left out of the file
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ProjectMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub comment: Option<String>,
+    pub date: Option<String>,
+}
+
+impl ProjectMetadata {
+    pub const FIELDS: [&'static str; 4] = ["title", "artist", "comment", "date"];
+
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut Option<String>> {
+        match name {
+            "title" => Some(&mut self.title),
+            "artist" => Some(&mut self.artist),
+            "comment" => Some(&mut self.comment),
+            "date" => Some(&mut self.date),
+            _ => None,
+        }
+    }
+
+    // (INFO chunk id, text) for every tag that's set
+    pub fn info_tags(&self) -> Vec<(&'static [u8; 4], &str)> {
+        [(b"INAM", &self.title), (b"IART", &self.artist), (b"ICMT", &self.comment), (b"ICRD", &self.date)]
+            .into_iter()
+            .filter_map(|(id, v)| v.as_deref().map(|v| (id, v)))
+            .collect()
+    }
 }
 // persistent timeline of all segments (that were approved)
 
+impl Project {
+    // groups of segment indexes whose audio is identical (see
+    // Segment::content_hash), in timeline order. empty when there are none
+    pub fn duplicate_groups(&self) -> Vec<Vec<usize>> {
+        let hashes: Vec<u64> = self.segments.iter().map(Segment::content_hash).collect();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for (i, h) in hashes.iter().enumerate() {
+            if hashes[..i].contains(h) { continue; } // already grouped with an earlier one
+            let group: Vec<usize> = (i..hashes.len()).filter(|&j| hashes[j] == *h).collect();
+            if group.len() > 1 { groups.push(group); }
+        }
+        groups
+    }
+
+    // silence (seconds) at each join between neighbouring segments: the
+    // tail of segment i below `threshold` plus the head of segment i + 1.
+    // one entry per adjacent pair, so n segments give n - 1 gaps
+    pub fn inter_segment_gaps(&self, threshold: f32) -> Vec<f32> {
+        let frames_per_sec = (self.sample_rate * self.channels.max(1) as u32) as f32;
+        self.segments.windows(2)
+            .map(|pair| {
+                let (a, b) = (&pair[0].samples, &pair[1].samples);
+                let tail = speech_bounds(a, threshold).map_or(a.len(), |(_, last)| a.len() - last - 1);
+                let head = speech_bounds(b, threshold).map_or(b.len(), |(first, _)| first);
+                (tail + head) as f32 / frames_per_sec
+            })
+            .collect()
+    }
+}
+
+// first and last sample index whose level exceeds `threshold`,
+// None when the whole buffer is below it
+pub fn speech_bounds(samples: &[f32], threshold: f32) -> Option<(usize, usize)> {
+    let first = samples.iter().position(|s| s.abs() > threshold)?;
+    let last = samples.iter().rposition(|s| s.abs() > threshold)?;
+    Some((first, last))
+}
+
+// sample ranges of the neighbours that pad a segment for context playback:
+// the last `roll` samples of the previous one and the first `roll` of the
+// next one, shortened to whatever they actually have. None = no neighbour
+pub fn context_ranges(prev_len: Option<usize>, next_len: Option<usize>, roll: usize) -> (Range<usize>, Range<usize>) {
+    let pre = prev_len.map_or(0..0, |len| len.saturating_sub(roll)..len);
+    let post = next_len.map_or(0..0, |len| 0..roll.min(len));
+    (pre, post)
+}
+
+// convert a millisecond duration to a sample count at `sample_rate`
+pub fn ms_to_samples(ms: f32, sample_rate: u32) -> usize {
+    (ms.max(0.0) / 1000.0 * sample_rate as f32).round() as usize
+}
+
+// ===== Settings =====
+
+// what to do when the project's channel layout differs from what the
+// input is capturing (e.g. a stereo project opened on a mono mic)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelPolicy {
+    Duplicate, // copy each captured sample into every project channel
+    Refuse,    // don't record, tell the user instead
+}
+
+// how captured frames are written into a project, see reconcile_channels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelPlan {
+    Passthrough,
+    Duplicate(u16), // mono capture written n times per frame
+}
+
+// decide how captured audio fits the project layout. without this a mono
+// take in a stereo project is read as half as many stereo frames, so it
+// plays at double speed and half length
+pub fn reconcile_channels(project: u16, capture: u16, policy: ChannelPolicy) -> Result<ChannelPlan, String> {
+    match (project, capture, policy) {
+        (p, c, _) if p == c => Ok(ChannelPlan::Passthrough),
+        (p, 1, ChannelPolicy::Duplicate) => Ok(ChannelPlan::Duplicate(p)),
+        (p, c, _) => Err(format!(
+            "Project is {}-channel but the input captures {} channel(s), not recording.", p, c)),
+    }
+}
+
+// why the input callback ended a take on its own
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoStop {
+    Silence,   // settings.auto_stop
+    MaxLength, // settings.max_record_seconds
+}
+
+// what to do with a take nobody decided on (see Settings::review_timeout)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReviewAction {
+    Approve,
+    Reject,
+}
+
+// user-tweakable behaviour, grouped so it can be changed
+// from the command line without touching the workflow state
+#[derive(Clone)]
+pub struct Settings {
+    pub sparkline_width: usize, // columns of the `q` sparkline, 0 = hidden
+    pub review_repeats: u32,    // how many times `p` plays the take under review
+    // booth mode: once the project reaches either limit approve() hands the
+    // finished session back for export and starts a fresh one. None = off
+    pub auto_export_segments: Option<usize>,
+    pub auto_export_seconds: Option<f32>,
+    pub trim_pad_ms: f32,       // silence kept either side of a silence trim
+    pub context_ms: f32,        // `p <n>` also plays this much of the neighbours either side, 0 = off
+    pub auto_trim: Option<f32>, // threshold to tighten both edges of every take on stop, None = off
+    // takes shorter than this are flagged on approve, and thrown away
+    // instead of committed when reject_short is set. None = any length
+    pub min_segment_ms: Option<f32>,
+    pub reject_short: bool,
+    pub input_device: Option<String>, // input device name (see audio_input::match_device_name), None = default
+    pub output_device: Option<String>, // playback device name (see audio_output::open_sink), None = default
+    pub input_channels: Option<Vec<usize>>, // 0-based hardware channels to capture, None = all
+    pub stereo: bool, // keep the first two selected channels as a stereo pair instead of down-mixing
+    pub input_gain: f32, // multiplier on captured samples before they're stored, 1.0 = as the device delivers
+    // rate new projects are recorded at, input is resampled to it when the
+    // device runs at another rate. None = follow the device (the default)
+    pub target_sample_rate: Option<u32>,
+    // noise gate on captured samples (see dsp::NoiseGate), quieter than this
+    // is faded out before it's stored. 0.0 = off (the default)
+    pub noise_gate: f32,
+    pub realtime_priority: bool, // ask the OS to boost the input callback thread (best-effort)
+    // hands-free review: seconds to wait for c/x before review_default is applied, None = wait forever
+    pub review_timeout: Option<f32>,
+    pub review_default: ReviewAction,
+    // stop recording after this many seconds under auto_stop_threshold once
+    // the take has started, None = only `s` stops (the default)
+    pub auto_stop: Option<f32>,
+    pub auto_stop_threshold: f32,
+    // safety cap on a single take so a forgotten recording can't eat all
+    // memory, stops it like `s` would. None = unlimited (the default)
+    pub max_record_seconds: Option<f32>,
+    pub channel_mismatch: ChannelPolicy,
+    pub block_size: usize,      // frames per chunk when effects walk a segment
+    pub prompt_template: Option<String>, // CLI status line, None = built-in
+    pub export: ExportOptions,
+    pub playback: PlaybackOptions,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sparkline_width: 16,
+            review_repeats: 1,
+            auto_export_segments: None,
+            auto_export_seconds: None,
+            trim_pad_ms: 0.0,
+            context_ms: 0.0,
+            auto_trim: None,
+            min_segment_ms: None,
+            reject_short: false,
+            input_device: None,
+            output_device: None,
+            input_channels: None,
+            stereo: false,
+            input_gain: 1.0,
+            target_sample_rate: None,
+            noise_gate: 0.0,
+            realtime_priority: false,
+            review_timeout: None,
+            review_default: ReviewAction::Approve,
+            auto_stop: None,
+            auto_stop_threshold: 0.01,
+            max_record_seconds: None,
+            channel_mismatch: ChannelPolicy::Duplicate,
+            block_size: dsp::DEFAULT_BLOCK_SIZE,
+            prompt_template: None,
+            export: ExportOptions::default(),
+            playback: PlaybackOptions::default(),
+        }
+    }
+}
+
+// ===== Metering =====
+
+// live input levels, written by the audio callback and read by the UI.
+// atomics instead of RecorderState fields so the callback can meter
+// even when try_lock() on the recorder fails.
+// peak meter ballistics: a new peak shows instantly, then falls at
+// METER_DECAY_DB_PER_SEC, so short peaks stay visible long enough to read
+// and the bars drop back to nothing when the input stops instead of
+// freezing at the last buffer
+pub struct LevelMeter {
+    peaks: [AtomicU32; 2], // f32 bits, left/mono and right, as of `stamp`
+    stamp: AtomicU64,      // micros since `epoch` when the peaks were last stored
+    channels: AtomicUsize, // how many of `peaks` are live (1 or 2)
+    epoch: Instant,
+}
+
+const METER_DECAY_DB_PER_SEC: f32 = 24.0;
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self {
+            peaks: Default::default(),
+            stamp: AtomicU64::new(0),
+            channels: AtomicUsize::new(0),
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl LevelMeter {
+    pub fn set_channels(&self, channels: usize) {
+        self.channels.store(channels.clamp(1, 2), Ordering::Relaxed);
+    }
+
+    pub fn store(&self, peaks: &[f32]) {
+        self.store_at(peaks, self.epoch.elapsed());
+    }
+
+    // current (decayed) level per live channel
+    pub fn levels(&self) -> Vec<f32> {
+        self.levels_at(self.epoch.elapsed())
+    }
+
+    // the louder of the new peak and the old one after its decay
+    fn store_at(&self, peaks: &[f32], now: Duration) {
+        let held = self.levels_at(now);
+        for (i, (slot, p)) in self.peaks.iter().zip(peaks).enumerate() {
+            let level = p.max(held.get(i).copied().unwrap_or(0.0));
+            slot.store(level.to_bits(), Ordering::Relaxed);
+        }
+        self.stamp.store(now.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn levels_at(&self, now: Duration) -> Vec<f32> {
+        let n = self.channels.load(Ordering::Relaxed).max(1);
+        let since = now.saturating_sub(Duration::from_micros(self.stamp.load(Ordering::Relaxed)));
+        let fall = dsp::db_to_linear(-METER_DECAY_DB_PER_SEC * since.as_secs_f32());
+        self.peaks[..n].iter().map(|p| f32::from_bits(p.load(Ordering::Relaxed)) * fall).collect()
+    }
+}
+
+// ===== Review trim =====
+
+// in/out points (frame indexes, = samples for mono) over the take under review, moved
+// around by the `tw` loop in main.rs until the user is happy.
+// always a valid, non-empty range inside the take: every setter clamps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrimWindow {
+    pub start: usize,
+    pub end: usize, // exclusive
+    len: usize,
+}
+
+impl TrimWindow {
+    pub fn new(len: usize) -> Self {
+        Self { start: 0, end: len, len }
+    }
+
+    // in point, kept at least one sample before the out point
+    pub fn set_start(&mut self, at: i64) {
+        self.start = at.clamp(0, self.end.saturating_sub(1) as i64) as usize;
+    }
+
+    // out point, kept at least one sample after the in point
+    pub fn set_end(&mut self, at: i64) {
+        let min = (self.start + 1).min(self.len);
+        self.end = at.clamp(min as i64, self.len as i64) as usize;
+    }
+
+    pub fn nudge_start(&mut self, delta: i64) {
+        self.set_start(self.start as i64 + delta);
+    }
+
+    pub fn nudge_end(&mut self, delta: i64) {
+        self.set_end(self.end as i64 + delta);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.start == 0 && self.end == self.len
+    }
+}
+
 // ===== State =====
 
+// anything below this peak is treated as "nothing was captured"
+// (~ -80 dBFS, well under any real room noise floor)
+const SILENT_PEAK: f32 = 1e-4;
+
+// project snapshots kept for u/z, bounds memory since each one is a full copy
+const MAX_UNDO_STEPS: usize = 50;
+
+// upper bound for review repeats so a typo can't queue an hour of audio
+const MAX_REVIEW_REPEATS: u32 = 10;
+
+const REFERENCE_GAP_MS: f32 = 300.0; // between the reference tone and the segment
+
+// `norm` target, just under full scale so dither/resampling can't tip it over
+pub const NORMALIZE_PEAK: f32 = 0.99;
+
+// `pitch` semitones, two octaves either way keeps the stretch inside STRETCH_RANGE
+pub const PITCH_RANGE: std::ops::RangeInclusive<f32> = -24.0..=24.0;
+
+// pause kept either side of an auto_split cut
+const AUTO_SPLIT_PAD_MS: f32 = 150.0;
+
+// `stretch` factors, past these the overlap-add artifacts are all you hear
+pub const STRETCH_RANGE: std::ops::RangeInclusive<f32> = 0.25..=4.0;
+
+// what approve() noticed about the take it just committed,
+// the take is still committed unless `discarded` is set, the rest
+// are only warnings for the UI
+#[derive(Default)]
+pub struct ApproveResult {
+    pub silent: bool, // peak below SILENT_PEAK, mic muted?
+    pub clipped: usize, // samples at/over full scale, input gain too hot?
+    pub too_short: bool, // under settings.min_segment_ms, an accidental r/s tap?
+    pub discarded: bool, // too short and settings.reject_short, nothing was committed
+    // an auto-export threshold was crossed: the caller exports the project
+    // and only calls start_new_session once that worked
+    pub session_due: bool,
+}
+
 // tracks the entire app state
 #[derive(PartialEq)]
 pub enum AppState {
@@ -56,29 +528,87 @@ pub enum PlaybackState {
 pub enum Command {
     StartRecording,
     StopRecording,
+    PauseRecording,
+    ResumeRecording,
     Approve,
     Reject,
     RetryCurrentTake,
+    RestoreRejected,
     PlaySegment(usize),
-    PlayAll,
+    PlaySegmentFrom(usize, f32), // (index, start seconds), the segment on its own
+    PlayRange(usize, usize), // inclusive, 0-based
+    LoopSegment(usize, Option<u32>), // (index, times), None = until stopped
+    PlayAll(f32), // seconds of silence between segments, 0 = as exported
+    PlayReference(f32, usize), // (tone level dBFS, segment index)
+    StopPlayback,
     RetrySegment(usize),
     InsertAfter(usize),
+    InsertAt(f32),      // timeline position in seconds
+    InsertSilence(usize, f32), // (after index, seconds)
+    NameSegment(usize, Option<String>),
+    SetMetadata(String, Option<String>), // (field, text), None = remove
+    SetGain(usize, f32),
+    SetPan(usize, f32),
+    MoveSegment(usize, usize),
+    DuplicateSegment(usize),
+    ReverseSegment(usize),
+    SplitSegment(usize, f32),      // (index, seconds into it)
+    Paste(usize, f32),             // clipboard into (index, seconds into it)
+    MergeSegments(usize, usize),   // inclusive range // (from, to)
     DeleteSegment(usize),
+    ClearProject,
+    TimeStretch(usize, f32), // (index, factor)
+    PitchShift(usize, f32), // (index, semitones)
     TrimStart(Option<usize>, f32),
     TrimEnd(Option<usize>, f32),   // (index, seconds) - None = current
+    TrimSilence(usize, f32, f32),  // (index, threshold, pad_ms)
+    Filter(usize, FilterKind, f32, f32), // (index, shape, freq_hz, q)
+    HighPass(usize, f32), // (index, cutoff_hz)
+    Reverb(usize, f32, f32),       // (index, room_size, wet)
+    Delay(usize, f32, f32, f32),   // (index, time_ms, feedback, wet)
+    RenderEffect(usize, EffectSpec, usize), // (source, effect, insert_after)
+    MatchLoudness(f32),            // target RMS in dBFS
+    Normalize(Option<usize>),      // one segment, None = all of them
+    NormalizeProject(f32),         // target peak, one factor for all segments
+    RemoveDc(Option<usize>),       // one segment, None = all of them
     Undo,
     Redo,
     Export(Option<String>), // None = use auto-path, Some = use explicit path
+    ExportPackage(String),  // directory for combined + per-segment WAVs and manifest
+    ExportSegments(String, String), // (directory, file prefix), one WAV per segment
     LoadProject(String),
+    AppendProject(String),
+    ImportWav(String),
     SaveProjectAs(String),
 }
 
+impl Command {
+    // commands that open a new take and so start capturing audio
+    pub fn arms_recording(&self) -> bool {
+        matches!(self, Command::StartRecording | Command::RetryCurrentTake
+            | Command::RetrySegment(_) | Command::InsertAfter(_) | Command::InsertAt(_))
+    }
+
+    // edits that add, remove or reorder segments. a pending retake or
+    // insert holds a slot index, so these wait until the take is settled
+    pub fn restructures(&self) -> bool {
+        matches!(self, Command::InsertAt(_) | Command::InsertSilence(..) | Command::DeleteSegment(_)
+            | Command::ClearProject | Command::MoveSegment(..) | Command::DuplicateSegment(_)
+            | Command::SplitSegment(..) | Command::Paste(..) | Command::MergeSegments(..)
+            | Command::RenderEffect(..))
+    }
+}
+
 pub struct RecorderState {
     pub state: AppState,
     pub current: Option<Segment>, // current chunk being recorded/reviewed
     pub project: Project, // all chunks
     pub is_insertion: bool, // helps decide between replace vs insert
     pub playback_state: PlaybackState,
+    // raised to ask the running playback thread to stop early, each
+    // playback gets a fresh flag so a stale thread can't be confused
+    pub playback_stop: Arc<AtomicBool>,
+    pub meter: Arc<LevelMeter>, // shared with the input callback
     // project history
     // automatically triggered by edit methods
     pub history: Vec<Project>, // backup for commited segments
@@ -86,6 +616,22 @@ pub struct RecorderState {
     pub previous_current: Option<Segment>, // backup for uncommitted segment
     pub next_current: Option<Segment>, // redo backup for uncommitted segment
     pub save_path: Option<String>, // where the project is saved
+    pub recovery_dir: Option<PathBuf>, // where this session keeps its crash-recovery snapshot, None = off
+    pub capture_channels: u16, // layout the input callback produces (mono after down-mix)
+    pub device_sample_rate: Option<u32>, // rate the input stream runs at, None = no input (playback/export only)
+    pub review_id: u64, // bumped on every stop, identifies one review for the timeout thread
+    // auto-stop bookkeeping, kept across input callbacks (see track_silence)
+    pub silence_samples: usize, // trailing run of quiet samples in the take being recorded
+    pub heard_speech: bool,     // the take has gone above the threshold at least once
+    pub paused: bool, // take stays open but the input callback doesn't append, only while Recording
+    pub auto_stopped: Option<AutoStop>, // set when the input callback stopped a take, taken by the app to start the review
+    pub last_rejected: Option<Segment>, // most recent rejected take, for `restore`
+    // where that take was headed (editing_index, is_insertion) while `u` can
+    // still bring it back, cleared once the project moves on
+    pub undo_reject: Option<(Option<usize>, bool)>,
+    pub redo_reject: bool, // the take under review came back through undo, `z` rejects it again
+    pub clipboard: Option<Vec<f32>>, // last copy_range, interleaved like the project, gain applied
+    pub settings: Settings,
 }
 
 // holds the the current segment being recorded, the state
@@ -97,6 +643,7 @@ impl RecorderState { // master struct
             sample_rate,
             channels,
             editing_index: None,
+            metadata: Default::default(),
         };
 
         Self {
@@ -104,6 +651,8 @@ impl RecorderState { // master struct
             current: None,
             is_insertion: false,
             playback_state: PlaybackState::Idle,
+            playback_stop: Arc::new(AtomicBool::new(false)),
+            meter: Arc::new(LevelMeter::default()),
             project: empty_project.clone(), 
             // capture the initial state to be able to undo back to an empty project
             history: vec![empty_project], 
@@ -111,6 +660,19 @@ impl RecorderState { // master struct
             previous_current: None,
             next_current: None,
             save_path: None,
+            recovery_dir: None,
+            capture_channels: channels,
+            device_sample_rate: None,
+            review_id: 0,
+            silence_samples: 0,
+            heard_speech: false,
+            paused: false,
+            auto_stopped: None,
+            last_rejected: None,
+            undo_reject: None,
+            redo_reject: false,
+            clipboard: None,
+            settings: Settings::default(),
         }
     }
 
@@ -122,7 +684,7 @@ impl RecorderState { // master struct
 
     // *** Project History
     // save current project state to history (call BEFORE modifying)
-    fn save_state(&mut self) {
+    pub fn save_state(&mut self) {
         // truncate history beyond current index (discard redo branch)
         // check if history is non-empty before subtracting to prevent usize underflow
         if !self.history.is_empty() && self.history_index < self.history.len() - 1 {
@@ -132,8 +694,7 @@ impl RecorderState { // master struct
         self.history.push(self.project.clone());
         self.history_index = self.history.len() - 1;
         
-        const MAX_HISTORY: usize = 5; // limit history size
-        if self.history.len() > MAX_HISTORY {
+        if self.history.len() > MAX_UNDO_STEPS {
             self.history.remove(0); // remove oldest
             if self.history_index > 0 {
                 self.history_index -= 1; // reposition one back
@@ -141,36 +702,170 @@ impl RecorderState { // master struct
         }
         
         self.next_current = None; // clear redo backup for current segment
+        self.undo_reject = None;
+        self.redo_reject = false;
         self.save_to_disk(); // trigger auto-save to disk
+        self.write_recovery();
     }
 
+    // auto-save after every edit, failures are ignored (see save_project_as)
     pub fn save_to_disk(&self) {
         if let Some(path) = &self.save_path {
-            if let Ok(encoded) = bincode::serialize(&self.project) {
-                let _ = std::fs::write(path, encoded);
-            }
+            let _ = project_io::save_project(&self.project, path);
+        }
+    }
+
+    // this session's snapshot, named after the project it's saving to
+    pub fn recovery_file(&self) -> Option<PathBuf> {
+        let dir = self.recovery_dir.as_ref()?;
+        Some(dir.join(project_io::recovery_file_name(self.save_path.as_deref())))
+    }
+
+    // recovery snapshot of the committed segments, refreshed with every
+    // history step. failures are ignored like the auto-save. an empty
+    // project removes it, there's nothing left to lose
+    pub fn write_recovery(&self) {
+        let Some(path) = self.recovery_file() else { return; };
+        if self.project.segments.is_empty() {
+            let _ = std::fs::remove_file(path);
+        } else {
+            let _ = project_io::save_project(&self.project, path);
+        }
+    }
+
+    // snapshot for an interrupted session: the project plus the take being
+    // recorded or reviewed (as the last segment), since that's the one most
+    // likely to be lost. returns how many segments were written, 0 when
+    // there was nothing to keep, no recovery dir or the write failed
+    pub fn write_recovery_with_take(&self) -> usize {
+        let Some(path) = self.recovery_file() else { return 0; };
+        let mut project = self.project.clone();
+        if let Some(take) = self.current.as_ref().filter(|t| !t.samples.is_empty()) {
+            project.segments.push(take.clone());
         }
+        if project.segments.is_empty() || project_io::save_project(&project, path).is_err() { return 0; }
+        project.segments.len()
     }
 
+    // the session ended cleanly, the snapshot isn't needed anymore
+    pub fn clear_recovery(&self) {
+        if let Some(path) = self.recovery_file() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    // the snapshot is named after the save path, move it along when that changes
+    fn change_save_path(&mut self, path: Option<String>) {
+        self.clear_recovery();
+        self.save_path = path;
+        self.write_recovery();
+    }
+
+    // take the project back from a recovery snapshot. unlike `open` the
+    // snapshot doesn't become the save path, this session writes its own
+    pub fn restore_recovery(&mut self, path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        let project = project_io::load_project(path)?;
+        if let Some(rate) = self.recording_rate() {
+            project_io::check_sample_rate(&project, rate)?;
+        }
+        let count = project.segments.len();
+        self.replace_project(project);
+        Ok(count)
+    }
+
+    // explicit save, remembered as the auto-save path once it worked
+    pub fn save_project_as(&mut self, path: String) -> Result<(), Box<dyn std::error::Error>> {
+        project_io::save_project(&self.project, &path)?;
+        self.change_save_path(Some(path));
+        Ok(())
+    }
+
+    // the rate takes are stored at: the target when one is set, otherwise
+    // the device's. None without an input device
+    pub fn recording_rate(&self) -> Option<u32> {
+        self.device_sample_rate.map(|device| self.settings.target_sample_rate.unwrap_or(device))
+    }
+
+    // replace the project with a saved one. refused when it was recorded at
+    // a different rate than takes are recorded at, the current project is kept.
+    // without an input device any rate opens, it's only played and exported
     pub fn load_from_disk(&mut self, path: String) -> Result<(), Box<dyn std::error::Error>> {
-        let data = std::fs::read(&path)?;
-        let project: Project = bincode::deserialize(&data)?;
+        let project = project_io::load_project(&path)?;
+        if let Some(rate) = self.recording_rate() {
+            project_io::check_sample_rate(&project, rate)?;
+        }
         
+        self.replace_project(project);
+        self.change_save_path(Some(path));
+        Ok(())
+    }
+
+    fn replace_project(&mut self, project: Project) {
         self.project = project.clone();
-        self.save_path = Some(path);
-        
         // reset history on load
         self.history = vec![project];
         self.history_index = 0;
         self.previous_current = None;
         self.next_current = None;
-        
-        Ok(())
+        self.write_recovery();
+    }
+
+    // add every segment of another saved project to the end of this one,
+    // converting its channel count and sample rate if needed. returns how
+    // many were added
+    pub fn append_project(&mut self, path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let other = project_io::load_project(path)?;
+        let (rate, channels) = (self.project.sample_rate, self.project.channels);
+        let added = other.segments.len();
+        for mut seg in other.segments {
+            if other.channels != channels {
+                seg.samples = crate::import::remix(&seg.samples, other.channels, channels);
+                seg.log(format!("re-mixed {}ch -> {}ch", other.channels, channels));
+            }
+            if other.sample_rate != rate {
+                seg.samples = dsp::resample_interleaved(&seg.samples, channels, other.sample_rate, rate);
+                seg.log(format!("resampled {}Hz -> {}Hz", other.sample_rate, rate));
+            }
+            self.project.segments.push(seg);
+        }
+        self.save_state();
+        Ok(added)
+    }
+
+    // move the whole project to another sample rate, e.g. when the input
+    // device runs at a different rate than the project was recorded at.
+    // every segment is resampled so it keeps its duration and pitch.
+    // undo history is reset, stepping back across the conversion would
+    // bring back audio at the old rate. returns how many were converted
+    pub fn convert_sample_rate(&mut self, rate: u32) -> usize {
+        let from = self.project.sample_rate;
+        if from == rate {
+            return 0;
+        }
+        let channels = self.project.channels;
+        for seg in &mut self.project.segments {
+            seg.samples = dsp::resample_interleaved(&seg.samples, channels, from, rate);
+            seg.log(format!("resampled {}Hz -> {}Hz", from, rate));
+        }
+        self.project.sample_rate = rate;
+        self.history = vec![self.project.clone()];
+        self.history_index = 0;
+        self.project.segments.len()
+    }
+
+    // append a WAV file as a new segment, converted to the project's
+    // channel count and sample rate
+    pub fn import_wav(&mut self, path: &str) -> Result<&Segment, Box<dyn std::error::Error>> {
+        let seg = crate::import::import_wav(path, self.project.channels, self.project.sample_rate)?;
+        self.project.segments.push(seg);
+        self.save_state();
+        Ok(self.project.segments.last().unwrap())
     }
 
     // save the current uncommitted segment to backup (call BEFORE modifying)
     fn push_current_backup(&mut self) {
         self.next_current = None; // clear redo backup when state is changed
+        self.redo_reject = false;
         if let Some(seg) = &self.current {
             self.previous_current = Some(seg.clone());
         }
@@ -180,6 +875,21 @@ impl RecorderState { // master struct
     // if there's an uncommited backup segment, switch it
     // if there's a previous project copy, switch to it
     pub fn undo(&mut self) -> bool {
+        // case 0: bring back a take that was just rejected, aimed where it was
+        if self.state == AppState::Idle
+            && let Some((slot, insertion)) = self.undo_reject
+            && let Some(seg) = self.last_rejected.take()
+        {
+            self.undo_reject = None;
+            self.current = Some(seg);
+            self.project.editing_index = slot;
+            self.is_insertion = insertion;
+            self.state = AppState::Reviewing;
+            self.review_id += 1;
+            self.redo_reject = true;
+            return true;
+        }
+
         // case 1: undo uncommitted segment (e.g. trim during Reviewing)
         if let Some(prev) = self.previous_current.take() {
             if let Some(curr) = &self.current {
@@ -193,6 +903,7 @@ impl RecorderState { // master struct
         if self.history_index > 0 {
             self.history_index -= 1;
             self.project = self.history[self.history_index].clone();
+            self.write_recovery();
             return true;
         }
 
@@ -200,6 +911,12 @@ impl RecorderState { // master struct
     }
 
     pub fn redo(&mut self) -> bool {
+        // case 0: reject the take that undo brought back
+        if self.redo_reject && self.state == AppState::Reviewing && self.next_current.is_none() {
+            self.reject();
+            return true;
+        }
+
         // case 1: redo uncommitted segment
         if let Some(next) = self.next_current.take() {
             if let Some(curr) = &self.current {
@@ -213,34 +930,160 @@ impl RecorderState { // master struct
         if self.history_index < self.history.len() - 1 {
             self.history_index += 1;
             self.project = self.history[self.history_index].clone();
+            self.write_recovery();
             return true;
         }
 
         false
     }
 
+    // how the input callback should lay out frames for this project
+    pub fn capture_plan(&self) -> Result<ChannelPlan, String> {
+        reconcile_channels(self.project.channels, self.capture_channels, self.settings.channel_mismatch)
+    }
+
+    // *** Playback lifecycle ***
+    // Idle -> begin_playback -> Playing -> (request_stop_playback) -> finish_playback -> Idle
+    // the audio thread owns the device, these only move the shared state
+
+    // mark playback as running and hand back the stop flag for the thread
+    pub fn begin_playback(&mut self) -> Arc<AtomicBool> {
+        self.playback_state = PlaybackState::Playing;
+        self.playback_stop = Arc::new(AtomicBool::new(false));
+        self.playback_stop.clone()
+    }
+
+    // ask the playback thread to halt, state stays Playing until the thread
+    // has actually released the device and called finish_playback
+    pub fn request_stop_playback(&mut self) -> bool {
+        if self.playback_state != PlaybackState::Playing { return false; }
+        self.playback_stop.store(true, Ordering::Relaxed);
+        true
+    }
+
+    pub fn finish_playback(&mut self) {
+        self.playback_state = PlaybackState::Idle;
+    }
+
     // *** Workflow Methods ***
 
     // create an empty segment and start recording
     pub fn start_recording(&mut self) {
         self.state = AppState::Recording;
         self.is_insertion = false; // append not insert
-        self.current = Some(Segment { samples: Vec::new() });
+        self.current = Some(Segment::recording());
         self.project.editing_index = None; // None: segment at end default
         // starting a new take invalidates old undo history for the previous take
         self.previous_current = None;
         self.next_current = None;
     }
 
+    // hold the take open without recording into it (a cough, a page turn).
+    // false unless a take is being recorded
+    pub fn pause_recording(&mut self) -> bool {
+        if self.state != AppState::Recording { return false; }
+        self.paused = true;
+        true
+    }
+
+    pub fn resume_recording(&mut self) -> bool {
+        if self.state != AppState::Recording || !self.paused { return false; }
+        self.paused = false;
+        true
+    }
+
     pub fn stop_recording(&mut self) {
+        self.paused = false;
         if let AppState::Recording = self.state {
             self.state = AppState::Reviewing; // only review if we were recording
+            self.review_id += 1;
+            if let Some(threshold) = self.settings.auto_trim {
+                self.auto_trim_current(threshold);
+            }
+        }
+    }
+
+    // auto-stop: the input callback hands over each mono block before
+    // appending it. silence before the first word doesn't count, so a slow
+    // start isn't cut off; after that every quiet sample extends the run and
+    // a loud one resets it. true once the run reaches settings.auto_stop
+    pub fn track_silence(&mut self, block: &[f32]) -> bool {
+        let Some(secs) = self.settings.auto_stop else { return false; };
+        let Some(seg) = &self.current else { return false; };
+        if seg.samples.is_empty() { // a new take, start counting afresh
+            self.silence_samples = 0;
+            self.heard_speech = false;
+        }
+        let threshold = self.settings.auto_stop_threshold;
+        for &s in block {
+            if s.abs() > threshold {
+                self.heard_speech = true;
+                self.silence_samples = 0;
+            } else if self.heard_speech {
+                self.silence_samples += 1;
+            }
+        }
+        let limit = ms_to_samples(secs * 1000.0, self.project.sample_rate) * self.capture_channels.max(1) as usize;
+        self.silence_samples >= limit.max(1)
+    }
+
+    // how long the take in progress (or under review) runs, 0 without one
+    pub fn take_seconds(&self) -> f32 {
+        self.current.as_ref()
+            .map(|s| s.duration_seconds(self.project.sample_rate, self.project.channels))
+            .unwrap_or(0.0)
+    }
+
+    // true once the take being recorded has reached settings.max_record_seconds
+    pub fn reached_max_length(&self) -> bool {
+        let (Some(secs), Some(seg)) = (self.settings.max_record_seconds, &self.current) else { return false; };
+        let limit = ms_to_samples(secs * 1000.0, self.project.sample_rate) * self.project.channels.max(1) as usize;
+        seg.samples.len() >= limit.max(1)
+    }
+
+    // cut the lead-in before the first word and the tail after the last
+    // (including the stop keypress), keeping settings.trim_pad_ms either side.
+    // backed up first so `u` gets the raw take back
+    fn auto_trim_current(&mut self, threshold: f32) {
+        let pad = ms_to_samples(self.settings.trim_pad_ms, self.project.sample_rate);
+        let Some(seg) = &self.current else { return; };
+        let Some((first, last)) = speech_bounds(&seg.samples, threshold) else { return; };
+        let ch = self.project.channels.max(1) as usize;
+        if first / ch <= pad && (last / ch + 1 + pad) * ch >= seg.samples.len() { return; } // already tight
+        self.push_current_backup();
+        let channels = self.project.channels;
+        if let Some(seg) = &mut self.current {
+            seg.trim_silence(threshold, pad, channels);
+            seg.log(format!("auto-trim < {} (pad {}ms)", threshold, self.settings.trim_pad_ms));
         }
     }
 
+    // called by the review timer when it fires: the default action, but only
+    // if that same review is still undecided (the user may have already hit
+    // c/x, or retried, which starts a new review with a new id)
+    pub fn review_timeout_action(&self, review_id: u64) -> Option<ReviewAction> {
+        self.settings.review_timeout?;
+        if self.state != AppState::Reviewing || self.review_id != review_id { return None; }
+        Some(self.settings.review_default)
+    }
+
     // appends the approved segment into project.segments
-    pub fn approve(&mut self) {
+    pub fn approve(&mut self) -> ApproveResult {
+        let mut result = ApproveResult::default();
+        if let Some(min_ms) = self.settings.min_segment_ms
+            && let Some(seg) = &self.current
+            && seg.samples.len() < ms_to_samples(min_ms, self.project.sample_rate) * self.project.channels.max(1) as usize
+        {
+            result.too_short = true;
+            if self.settings.reject_short {
+                self.reject();
+                result.discarded = true;
+                return result;
+            }
+        }
         if let Some(seg) = self.current.take() { // if current segment exists
+            result.silent = seg.peak() < SILENT_PEAK;
+            result.clipped = seg.clipped_samples();
             match self.project.editing_index.take() { // if index is provided
                 Some(idx) if idx <= self.project.segments.len() => { // in bound
                     if self.is_insertion { // if insert, slide it in
@@ -255,28 +1098,78 @@ impl RecorderState { // master struct
             }
         }
         self.state = AppState::Idle;
+        self.paused = false;
         self.is_insertion = false;
         // once approved, clear current-segment undo buffers when lifecycle ends
         self.previous_current = None;
         self.next_current = None;
+        result.session_due = self.auto_export_due();
+        result
+    }
+
+    // has the project grown past either auto-export threshold?
+    fn auto_export_due(&self) -> bool {
+        let by_count = self.settings.auto_export_segments
+            .is_some_and(|max| self.project.segments.len() >= max);
+        let by_time = self.settings.auto_export_seconds
+            .is_some_and(|max| self.total_seconds() >= max);
+        by_count || by_time
+    }
+
+    // drop the exported session and start an empty one with the same format.
+    // history restarts at the empty project (no save_state, it would be a
+    // second copy) and the save path is forgotten so the empty project is
+    // never auto-saved over the session's file
+    pub fn start_new_session(&mut self) {
+        self.project = Project {
+            segments: Vec::new(),
+            sample_rate: self.project.sample_rate,
+            channels: self.project.channels,
+            editing_index: None,
+            metadata: self.project.metadata.clone(), // same show, same tags
+        };
+        self.history = vec![self.project.clone()];
+        self.history_index = 0;
+        self.change_save_path(None);
     }
     // retry can provide an idx number, which is held in struct
     // so approve accounts for both cases
 
     pub fn reject(&mut self) {
-        self.current = None; // delete current segment
+        // kept aside instead of dropped, in case it was the good one after all.
+        // `u` puts it back where it was going, `restore` appends it
+        if let Some(seg) = self.current.take().filter(|s| !s.samples.is_empty()) {
+            self.last_rejected = Some(seg);
+            self.undo_reject = Some((self.project.editing_index, self.is_insertion));
+        }
+        self.redo_reject = false;
         self.project.editing_index = None;
         self.state = AppState::Idle;
+        self.paused = false;
         // once rejected, clear current-segment undo buffers
         self.previous_current = None;
         self.next_current = None;
     }
 
+    // bring the last rejected take back for review, as a new take at the
+    // end of the project (where a retake was meant to go isn't kept).
+    // only from Idle, so nothing in progress is replaced
+    pub fn restore_rejected(&mut self) -> bool {
+        if self.state != AppState::Idle || self.last_rejected.is_none() { return false; }
+        self.current = self.last_rejected.take();
+        self.undo_reject = None;
+        self.is_insertion = false;
+        self.project.editing_index = None;
+        self.state = AppState::Reviewing;
+        self.review_id += 1;
+        true
+    }
+
     // retry the take that was just recorded
     pub fn retry_current_take(&mut self) {
         if self.state == AppState::Reviewing {
             // Create a new empty segment for the retry
-            self.current = Some(Segment { samples: Vec::new() });
+            self.current = Some(Segment::recording());
             // Switch back to recording from Idle
             self.state = AppState::Recording; // automatically starts recording
             // IMPORTANT: We do NOT reset editing_index or is_insertion here.
@@ -292,7 +1185,10 @@ impl RecorderState { // master struct
         
         self.project.editing_index = Some(index);
         self.is_insertion = false; // overwriting
-        self.current = Some(Segment { samples: Vec::new() });
+        // a retake fixes the audio, it keeps the segment's name
+        let mut retake = Segment::recording();
+        retake.name = self.project.segments[index].name.clone();
+        self.current = Some(retake);
         self.state = AppState::Recording;
         true
     }
@@ -303,55 +1199,506 @@ impl RecorderState { // master struct
         
         self.project.editing_index = Some(after_index + 1); // index after
         self.is_insertion = true; // inserting
-        self.current = Some(Segment { samples: Vec::new() });
+        self.current = Some(Segment::recording());
         self.state = AppState::Recording;
         true
     }
 
-    // removes a segment
-    pub fn delete_segment(&mut self, index: usize) -> bool {
-        if index >= self.project.segments.len() { return false; }
-        self.project.segments.remove(index);
+    // record a new take to land at `seconds` on the project timeline. a time
+    // inside a segment splits it there first (see split_segment) so the take
+    // goes between the halves; a time on a boundary or at the very end needs
+    // no split. false when the time is past the end of the project
+    pub fn prepare_insert_at_time(&mut self, seconds: f32) -> bool {
+        if !self.can_restructure() { return false; }
+        let channels = self.project.channels.max(1) as usize;
+        let target = ms_to_samples(seconds * 1000.0, self.project.sample_rate);
+        let mut start = 0; // frame where the segment being looked at begins
+        let mut position = None;
+        for (i, seg) in self.project.segments.iter().enumerate() {
+            if target <= start { position = Some(i); break; }
+            let frames = seg.samples.len() / channels;
+            if target < start + frames {
+                let offset = (target - start) as f32 / self.project.sample_rate as f32;
+                if !self.split_segment(i, offset) { return false; }
+                position = Some(i + 1);
+                break;
+            }
+            start += frames;
+        }
+        let index = match position {
+            Some(i) => i,
+            None if target <= start => self.project.segments.len(), // right at the end
+            None => return false,
+        };
+
+        self.project.editing_index = Some(index);
+        self.is_insertion = true;
+        self.current = Some(Segment::recording());
+        self.state = AppState::Recording;
         true
     }
 
-    // optionally add empty segments in between recordings
-    // silence(0.5, sample_rate) would add a 0.5s silence
-    #[allow(unused)]
-    fn silence(seconds: f32, sample_rate: u32) -> Segment {
-        let count = (seconds * sample_rate as f32) as usize;
-        Segment {
-            samples: vec![0.0; count],
-        }
+    // set one of ProjectMetadata::FIELDS, None (or empty text) removes the tag.
+    // false for an unknown field
+    pub fn set_metadata(&mut self, field: &str, value: Option<String>) -> bool {
+        let Some(slot) = self.project.metadata.field_mut(field) else { return false; };
+        *slot = value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        true
     }
 
-    pub fn trim_start(&mut self, segment_index: Option<usize>, seconds: f32) -> bool {
-        let sample_rate = self.project.sample_rate;
-        let samples_to_trim = (seconds * sample_rate as f32) as usize;
+    // label a segment, None (or an empty name) clears it back to just its number
+    pub fn name_segment(&mut self, index: usize, name: Option<String>) -> bool {
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        seg.name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        true
+    }
 
-        if samples_to_trim == 0 { return false; }
+    // non-destructive volume: stored on the segment and applied when it's
+    // played or exported, so it can be changed (or undone) without loss
+    pub fn set_gain(&mut self, index: usize, gain: f32) -> bool {
+        if !gain.is_finite() || gain < 0.0 { return false; }
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        seg.gain = gain;
+        seg.log(format!("gain set to {:.2}", gain));
+        true
+    }
 
-        match segment_index {
-            None => { // current segment
-                self.push_current_backup(); // save backup
-                if let Some(ref mut seg) = self.current {
-                    if samples_to_trim >= seg.samples.len() {
-                        seg.samples.clear(); // Trim all if requested >= length
-                    } else {
-                        seg.samples.drain(0..samples_to_trim);
-                    }
-                    return true;
-                }
-            }
-            Some(idx) => {
-                self.save_state(); // save backup (not current segment so save state)
-                if idx < self.project.segments.len() {
-                    let seg = &mut self.project.segments[idx];
-                    if samples_to_trim >= seg.samples.len() {
-                        seg.samples.clear();
-                    } else {
-                        seg.samples.drain(0..samples_to_trim);
+    // a muted take stays in the project (and plays with `p <n>`) but is
+    // skipped by `pa` and the exports, for trying the project without it
+    pub fn mute_segment(&mut self, index: usize) -> bool {
+        self.set_muted(index, true)
+    }
+
+    pub fn unmute_segment(&mut self, index: usize) -> bool {
+        self.set_muted(index, false)
+    }
+
+    fn set_muted(&mut self, index: usize, muted: bool) -> bool {
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        seg.muted = muted;
+        seg.log(if muted { "muted".into() } else { "unmuted".into() });
+        true
+    }
+
+    // stereo placement, -1.0 (left) ..= 1.0 (right). refused on mono
+    // projects, where there's nowhere to move the take
+    pub fn set_pan(&mut self, index: usize, pan: f32) -> bool {
+        if self.project.channels != 2 || !(-1.0..=1.0).contains(&pan) { return false; }
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        seg.pan = pan;
+        seg.log(format!("pan set to {:+.2}", pan));
+        true
+    }
+
+    // reorder: take segment `from` out and put it back so it ends up at `to`.
+    // `to` may equal the segment count, which means "to the end"
+    pub fn move_segment(&mut self, from: usize, to: usize) -> bool {
+        if !self.can_restructure() { return false; }
+        let len = self.project.segments.len();
+        if from >= len || to > len { return false; }
+        let to = to.min(len - 1);
+        if from == to { return true; }
+        let seg = self.project.segments.remove(from);
+        self.project.segments.insert(to, seg);
+        true
+    }
+
+    // copy of segment `index` (audio, name, gain, history) right after it,
+    // so one take can be edited two ways
+    pub fn duplicate_segment(&mut self, index: usize) -> bool {
+        if !self.can_restructure() { return false; }
+        let Some(seg) = self.project.segments.get(index) else { return false; };
+        let mut copy = seg.clone();
+        copy.log(format!("duplicated from segment {}", index + 1));
+        self.project.segments.insert(index + 1, copy);
+        true
+    }
+
+    // play a take backwards. reverses whole frames, not raw samples, so
+    // a stereo segment keeps left on the left
+    pub fn reverse_segment(&mut self, index: usize) -> bool {
+        let channels = self.project.channels.max(1) as usize;
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        seg.samples = seg.samples.chunks_exact(channels).rev().flatten().copied().collect();
+        seg.log("reversed".into());
+        true
+    }
+
+    // removes a segment
+    pub fn delete_segment(&mut self, index: usize) -> bool {
+        if !self.can_restructure() || index >= self.project.segments.len() { return false; }
+        self.project.segments.remove(index);
+        true
+    }
+
+    // start over: every segment is dropped, the sample rate and channel
+    // layout stay. undoable like any other edit, refused mid-take
+    pub fn clear_project(&mut self) -> bool {
+        if !self.can_restructure() { return false; }
+        self.project.segments.clear();
+        self.project.editing_index = None;
+        true
+    }
+
+    // segments can only be added, removed or reordered from Idle: a take
+    // being recorded or reviewed may be a retake/insert aimed at a slot
+    pub fn can_restructure(&self) -> bool {
+        self.state == AppState::Idle
+    }
+
+    // optionally add empty segments in between recordings
+    // silence(0.5, sample_rate, 1) would add a 0.5s silence
+    fn silence(seconds: f32, sample_rate: u32, channels: u16) -> Segment {
+        let count = (seconds * sample_rate as f32) as usize * channels.max(1) as usize;
+        Segment::new(vec![0.0; count])
+    }
+
+    // a deliberate pause after segment `after_index`, as its own segment
+    // so it can be moved, trimmed or deleted like a take
+    pub fn insert_silence(&mut self, after_index: usize, seconds: f32) -> bool {
+        if !self.can_restructure() { return false; }
+        if after_index >= self.project.segments.len() || !seconds.is_finite() || seconds <= 0.0 { return false; }
+        let mut seg = Self::silence(seconds, self.project.sample_rate, self.project.channels);
+        seg.log(format!("silence {}s", seconds));
+        self.project.segments.insert(after_index + 1, seg);
+        true
+    }
+
+    // strip dead air from both ends of a committed segment, keeping
+    // `pad_ms` of it beyond the detected speech so the edges breathe
+    pub fn trim_silence(&mut self, index: usize, threshold: f32, pad_ms: f32) -> bool {
+        if !pad_ms.is_finite() { return false; }
+        let (pad, channels) = (ms_to_samples(pad_ms, self.project.sample_rate), self.project.channels);
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        if !seg.trim_silence(threshold, pad, channels) { return false; }
+        seg.log(format!("trim silence < {} (pad {}ms)", threshold, pad_ms));
+        true
+    }
+
+    // *** Effect Methods ***
+    // destructive, applied in place to a committed segment's samples.
+    // sample-by-sample effects run through dsp::process_in_blocks (one
+    // processor per channel, see EffectSpec::apply) so long
+    // takes are walked settings.block_size frames at a time
+
+    // run a single biquad (see dsp.rs) over the segment
+    pub fn filter_segment(&mut self, index: usize, kind: FilterKind, freq: f32, q: f32) -> bool {
+        self.apply_effect(index, EffectSpec::Filter(kind, freq, q))
+    }
+
+    // rumble removal (traffic, handling noise): a Butterworth high-pass
+    // biquad at `cutoff_hz`, coefficients from the project's sample rate.
+    // false for a cutoff at or past Nyquist, which would filter out everything
+    pub fn high_pass(&mut self, index: usize, cutoff_hz: f32) -> bool {
+        let nyquist = self.project.sample_rate as f32 / 2.0;
+        if !(cutoff_hz > 0.0 && cutoff_hz < nyquist) { return false; }
+        self.filter_segment(index, FilterKind::HighPass, cutoff_hz, std::f32::consts::FRAC_1_SQRT_2)
+    }
+
+    // add room ambience, the segment grows by the reverb tail so it
+    // rings out instead of being cut off at the last spoken sample
+    pub fn reverb(&mut self, index: usize, room_size: f32, wet: f32) -> bool {
+        self.apply_effect(index, EffectSpec::Reverb(room_size, wet))
+    }
+
+    // feedback echo, lengthened by the decaying repeats
+    pub fn delay(&mut self, index: usize, time_ms: f32, feedback: f32, wet: f32) -> bool {
+        if !dsp::delay_time_ok(time_ms) { return false; }
+        self.apply_effect(index, EffectSpec::Delay(time_ms, feedback, wet))
+    }
+
+    // replace segment `index` with its two halves, split `seconds` in.
+    // false (and no change) when the offset isn't strictly inside the take
+    pub fn split_segment(&mut self, index: usize, seconds: f32) -> bool {
+        if !self.can_restructure() { return false; }
+        let frame = ms_to_samples(seconds * 1000.0, self.project.sample_rate);
+        let at = frame * self.project.channels.max(1) as usize; // keep frames whole
+        let Some(seg) = self.project.segments.get(index) else { return false; };
+        let Some((first, second)) = seg.split_at(at) else { return false; };
+        self.project.segments.splice(index..=index, [first, second]);
+        true
+    }
+
+    // cut a long take at its pauses: every run of frames under `threshold`
+    // lasting at least `min_silence_sec` between two bits of speech becomes
+    // a cut. each side keeps AUTO_SPLIT_PAD_MS of the pause (at most half of
+    // it) so words aren't clipped, the rest of the pause is dropped. silence
+    // at the very start or end isn't a pause and is left alone. level is the
+    // peak of 10ms windows, so zero crossings inside a word don't count.
+    // returns how many pieces the segment became, 1 = no pause long enough,
+    // 0 = no such segment (or a take is in progress)
+    pub fn auto_split(&mut self, index: usize, threshold: f32, min_silence_sec: f32) -> usize {
+        if !self.can_restructure() { return 0; }
+        let (rate, ch) = (self.project.sample_rate, self.project.channels.max(1) as usize);
+        let Some(seg) = self.project.segments.get(index) else { return 0; };
+        let window = ms_to_samples(10.0, rate).max(1); // frames
+        let min_gap = (min_silence_sec.max(0.0) * rate as f32) as usize;
+        let pad = ms_to_samples(AUTO_SPLIT_PAD_MS, rate);
+        let frames = seg.samples.len() / ch;
+
+        // (start, end) frame ranges to keep, a new one after each long pause
+        let mut pieces: Vec<(usize, usize)> = Vec::new();
+        let mut piece_start = 0;
+        let mut quiet_from: Option<usize> = None; // start of the current quiet run
+        let mut heard = false; // speech since piece_start
+        for w in (0..frames).step_by(window) {
+            let end = (w + window).min(frames);
+            let loud = seg.samples[w * ch..end * ch].iter().any(|s| s.abs() > threshold);
+            match (loud, quiet_from) {
+                (false, None) => quiet_from = Some(w),
+                (true, Some(q)) => {
+                    if heard && w - q >= min_gap {
+                        let keep = pad.min((w - q) / 2);
+                        pieces.push((piece_start, q + keep));
+                        piece_start = w - keep;
+                    }
+                    quiet_from = None;
+                    heard = true;
+                }
+                (true, None) => heard = true,
+                (false, Some(_)) => {}
+            }
+        }
+        if pieces.is_empty() { return 1; }
+        pieces.push((piece_start, frames));
+
+        let count = pieces.len();
+        let parts: Vec<Segment> = pieces.iter().enumerate().map(|(i, &(start, end))| {
+            let mut part = seg.clone();
+            part.samples = seg.samples[start * ch..end * ch].to_vec();
+            part.name = seg.name.as_ref().map(|n| format!("{}.{}", n, i + 1));
+            part.log(format!("auto-split at pauses >= {}s (part {}/{})", min_silence_sec, i + 1, count));
+            part
+        }).collect();
+        self.project.segments.splice(index..=index, parts);
+        count
+    }
+
+    // copy start..end seconds of segment `index` to the clipboard, as heard
+    // (gain applied). None when the range is empty or runs past the take
+    pub fn copy_range(&mut self, index: usize, start_sec: f32, end_sec: f32) -> Option<Vec<f32>> {
+        let ch = self.project.channels.max(1) as usize;
+        let (start, end) = (self.time_to_sample(start_sec)?, self.time_to_sample(end_sec)?);
+        let seg = self.project.segments.get(index)?;
+        if start >= end || end > seg.samples.len() / ch * ch { return None; }
+        let copied: Vec<f32> = seg.gained()[start..end].to_vec();
+        self.clipboard = Some(copied.clone());
+        Some(copied)
+    }
+
+    // splice `samples` into segment `index` at `at_sec`, whole frames only.
+    // they're scaled back by the segment's gain so they play as copied.
+    // false when the position is past the end of the take
+    pub fn paste_samples(&mut self, index: usize, at_sec: f32, samples: &[f32]) -> bool {
+        let ch = self.project.channels.max(1) as usize;
+        let Some(at) = self.time_to_sample(at_sec) else { return false; };
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        if at > seg.samples.len() / ch * ch || samples.is_empty() || !samples.len().is_multiple_of(ch) { return false; }
+        let unity = if seg.gain > 0.0 { 1.0 / seg.gain } else { 1.0 };
+        seg.samples.splice(at..at, samples.iter().map(|s| s * unity));
+        seg.log(format!("pasted {:.2}s at {:.2}s", (samples.len() / ch) as f32 / self.project.sample_rate as f32, at_sec));
+        true
+    }
+
+    pub fn paste_clipboard(&mut self, index: usize, at_sec: f32) -> bool {
+        if !self.can_restructure() { return false; }
+        let Some(clip) = self.clipboard.take() else { return false; };
+        let pasted = self.paste_samples(index, at_sec, &clip);
+        self.clipboard = Some(clip); // can be pasted again
+        pasted
+    }
+
+    // seconds into a take -> index of the first sample of that frame
+    fn time_to_sample(&self, seconds: f32) -> Option<usize> {
+        if !seconds.is_finite() || seconds < 0.0 { return None; }
+        Some(ms_to_samples(seconds * 1000.0, self.project.sample_rate) * self.project.channels.max(1) as usize)
+    }
+
+    // glue segments start..=end (at least two) into one, in place. each
+    // part's gain (and in stereo its pan) is baked into the samples first so
+    // it sounds the same; the merged take keeps the first part's name and
+    // history. refused when a part is muted, its audio would become audible
+    // and the mute lost
+    pub fn merge_segments(&mut self, start: usize, end: usize) -> bool {
+        if !self.can_restructure() || start >= end || end >= self.project.segments.len() { return false; }
+        if self.project.segments[start..=end].iter().any(|p| p.muted) { return false; }
+        let parts: Vec<Segment> = self.project.segments.drain(start..=end).collect();
+        let channels = self.project.channels;
+        let mut merged = Segment::new(parts.iter().flat_map(|p| p.heard(channels).into_owned()).collect());
+        merged.name = parts[0].name.clone();
+        merged.recorded_at = parts[0].recorded_at;
+        merged.history = parts[0].history.clone();
+        merged.log(format!("merged segments {}-{}", start + 1, end + 1));
+        self.project.segments.insert(start, merged);
+        true
+    }
+
+    // find and smooth over clicks/pops (see dsp::declick), returns how many
+    // were repaired, 0 also for a bad index
+    pub fn declick(&mut self, index: usize, sensitivity: f32) -> usize {
+        let Some(seg) = self.project.segments.get_mut(index) else { return 0; };
+        let repaired = dsp::declick(&mut seg.samples, sensitivity);
+        if repaired > 0 {
+            seg.log(format!("declick x{} ({} repaired)", sensitivity, repaired));
+        }
+        repaired
+    }
+
+    // bring every segment to the same RMS level so takes sound equally loud
+    // regardless of their peaks, measured as heard (gain included). a segment
+    // whose gain would push its peak over 0dBFS only gets as much as its peak
+    // allows (it ends up quieter than the target instead of clipping). silent
+    // segments are left alone.
+    // returns how many segments were changed
+    pub fn match_loudness(&mut self, target_rms_db: f32) -> usize {
+        let target = dsp::db_to_linear(target_rms_db);
+        let gains: Vec<Option<f32>> = self.project.segments.iter()
+            .map(|seg| {
+                let rms = dsp::rms(&seg.samples) * seg.gain;
+                if rms < SILENT_PEAK { return None; }
+                let gain = (target / rms).min(1.0 / (seg.peak() * seg.gain));
+                ((gain - 1.0).abs() > 1e-4).then_some(gain)
+            })
+            .collect();
+        let mut changed = 0;
+        for (i, gain) in gains.into_iter().enumerate() {
+            if let Some(g) = gain && self.apply_effect(i, EffectSpec::Gain(g)) {
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    // scale segment `index` so its loudest sample is heard at NORMALIZE_PEAK,
+    // its gain included (like normalize_project), so it can't clip on export.
+    // false for a missing or silent segment (nothing to scale up)
+    pub fn normalize_segment(&mut self, index: usize) -> bool {
+        let Some(seg) = self.project.segments.get(index) else { return false; };
+        let peak = seg.peak() * seg.gain;
+        if peak < SILENT_PEAK { return false; }
+        self.apply_effect(index, EffectSpec::Gain(NORMALIZE_PEAK / peak))
+    }
+
+    // every segment to the same peak, each with its own factor.
+    // returns how many were normalized
+    pub fn normalize_all(&mut self) -> usize {
+        (0..self.project.segments.len()).filter(|&i| self.normalize_segment(i)).count()
+    }
+
+    // one shared factor for every segment so the loudest peak in the project
+    // (as heard, gain included) lands on `target`. unlike normalize_all the
+    // takes keep their levels relative to each other. false when the whole
+    // project is silent
+    pub fn normalize_project(&mut self, target: f32) -> bool {
+        let peak = self.project.segments.iter().map(|s| s.peak() * s.gain).fold(0.0_f32, f32::max);
+        if peak < SILENT_PEAK || !target.is_finite() || target <= 0.0 { return false; }
+        let factor = target / peak;
+        for i in 0..self.project.segments.len() {
+            self.apply_effect(i, EffectSpec::Gain(factor));
+        }
+        true
+    }
+
+    // subtract each channel's mean so the waveform is centred on zero again
+    // (a constant bias wastes headroom and clicks at cuts). false for a
+    // missing or empty segment
+    pub fn remove_dc_offset(&mut self, index: usize) -> bool {
+        let ch = self.project.channels.max(1) as usize;
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        let frames = seg.samples.len() / ch;
+        if frames == 0 { return false; }
+        for c in 0..ch {
+            let mean = seg.samples.iter().skip(c).step_by(ch).map(|&s| s as f64).sum::<f64>() / frames as f64;
+            seg.samples.iter_mut().skip(c).step_by(ch).for_each(|s| *s -= mean as f32);
+        }
+        seg.log("dc offset removed".into());
+        true
+    }
+
+    // make the take `factor` times as long (2.0 = twice, 0.5 = half) at the
+    // same pitch, see dsp::time_stretch for the algorithm and its artifacts.
+    // false (and untouched) for a take shorter than one 40ms analysis frame
+    pub fn time_stretch(&mut self, index: usize, factor: f32) -> bool {
+        if !STRETCH_RANGE.contains(&factor) { return false; }
+        let (rate, channels) = (self.project.sample_rate, self.project.channels);
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        let Some(stretched) = dsp::time_stretch(&seg.samples, channels, rate, factor) else { return false; };
+        seg.samples = stretched;
+        seg.log(format!("time-stretched x{:.2}", factor));
+        true
+    }
+
+    // raise (or lower, negative) the take by `semitones` at the same length,
+    // see dsp::pitch_shift for how and what it costs in quality. `dup` first
+    // to keep the original for layering
+    pub fn pitch_shift(&mut self, index: usize, semitones: f32) -> bool {
+        if !PITCH_RANGE.contains(&semitones) { return false; }
+        let (rate, channels) = (self.project.sample_rate, self.project.channels);
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        let Some(shifted) = dsp::pitch_shift(&seg.samples, channels, rate, semitones) else { return false; };
+        seg.samples = shifted;
+        seg.log(format!("pitch-shifted {:+} semitones", semitones));
+        true
+    }
+
+    // returns how many segments were corrected
+    pub fn remove_dc_all(&mut self) -> usize {
+        (0..self.project.segments.len()).filter(|&i| self.remove_dc_offset(i)).count()
+    }
+
+    // shared path for every effect above
+    fn apply_effect(&mut self, index: usize, effect: EffectSpec) -> bool {
+        let (sample_rate, channels, block) = (self.project.sample_rate, self.project.channels, self.settings.block_size);
+        let Some(seg) = self.project.segments.get_mut(index) else { return false; };
+        effect.apply(&mut seg.samples, sample_rate, channels, block);
+        seg.log(effect.describe());
+        true
+    }
+
+    // non-destructive audition: apply `effect` to a copy of segment `index`
+    // and insert the copy after `insert_after`, the original is untouched
+    pub fn render_with_effect(&mut self, index: usize, effect: EffectSpec, insert_after: usize) -> bool {
+        if !self.can_restructure() { return false; }
+        let len = self.project.segments.len();
+        if index >= len || insert_after >= len { return false; }
+        let (sample_rate, channels, block) = (self.project.sample_rate, self.project.channels, self.settings.block_size);
+        let mut copy = self.project.segments[index].clone();
+        effect.apply(&mut copy.samples, sample_rate, channels, block);
+        copy.log(format!("rendered from #{} with {}", index + 1, effect.describe()));
+        self.project.segments.insert(insert_after + 1, copy);
+        true
+    }
+
+    pub fn trim_start(&mut self, segment_index: Option<usize>, seconds: f32) -> bool {
+        let sample_rate = self.project.sample_rate;
+        // whole frames, so a stereo take doesn't swap left and right
+        let samples_to_trim = (seconds * sample_rate as f32) as usize * self.project.channels.max(1) as usize;
+
+        if samples_to_trim == 0 { return false; }
+
+        match segment_index {
+            None => { // current segment
+                self.push_current_backup(); // save backup
+                if let Some(ref mut seg) = self.current {
+                    if samples_to_trim >= seg.samples.len() {
+                        seg.samples.clear(); // Trim all if requested >= length
+                    } else {
+                        seg.samples.drain(0..samples_to_trim);
                     }
+                    seg.log(format!("trim start {:.2}s", seconds));
+                    return true;
+                }
+            }
+            Some(idx) => {
+                self.save_state(); // save backup (not current segment so save state)
+                if idx < self.project.segments.len() {
+                    let seg = &mut self.project.segments[idx];
+                    if samples_to_trim >= seg.samples.len() {
+                        seg.samples.clear();
+                    } else {
+                        seg.samples.drain(0..samples_to_trim);
+                    }
+                    seg.log(format!("trim start {:.2}s", seconds));
                     return true;
                 }
             }
@@ -361,7 +1708,8 @@ impl RecorderState { // master struct
 
     pub fn trim_end(&mut self, segment_index: Option<usize>, seconds: f32) -> bool {
         let sample_rate = self.project.sample_rate;
-        let samples_to_trim = (seconds * sample_rate as f32) as usize;
+        // whole frames, so a stereo take doesn't swap left and right
+        let samples_to_trim = (seconds * sample_rate as f32) as usize * self.project.channels.max(1) as usize;
 
         if samples_to_trim == 0 { return false; }
 
@@ -376,6 +1724,7 @@ impl RecorderState { // master struct
                         let new_len = seg.samples.len() - samples_to_trim;
                         seg.samples.truncate(new_len);
                     }
+                    seg.log(format!("trim end {:.2}s", seconds));
                     return true;
                 }
             }
@@ -390,6 +1739,7 @@ impl RecorderState { // master struct
                         let new_len = seg.samples.len() - samples_to_trim;
                         seg.samples.truncate(new_len);
                     }
+                    seg.log(format!("trim end {:.2}s", seconds));
                     return true;
                 }
             }
@@ -399,11 +1749,83 @@ impl RecorderState { // master struct
 
     // *** Helpers ***
 
-    pub fn total_duration(&self) -> String {
-        let total_secs: f32 = self.project.segments
+    // the pending take back-to-back `repeats` times (at least once), ready
+    // to hand to audio_output once the lock is released. always targets
+    // `current`, never a committed segment, so it's only Some during review
+    pub fn review_playback(&self, repeats: u32) -> Option<Segment> {
+        if self.state != AppState::Reviewing { return None; }
+        let seg = self.current.as_ref()?;
+        let repeats = repeats.clamp(1, MAX_REVIEW_REPEATS) as usize;
+        Some(Segment::new(seg.samples.repeat(repeats)))
+    }
+
+    // what `p <n>` plays: segment `index`, with settings.context_ms of the
+    // previous segment's tail before it and the next one's head after it
+    // so it's heard in place. the first/last segment just gets less roll
+    pub fn segment_playback(&self, index: usize) -> Option<Segment> {
+        let segs = &self.project.segments;
+        let seg = segs.get(index)?;
+        let channels = self.project.channels.max(1) as usize;
+        let roll = ms_to_samples(self.settings.context_ms, self.project.sample_rate) * channels;
+        let (pre, post) = context_ranges(
+            index.checked_sub(1).map(|i| segs[i].samples.len()),
+            segs.get(index + 1).map(|s| s.samples.len()),
+            roll,
+        );
+        // each part at its own gain and pan, as it would sound in the export
+        let mut samples = Vec::with_capacity(pre.len() + seg.samples.len() + post.len());
+        let ch = self.project.channels;
+        if index > 0 { samples.extend_from_slice(&segs[index - 1].heard(ch)[pre]); }
+        samples.extend_from_slice(&seg.heard(ch));
+        if let Some(next) = segs.get(index + 1) { samples.extend_from_slice(&next.heard(ch)[post]); }
+        Some(Segment::new(samples))
+    }
+
+    // calibration: a 1s reference tone at `level_db` dBFS, a short pause,
+    // then segment `index`, so the take's loudness can be judged against it
+    pub fn reference_playback(&self, level_db: f32, index: usize) -> Option<Segment> {
+        let seg = self.project.segments.get(index)?;
+        let rate = self.project.sample_rate;
+        let ch = self.project.channels.max(1) as usize;
+        let tone = dsp::sine_tone(dsp::REFERENCE_TONE_HZ, level_db, 1.0, rate);
+        let mut samples: Vec<f32> = tone.iter().flat_map(|&s| std::iter::repeat_n(s, ch)).collect();
+        samples.extend(std::iter::repeat_n(0.0, ms_to_samples(REFERENCE_GAP_MS, rate) * ch));
+        samples.extend_from_slice(&seg.heard(self.project.channels));
+        Some(Segment::new(samples))
+    }
+
+    // the part of the take under review the window keeps, for hearing a
+    // trim before committing to it
+    pub fn trim_window_preview(&self, window: TrimWindow) -> Option<Segment> {
+        if self.state != AppState::Reviewing { return None; }
+        let seg = self.current.as_ref()?;
+        let ch = self.project.channels.max(1) as usize;
+        seg.samples.get(window.start * ch..window.end * ch).map(|s| Segment::new(s.to_vec()))
+    }
+
+    // cut the take under review down to the window, undoable like `trim`
+    pub fn apply_trim_window(&mut self, window: TrimWindow) -> bool {
+        if self.state != AppState::Reviewing || window.is_full() { return false; }
+        let Some(seg) = &self.current else { return false; };
+        let ch = self.project.channels.max(1) as usize;
+        if window.end * ch > seg.samples.len() { return false; }
+        self.push_current_backup();
+        let seg = self.current.as_mut().unwrap();
+        seg.samples.truncate(window.end * ch);
+        seg.samples.drain(..window.start * ch);
+        seg.log(format!("trim window {}..{}", window.start, window.end));
+        true
+    }
+
+    pub fn total_seconds(&self) -> f32 {
+        self.project.segments
             .iter()
-            .map(|seg| seg.duration_seconds(self.project.sample_rate))
-            .sum();
+            .map(|seg| seg.duration_seconds(self.project.sample_rate, self.project.channels))
+            .sum()
+    }
+
+    pub fn total_duration(&self) -> String {
+        let total_secs = self.total_seconds();
         let mins = (total_secs / 60.0) as u32;
         let secs = (total_secs % 60.0) as u32;
         format!("{}m:{:02}s", mins, secs)
@@ -428,6 +1850,65 @@ impl RecorderState { // master struct
 // unit test the entire workflow without needing audio
 
 
+// state dispatch, called by main, no audio I/O, no threads
+// Note: audio_output commands(PlaySegment, PlayAll(_)) are handled in main
+// because they need hold Arc<Mutex<RecorderState>> + threads and file I/O
+pub fn dispatch_command(rec: &mut RecorderState, cmd: Command) {
+    // refused outright, a snapshot now would also drop the take's redo
+    if cmd.restructures() && !rec.can_restructure() { return; }
+    let changed = match cmd {
+        Command::StartRecording       => { rec.start_recording(); false }
+        Command::StopRecording        => { rec.stop_recording(); false }
+        Command::PauseRecording       => { rec.pause_recording(); false }
+        Command::ResumeRecording      => { rec.resume_recording(); false }
+        Command::Approve              => !rec.approve().discarded, // a discarded take is rejected, undo-able
+        Command::Reject               => { rec.reject(); false } // kept aside, undo brings it back
+        Command::RetryCurrentTake     => { rec.retry_current_take(); false } // saved in prev_current
+        // no snapshot until the take is approved, so one undo reverses the whole retake/insert
+        Command::RetrySegment(i)      => { rec.retry_segment(i); false }
+        Command::InsertAfter(i)       => { rec.insert_segment(i); false }
+        Command::InsertAt(secs)       => { rec.prepare_insert_at_time(secs); false } // the split lands with the take
+        // the edits say whether they changed anything and only then get a
+        // snapshot: a refused one would add an empty undo step and drop the
+        // redo branch and the rejected take
+        Command::InsertSilence(i, secs) => rec.insert_silence(i, secs),
+        Command::DeleteSegment(i)     => rec.delete_segment(i),
+        Command::ClearProject         => rec.clear_project(),
+        Command::TimeStretch(i, f)    => rec.time_stretch(i, f),
+        Command::PitchShift(i, st)    => rec.pitch_shift(i, st),
+        Command::NameSegment(i, name) => rec.name_segment(i, name),
+        Command::SetMetadata(f, text) => rec.set_metadata(&f, text),
+        Command::SetGain(i, gain)     => rec.set_gain(i, gain),
+        Command::SetPan(i, pan)       => rec.set_pan(i, pan),
+        Command::MoveSegment(from, to) => rec.move_segment(from, to),
+        Command::DuplicateSegment(i)  => rec.duplicate_segment(i),
+        Command::ReverseSegment(i)    => rec.reverse_segment(i),
+        Command::SplitSegment(i, secs) => rec.split_segment(i, secs),
+        Command::Paste(i, secs)       => rec.paste_clipboard(i, secs),
+        Command::MergeSegments(a, b)  => rec.merge_segments(a, b),
+        Command::TrimStart(idx, secs) => { rec.trim_start(idx, secs); false } // saved in prev_current
+        Command::TrimEnd(idx, secs)   => { rec.trim_end(idx, secs); false }
+        Command::TrimSilence(i, t, p) => rec.trim_silence(i, t, p),
+        Command::Filter(i, k, f, q)   => rec.filter_segment(i, k, f, q),
+        Command::HighPass(i, hz)      => rec.high_pass(i, hz),
+        Command::Reverb(i, r, w)      => rec.reverb(i, r, w),
+        Command::Delay(i, t, f, w)    => rec.delay(i, t, f, w),
+        Command::RenderEffect(i, e, a) => rec.render_with_effect(i, e, a),
+        Command::MatchLoudness(db)    => rec.match_loudness(db) > 0,
+        Command::Normalize(Some(i))   => rec.normalize_segment(i),
+        Command::Normalize(None)      => rec.normalize_all() > 0,
+        Command::NormalizeProject(t)  => rec.normalize_project(t),
+        Command::RemoveDc(Some(i))    => rec.remove_dc_offset(i),
+        Command::RemoveDc(None)       => rec.remove_dc_all() > 0,
+        Command::StopPlayback         => { rec.request_stop_playback(); false }
+        Command::Undo                 => { rec.undo(); false }
+        Command::Redo                 => { rec.redo(); false }
+        _ => false,
+    };
+    if changed { rec.save_state(); }
+}
+
+
 // -------------------------
 // Tests
 // -------------------------
@@ -441,6 +1922,528 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_insert_at_time_splits_mid_segment() {
+        let mut recorder = RecorderState::new(10, 1);
+        recorder.project.segments = vec![Segment::new(vec![1.0; 20]), Segment::new(vec![2.0; 30])];
+
+        // 3.5s is 15 samples into the second segment (which starts at 2s)
+        assert!(recorder.prepare_insert_at_time(3.5));
+        assert!(recorder.state == AppState::Recording);
+        simulate_recording(&mut recorder, vec![9.0; 5]);
+        recorder.stop_recording();
+        recorder.approve();
+
+        let lens: Vec<usize> = recorder.project.segments.iter().map(|s| s.samples.len()).collect();
+        assert_eq!(lens, vec![20, 15, 5, 15]);
+        assert_eq!(recorder.project.segments[2].samples[0], 9.0);
+
+        // on a boundary nothing is split
+        assert!(recorder.prepare_insert_at_time(2.0));
+        assert_eq!(recorder.project.segments.len(), 4);
+        assert_eq!(recorder.project.editing_index, Some(1));
+        recorder.reject();
+
+        // the very end appends, past it is refused
+        assert!(recorder.prepare_insert_at_time(5.5));
+        assert_eq!(recorder.project.editing_index, Some(4));
+        recorder.reject();
+        assert!(!recorder.prepare_insert_at_time(6.0));
+    }
+
+    #[test]
+    fn test_context_playback_ranges() {
+        assert_eq!(context_ranges(Some(1000), Some(1000), 100), (900..1000, 0..100));
+        // short neighbours give what they have, missing ones give nothing
+        assert_eq!(context_ranges(Some(40), None, 100), (0..40, 0..0));
+        assert_eq!(context_ranges(None, Some(60), 100), (0..0, 0..60));
+        assert_eq!(context_ranges(Some(1000), Some(1000), 0), (1000..1000, 0..0));
+
+        let mut rec = RecorderState::new(1000, 1);
+        rec.project.segments = vec![
+            Segment::new(vec![1.0; 500]), Segment::new(vec![2.0; 300]), Segment::new(vec![3.0; 500]),
+        ];
+        assert_eq!(rec.segment_playback(1).unwrap().samples.len(), 300); // off by default
+        rec.settings.context_ms = 100.0;
+        let played = rec.segment_playback(1).unwrap().samples;
+        assert_eq!(played.len(), 100 + 300 + 100);
+        assert_eq!((played[99], played[100], played[399], played[400]), (1.0, 2.0, 2.0, 3.0));
+        assert_eq!(rec.segment_playback(0).unwrap().samples.len(), 500 + 100);
+    }
+
+    #[test]
+    fn test_reference_playback_layout() {
+        let mut rec = RecorderState::new(1000, 1);
+        rec.project.segments.push(Segment::new(vec![0.2; 50]));
+        let seg = rec.reference_playback(-12.0, 0).unwrap();
+        // 1s tone + 300ms gap + the segment
+        assert_eq!(seg.samples.len(), 1000 + 300 + 50);
+        assert!(seg.samples[1000..1300].iter().all(|&s| s == 0.0));
+        assert_eq!(seg.samples[1300], 0.2);
+        assert!(rec.reference_playback(-12.0, 1).is_none());
+    }
+
+    #[test]
+    fn test_trim_window_adjust_and_clamp() {
+        let mut w = TrimWindow::new(100);
+        assert!(w.is_full());
+        w.nudge_start(30);
+        w.nudge_end(-20);
+        assert_eq!((w.start, w.end), (30, 80));
+
+        // can't go outside the take or cross over
+        w.nudge_start(-500);
+        assert_eq!(w.start, 0);
+        w.set_end(1000);
+        assert_eq!(w.end, 100);
+        w.set_start(100);
+        assert_eq!(w.start, 99);
+        w.set_end(10);
+        assert_eq!(w.end, 100); // stays one past the in point
+        w.set_start(40);
+        w.set_end(-5);
+        assert_eq!((w.start, w.end), (40, 41));
+    }
+
+    #[test]
+    fn test_apply_trim_window_to_review() {
+        let mut recorder = RecorderState::new(1000, 1);
+        recorder.start_recording();
+        simulate_recording(&mut recorder, (0..100).map(|i| i as f32).collect());
+        recorder.stop_recording();
+
+        let mut w = TrimWindow::new(100);
+        w.set_start(10);
+        w.set_end(60);
+        assert_eq!(recorder.trim_window_preview(w).unwrap().samples.len(), 50);
+        assert_eq!(recorder.current.as_ref().unwrap().samples.len(), 100); // preview doesn't cut
+
+        assert!(recorder.apply_trim_window(w));
+        let seg = recorder.current.as_ref().unwrap();
+        assert_eq!(seg.samples.len(), 50);
+        assert_eq!((seg.samples[0], seg.samples[49]), (10.0, 59.0));
+        assert!(recorder.undo());
+        assert_eq!(recorder.current.as_ref().unwrap().samples.len(), 100);
+    }
+
+    #[test]
+    fn test_split_propagates_metadata() {
+        let mut rec = RecorderState::new(100, 1);
+        let mut seg = Segment::new(vec![0.5; 300]);
+        seg.name = Some("A".into());
+        rec.project.segments.push(seg);
+        rec.project.segments.push(Segment::new(vec![0.1; 50]));
+        rec.apply_effect(0, EffectSpec::Gain(0.5));
+
+        assert!(!rec.split_segment(0, 3.0)); // at the very end, nothing to split
+        assert!(rec.split_segment(0, 1.0));
+        let segs = &rec.project.segments;
+        assert_eq!(segs.len(), 3);
+        assert_eq!((segs[0].samples.len(), segs[1].samples.len()), (100, 200));
+        assert_eq!(segs[0].name.as_deref(), Some("A.1"));
+        assert_eq!(segs[1].name.as_deref(), Some("A.2"));
+        // the gain lives in the samples and in both halves' history
+        assert_eq!(segs[1].samples[0], 0.25);
+        for (part, seg) in segs[..2].iter().enumerate() {
+            assert_eq!(seg.history[0], "gain x0.5");
+            assert_eq!(seg.history[1], format!("split at sample 100 (part {}/2)", part + 1));
+        }
+
+        // unnamed takes stay unnamed
+        assert!(rec.split_segment(2, 0.2));
+        assert!(rec.project.segments[2].name.is_none() && rec.project.segments[3].name.is_none());
+    }
+
+    #[test]
+    fn test_split_command_keeps_frames_whole() {
+        let mut rec = RecorderState::new(10, 2);
+        rec.project.segments.push(Segment::new((0..40).map(|i| i as f32).collect())); // 2s stereo
+        rec.save_state();
+
+        dispatch_command(&mut rec, Command::SplitSegment(0, 2.5)); // past the end
+        dispatch_command(&mut rec, Command::SplitSegment(0, 0.0)); // at the start
+        assert_eq!(rec.project.segments.len(), 1);
+
+        dispatch_command(&mut rec, Command::SplitSegment(0, 0.5));
+        let segs = &rec.project.segments;
+        assert_eq!((segs[0].samples.len(), segs[1].samples.len()), (10, 30));
+        assert_eq!(segs[1].samples[0], 10.0); // starts on a left-channel sample
+
+        assert!(rec.undo());
+        assert_eq!(rec.project.segments.len(), 1);
+    }
+
+    #[test]
+    fn test_meter_decays_after_a_peak() {
+        let meter = LevelMeter::default();
+        let at = Duration::from_millis;
+        meter.store_at(&[0.8], at(0));
+        assert_eq!(meter.levels_at(at(0)), vec![0.8]);
+
+        // quieter buffers don't pull the bar down faster than the decay
+        meter.store_at(&[0.1], at(250));
+        let held = meter.levels_at(at(250))[0];
+        assert!((held - 0.8 * dsp::db_to_linear(-6.0)).abs() < 1e-3);
+
+        // nothing stored for a while (stream paused): it still falls
+        assert!(meter.levels_at(at(2250))[0] < 0.01);
+
+        // a louder peak shows straight away
+        meter.store_at(&[0.9], at(2300));
+        assert_eq!(meter.levels_at(at(2300)), vec![0.9]);
+    }
+
+    #[test]
+    fn test_track_silence_stops_after_speech() {
+        let mut rec = RecorderState::new(100, 1);
+        rec.start_recording();
+        assert!(!rec.track_silence(&[0.0; 500])); // off by default
+
+        rec.settings.auto_stop = Some(0.5); // 50 samples at 100Hz
+        assert!(!rec.track_silence(&[0.0; 500])); // no speech yet, waits
+        rec.current.as_mut().unwrap().samples.extend([0.0; 500]);
+        assert!(!rec.track_silence(&[0.5, 0.0, 0.0]));
+        assert!(!rec.track_silence(&[0.0; 40]));
+        assert!(!rec.track_silence(&[0.0, 0.3])); // a word resets the run
+        assert!(!rec.track_silence(&[0.0; 49]));
+        assert!(rec.track_silence(&[0.0]));
+
+        // the next take starts from zero
+        rec.stop_recording();
+        rec.retry_current_take();
+        assert!(!rec.track_silence(&[0.0; 100]));
+    }
+
+    #[test]
+    fn test_stereo_edits_keep_frames_whole() {
+        // L = positive, R = negative, so a half-frame shift would show
+        let mut rec = RecorderState::new(10, 2);
+        let frames = |vals: &[f32]| -> Vec<f32> { vals.iter().flat_map(|&v| [v, -v]).collect() };
+        rec.project.segments.push(Segment::new(frames(&[0.0, 0.0, 0.0, 0.5, 0.6, 0.0, 0.0, 0.0])));
+
+        assert!(rec.trim_silence(0, 0.01, 100.0)); // 1 frame of pad
+        assert_eq!(rec.project.segments[0].samples, frames(&[0.0, 0.5, 0.6, 0.0]));
+        assert_eq!(rec.project.segments[0].duration_seconds(10, 2), 0.4);
+
+        assert!(rec.trim_start(Some(0), 0.1));
+        assert_eq!(rec.project.segments[0].samples, frames(&[0.5, 0.6, 0.0]));
+
+        // a delay runs per channel, the echo of L stays in L
+        rec.project.segments[0] = Segment::new(frames(&[1.0, 0.0, 0.0]));
+        rec.delay(0, 100.0, 0.0, 1.0);
+        let seg = &rec.project.segments[0].samples;
+        assert_eq!(seg.len() % 2, 0);
+        assert!(seg[2] > 0.5 && seg[3] < -0.5); // frame 1: L echo positive, R echo negative
+    }
+
+    #[test]
+    fn test_merge_segments() {
+        let mut rec = RecorderState::new(10, 1);
+        rec.project.segments = vec![
+            Segment::new(vec![1.0, 2.0]), Segment::new(vec![3.0]), Segment::new(vec![4.0]),
+        ];
+        rec.project.segments[0].name = Some("intro".into());
+        assert!(!rec.merge_segments(1, 1)); // needs two
+        assert!(!rec.merge_segments(1, 3)); // past the end
+        assert!(!rec.merge_segments(1, 0));
+
+        assert!(rec.merge_segments(0, 1));
+        let segs = &rec.project.segments;
+        assert_eq!(segs.len(), 2);
+        assert_eq!(segs[0].samples, vec![1.0, 2.0, 3.0]);
+        assert_eq!(segs[0].name.as_deref(), Some("intro"));
+        assert_eq!(segs[1].samples, vec![4.0]);
+
+        // gain is baked in so the merged take sounds the same
+        rec.project.segments[1].gain = 0.5;
+        assert!(rec.merge_segments(0, 1));
+        assert_eq!(rec.project.segments[0].samples, vec![1.0, 2.0, 3.0, 2.0]);
+        assert_eq!(rec.project.segments[0].gain, 1.0);
+
+        // a muted part would be heard again once merged
+        rec.project.segments.push(Segment::new(vec![5.0]));
+        rec.project.segments[1].muted = true;
+        assert!(!rec.merge_segments(0, 1));
+        assert_eq!(rec.get_segment_count(), 2);
+        assert!(rec.project.segments[1].muted);
+
+        // stereo: a panned part stays where it was panned
+        let mut rec = RecorderState::new(10, 2);
+        let mut left = Segment::new(vec![0.5, 0.5]);
+        left.pan = -1.0;
+        rec.project.segments = vec![Segment::new(vec![0.5, 0.5]), left];
+        let heard = rec.project.segments[1].heard(2).into_owned();
+        assert!(rec.merge_segments(0, 1));
+        assert_eq!(rec.project.segments[0].samples[2..], heard[..]);
+        assert_eq!(heard[1], 0.0); // nothing on the right
+    }
+
+    #[test]
+    fn test_match_loudness_equalises_rms() {
+        let mut rec = RecorderState::new(1000, 1);
+        let tone = |amp: f32| Segment::new((0..1000).map(|i| amp * (i as f32 * 0.3).sin()).collect());
+        let mut boosted = tone(0.05);
+        boosted.gain = 2.0;
+        rec.project.segments = vec![tone(0.05), tone(0.4), Segment::new(vec![0.0; 100]), boosted];
+
+        assert_eq!(rec.match_loudness(-20.0), 3); // silence is skipped
+        let heard_db = |i: usize| 20.0 * dsp::rms(&rec.project.segments[i].gained()).log10();
+        let (a, b, c) = (heard_db(0), heard_db(1), heard_db(3));
+        assert!((a + 20.0).abs() < 0.1 && (b + 20.0).abs() < 0.1 && (c + 20.0).abs() < 0.1, "{} {} {}", a, b, c);
+
+        // a target above what the peak allows stops at 0dBFS as heard
+        rec.match_loudness(0.0);
+        assert!(rec.project.segments.iter().all(|s| s.peak() * s.gain <= 1.0 + 1e-5));
+    }
+
+    #[test]
+    fn test_min_segment_length_on_approve() {
+        let mut recorder = RecorderState::new(1000, 1);
+        recorder.settings.min_segment_ms = Some(50.0);
+
+        // warn only: the 1-sample tap is flagged but kept
+        recorder.start_recording();
+        simulate_recording(&mut recorder, vec![0.3]);
+        recorder.stop_recording();
+        let result = recorder.approve();
+        assert!(result.too_short && !result.discarded);
+        assert_eq!(recorder.get_segment_count(), 1);
+
+        // reject: the tap never reaches the project
+        recorder.settings.reject_short = true;
+        recorder.start_recording();
+        simulate_recording(&mut recorder, vec![0.3]);
+        recorder.stop_recording();
+        let result = recorder.approve();
+        assert!(result.too_short && result.discarded);
+        assert_eq!(recorder.get_segment_count(), 1);
+        assert!(recorder.state == AppState::Idle);
+
+        // a normal take passes
+        recorder.start_recording();
+        simulate_recording(&mut recorder, vec![0.3; 200]);
+        recorder.stop_recording();
+        let result = recorder.approve();
+        assert!(!result.too_short && !result.discarded);
+        assert_eq!(recorder.get_segment_count(), 2);
+    }
+
+    #[test]
+    fn test_stop_recording_auto_trims_both_edges() {
+        let mut recorder = RecorderState::new(1000, 1);
+        recorder.settings.auto_trim = Some(0.01);
+        recorder.settings.trim_pad_ms = 10.0; // 10 samples at 1kHz
+
+        recorder.start_recording();
+        simulate_recording(&mut recorder, vec![0.0; 300]); // lead-in
+        simulate_recording(&mut recorder, vec![0.4; 500]);
+        simulate_recording(&mut recorder, vec![0.0; 200]); // reaching for the key
+        recorder.stop_recording();
+
+        let seg = recorder.current.as_ref().unwrap();
+        assert_eq!(seg.samples.len(), 10 + 500 + 10);
+        assert_eq!(seg.samples[10], 0.4);
+        assert_eq!(seg.samples[509], 0.4);
+
+        // the untrimmed take is one undo away
+        assert!(recorder.undo());
+        assert_eq!(recorder.current.as_ref().unwrap().samples.len(), 1000);
+    }
+
+    #[test]
+    fn test_undo_destructive_edits() {
+        let mut recorder = RecorderState::new(1000, 1);
+        for v in 1..=3 {
+            recorder.start_recording();
+            simulate_recording(&mut recorder, vec![v as f32]);
+            recorder.stop_recording();
+            dispatch_command(&mut recorder, Command::Approve);
+        }
+        let order = |r: &RecorderState| r.project.segments.iter().map(|s| s.samples[0]).collect::<Vec<_>>();
+
+        dispatch_command(&mut recorder, Command::DeleteSegment(0));
+        dispatch_command(&mut recorder, Command::MoveSegment(1, 0));
+        assert_eq!(order(&recorder), vec![3.0, 2.0]);
+
+        // a retake replacing #1, then a rejected take that changes nothing
+        dispatch_command(&mut recorder, Command::RetrySegment(0));
+        simulate_recording(&mut recorder, vec![9.0]);
+        dispatch_command(&mut recorder, Command::StopRecording);
+        dispatch_command(&mut recorder, Command::Approve);
+        dispatch_command(&mut recorder, Command::StartRecording);
+        dispatch_command(&mut recorder, Command::StopRecording);
+        dispatch_command(&mut recorder, Command::Reject);
+        assert_eq!(order(&recorder), vec![9.0, 2.0]);
+
+        assert!(recorder.undo());
+        assert_eq!(order(&recorder), vec![3.0, 2.0]); // retake undone
+        assert!(recorder.undo());
+        assert!(recorder.undo());
+        assert_eq!(order(&recorder), vec![1.0, 2.0, 3.0]); // move and delete undone
+        assert!(recorder.redo());
+        assert_eq!(order(&recorder), vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_undo_history_is_capped() {
+        let mut recorder = RecorderState::new(1000, 1);
+        recorder.project.segments.push(Segment::new(vec![0.0]));
+        for _ in 0..(MAX_UNDO_STEPS * 2) {
+            dispatch_command(&mut recorder, Command::MoveSegment(0, 0));
+        }
+        assert_eq!(recorder.history.len(), MAX_UNDO_STEPS);
+        let mut steps = 0;
+        while recorder.undo() { steps += 1; }
+        assert_eq!(steps, MAX_UNDO_STEPS - 1);
+    }
+
+    #[test]
+    fn test_mute_and_unmute() {
+        let mut rec = RecorderState::new(1000, 1);
+        rec.project.segments.push(Segment::new(vec![0.1; 4]));
+        assert!(rec.mute_segment(0));
+        assert!(rec.project.segments[0].muted);
+        assert!(rec.unmute_segment(0));
+        assert!(!rec.project.segments[0].muted);
+        assert!(!rec.mute_segment(1));
+    }
+
+    #[test]
+    fn test_pan_only_on_stereo() {
+        let mut mono = RecorderState::new(1000, 1);
+        mono.project.segments.push(Segment::new(vec![0.5, 0.5]));
+        assert!(!mono.set_pan(0, 1.0));
+
+        let mut rec = RecorderState::new(1000, 2);
+        rec.project.segments.push(Segment::new(vec![0.5, 0.5]));
+        assert!(!rec.set_pan(0, 1.5));
+        dispatch_command(&mut rec, Command::SetPan(0, 1.0));
+        assert_eq!(rec.project.segments[0].samples, vec![0.5, 0.5]); // samples untouched
+        let heard = rec.segment_playback(0).unwrap().samples;
+        assert!(heard[0].abs() < 1e-6);
+        assert!((heard[1] - 0.5 * std::f32::consts::SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_set_gain_is_non_destructive() {
+        let mut rec = RecorderState::new(1000, 1);
+        rec.project.segments.push(Segment::new(vec![0.1, -0.2]));
+        rec.save_state();
+        dispatch_command(&mut rec, Command::SetGain(0, 3.0));
+        assert_eq!(rec.project.segments[0].samples, vec![0.1, -0.2]);
+        assert_eq!(&rec.project.segments[0].gained()[..], &[0.1 * 3.0, -0.2 * 3.0]);
+        assert_eq!(rec.segment_playback(0).unwrap().samples, vec![0.1 * 3.0, -0.2 * 3.0]);
+
+        assert!(!rec.set_gain(0, -1.0));
+        assert!(!rec.set_gain(5, 1.0));
+        assert!(rec.undo());
+        assert_eq!(rec.project.segments[0].gain, 1.0);
+    }
+
+    #[test]
+    fn test_normalize_segment() {
+        let mut rec = RecorderState::new(1000, 1);
+        rec.project.segments.push(Segment::new(vec![0.1, -0.25, 0.05]));
+        rec.project.segments.push(Segment::new(vec![0.0; 3]));
+        rec.project.segments.push(Segment::new(vec![0.5, -0.1]));
+
+        assert!(rec.normalize_segment(0));
+        // peak 0.25 -> 0.99 is a factor of 3.96, applied to every sample
+        let seg = &rec.project.segments[0].samples;
+        assert!((seg[1] + 0.99).abs() < 1e-6);
+        assert!((seg[0] - 0.396).abs() < 1e-6);
+        assert!((seg[2] - 0.198).abs() < 1e-6);
+
+        assert!(!rec.normalize_segment(1)); // silent, untouched
+        assert_eq!(rec.project.segments[1].samples, vec![0.0; 3]);
+        assert!(!rec.normalize_segment(9));
+
+        assert_eq!(rec.normalize_all(), 2);
+        assert!((rec.project.segments[2].peak() - 0.99).abs() < 1e-6);
+
+        // the gain is part of what's heard, so the samples end up at 0.99 / gain
+        rec.project.segments[2].gain = 2.0;
+        assert!(rec.normalize_segment(2));
+        assert!((rec.project.segments[2].peak() * 2.0 - 0.99).abs() < 1e-6);
+        rec.project.segments[2].gain = 0.0;
+        assert!(!rec.normalize_segment(2)); // silenced by its gain
+    }
+
+    #[test]
+    fn test_move_segment_reorders() {
+        let mut recorder = RecorderState::new(1000, 1);
+        recorder.project.segments = (1..=3).map(|v| Segment::new(vec![v as f32])).collect();
+        let order = |r: &RecorderState| r.project.segments.iter().map(|s| s.samples[0]).collect::<Vec<_>>();
+
+        assert!(recorder.move_segment(0, 2));
+        assert_eq!(order(&recorder), vec![2.0, 3.0, 1.0]);
+        assert!(recorder.move_segment(2, 0));
+        assert_eq!(order(&recorder), vec![1.0, 2.0, 3.0]);
+        assert!(recorder.move_segment(0, 3)); // == len, to the end
+        assert_eq!(order(&recorder), vec![2.0, 3.0, 1.0]);
+        assert!(recorder.move_segment(1, 1)); // no-op
+        assert_eq!(order(&recorder), vec![2.0, 3.0, 1.0]);
+        assert!(!recorder.move_segment(3, 0));
+        assert!(!recorder.move_segment(0, 4));
+    }
+
+    #[test]
+    fn test_retry_keeps_segment_name() {
+        let mut recorder = RecorderState::new(1000, 1);
+        recorder.start_recording();
+        simulate_recording(&mut recorder, vec![0.1; 10]);
+        recorder.stop_recording();
+        recorder.approve();
+        assert!(recorder.name_segment(0, Some(" intro ".into())));
+        assert_eq!(recorder.project.segments[0].name.as_deref(), Some("intro"));
+
+        recorder.retry_segment(0);
+        simulate_recording(&mut recorder, vec![0.2; 20]);
+        recorder.stop_recording();
+        recorder.approve();
+        let seg = &recorder.project.segments[0];
+        assert_eq!((seg.samples.len(), seg.name.as_deref()), (20, Some("intro")));
+
+        assert!(recorder.name_segment(0, None));
+        assert!(recorder.project.segments[0].name.is_none());
+        assert!(!recorder.name_segment(5, Some("x".into())));
+    }
+
+    #[test]
+    fn test_content_hash_tracks_samples() {
+        let mut rec = RecorderState::new(1000, 1);
+        let take = Segment::new((0..500).map(|i| (i as f32 * 0.01).sin()).collect());
+        let mut copy = take.clone();
+        copy.name = Some("copy".into()); // metadata doesn't change the fingerprint
+        rec.project.segments = vec![take, Segment::new(vec![0.1; 10]), copy];
+
+        let hash = |rec: &RecorderState, i: usize| rec.project.segments[i].content_hash();
+        assert_eq!(hash(&rec, 0), hash(&rec, 2));
+        assert_ne!(hash(&rec, 0), hash(&rec, 1));
+        assert_eq!(rec.project.duplicate_groups(), vec![vec![0, 2]]);
+
+        rec.apply_effect(2, EffectSpec::Gain(0.9));
+        assert_ne!(hash(&rec, 0), hash(&rec, 2));
+        assert!(rec.project.duplicate_groups().is_empty());
+    }
+
+    #[test]
+    fn test_inter_segment_gaps() {
+        let speech = |lead: usize, talk: usize, trail: usize| {
+            let mut v = vec![0.0; lead];
+            v.extend(vec![0.5; talk]);
+            v.extend(vec![0.0; trail]);
+            Segment::new(v)
+        };
+        let mut rec = RecorderState::new(100, 1);
+        rec.project.segments = vec![speech(5, 50, 20), speech(10, 50, 30), speech(0, 50, 0)];
+        // 20 + 10 samples, then 30 + 0 samples at 100Hz
+        assert_eq!(rec.project.inter_segment_gaps(0.01), vec![0.3, 0.3]);
+
+        rec.project.segments.truncate(1);
+        assert!(rec.project.inter_segment_gaps(0.01).is_empty());
+    }
+
     #[test]
     fn test_full_workflow() {
         let mut rec = RecorderState::new(48000, 1);
@@ -526,25 +2529,721 @@ mod tests {
         dispatch_command(&mut rec, Command::DeleteSegment(0));
         assert_eq!(rec.get_segment_count(), 0);
     }
-}
 
-// state dispatch, called by main, no audio I/O, no threads
-// Note: audio_output commands(PlaySegment, PlayAll) are handled in main
-// because they need hold Arc<Mutex<RecorderState>> + threads and file I/O
-pub fn dispatch_command(rec: &mut RecorderState, cmd: Command) {
-    match cmd {
-        Command::StartRecording       => rec.start_recording(),
-        Command::StopRecording        => rec.stop_recording(),
-        Command::Approve              => { rec.approve(); rec.save_state(); }
-        Command::Reject               => rec.reject(),
-        Command::RetryCurrentTake     => rec.retry_current_take(), // saved in prev_current
-        Command::RetrySegment(i)      => { rec.retry_segment(i); rec.save_state(); }
-        Command::InsertAfter(i)       => { rec.insert_segment(i); rec.save_state(); }
-        Command::DeleteSegment(i)     => { rec.delete_segment(i); rec.save_state(); }
-        Command::TrimStart(idx, secs) => { rec.trim_start(idx, secs); } // saved in prev_current
-        Command::TrimEnd(idx, secs)   => { rec.trim_end(idx, secs); }
-        Command::Undo                 => { rec.undo(); }
-        Command::Redo                 => { rec.redo(); }
-        _ => {}
+    #[test]
+    fn test_approve_flags_silent_take() {
+        let mut rec = RecorderState::new(48000, 1);
+
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.0; 480]);
+        rec.stop_recording();
+        assert!(rec.approve().silent);
+
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.0, 0.2, -0.3, 0.1]);
+        rec.stop_recording();
+        assert!(!rec.approve().silent);
+        assert_eq!(rec.get_segment_count(), 2); // silent takes are still kept
+    }
+
+    #[test]
+    fn test_approve_counts_clipped_samples() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.5, 1.0, -1.2, 0.99, 1.5]);
+        rec.stop_recording();
+        assert_eq!(rec.approve().clipped, 3);
+        // flagged only, the audio is committed as recorded
+        assert_eq!(rec.project.segments[0].samples, vec![0.5, 1.0, -1.2, 0.99, 1.5]);
+        assert_eq!(rec.project.segments[0].clipped_samples(), 3);
+    }
+
+    #[test]
+    fn test_review_playback_targets_current() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![1.0]);
+        rec.stop_recording();
+        rec.approve();
+
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.5, 0.25]);
+        assert!(rec.review_playback(1).is_none()); // still recording
+        rec.stop_recording();
+
+        let seg = rec.review_playback(3).unwrap();
+        assert_eq!(seg.samples, vec![0.5, 0.25, 0.5, 0.25, 0.5, 0.25]);
+        assert_eq!(rec.review_playback(0).unwrap().samples, vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_edit_history_is_ordered() {
+        let mut rec = RecorderState::new(10, 1);
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.1; 40]);
+        rec.stop_recording();
+        rec.approve();
+
+        rec.trim_start(Some(0), 0.5);
+        rec.trim_end(Some(0), 1.0);
+        assert_eq!(rec.project.segments[0].history, vec!["trim start 0.50s", "trim end 1.00s"]);
+        assert_eq!(rec.project.segments[0].samples.len(), 25);
+    }
+
+    #[test]
+    fn test_auto_export_threshold_resets_project() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.settings.auto_export_segments = Some(2);
+
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.5]);
+        rec.stop_recording();
+        assert!(!rec.approve().session_due);
+
+        rec.start_recording();
+        simulate_recording(&mut rec, vec![0.6]);
+        rec.stop_recording();
+        rec.save_path = Some("session.bin".into());
+        assert!(rec.approve().session_due);
+        assert_eq!(rec.get_segment_count(), 2); // nothing is dropped until the export worked
+
+        rec.start_new_session();
+        assert_eq!(rec.get_segment_count(), 0);
+        assert_eq!(rec.project.sample_rate, 48000);
+        assert!(rec.save_path.is_none());
+        assert_eq!(rec.history.len(), 1);
+        assert!(!rec.undo());
+    }
+
+    #[test]
+    fn test_trim_silence_keeps_pad() {
+        let mut rec = RecorderState::new(44100, 1);
+        let mut take = vec![0.0; 44100];      // 1s of dead air
+        take.extend(vec![0.5; 22050]);         // 0.5s of "speech"
+        take.extend(vec![0.0; 44100]);         // 1s tail
+        rec.start_recording();
+        simulate_recording(&mut rec, take);
+        rec.stop_recording();
+        rec.approve();
+
+        assert!(rec.trim_silence(0, 0.01, 100.0));
+        let samples = &rec.project.segments[0].samples;
+        let lead = samples.iter().position(|s| *s != 0.0).unwrap();
+        assert_eq!(lead, 4410);
+        assert_eq!(samples.len(), 4410 + 22050 + 4410);
+
+        // all-quiet segments are left alone rather than zeroed
+        rec.project.segments.push(Segment::new(vec![0.001; 100]));
+        assert!(!rec.trim_silence(1, 0.01, 0.0));
+        assert_eq!(rec.project.segments[1].samples.len(), 100);
+
+        // a pad past the take keeps all of it instead of overflowing
+        let mut seg = Segment::new(vec![0.0, 0.5, 0.5, 0.0]);
+        assert!(seg.trim_silence(0.01, usize::MAX, 2));
+        assert_eq!(seg.samples.len(), 4);
+        assert!(!rec.trim_silence(0, 0.01, f32::INFINITY));
+    }
+
+    #[test]
+    fn test_high_pass_attenuates_dc() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.project.segments.push(Segment::new(vec![0.5; 8000])); // 1s of pure DC
+        assert!(rec.high_pass(0, 60.0));
+        let tail = &rec.project.segments[0].samples[4000..];
+        assert!(tail.iter().all(|s| s.abs() < 0.001));
+        assert!(!rec.high_pass(0, 0.0));
+        assert!(!rec.high_pass(0, 4000.0)); // Nyquist
+        assert!(!rec.high_pass(3, 60.0));
+    }
+
+    #[test]
+    fn test_filter_segment_applies_biquad() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.project.segments.push(Segment::new(vec![0.5; 4800])); // pure DC
+        assert!(rec.filter_segment(0, FilterKind::HighPass, 80.0, 0.707));
+        assert!(rec.project.segments[0].samples.last().unwrap().abs() < 0.01);
+        assert!(!rec.filter_segment(5, FilterKind::LowPass, 80.0, 0.707));
+    }
+
+    #[test]
+    fn test_playback_stop_state_machine() {
+        let mut rec = RecorderState::new(48000, 1);
+        assert!(!rec.request_stop_playback()); // nothing playing
+
+        let stop = rec.begin_playback();
+        assert!(rec.playback_state == PlaybackState::Playing);
+        assert!(!stop.load(Ordering::Relaxed));
+
+        assert!(rec.request_stop_playback());
+        assert!(stop.load(Ordering::Relaxed));
+        assert!(rec.playback_state == PlaybackState::Playing); // until the thread is done
+
+        rec.finish_playback();
+        assert!(rec.playback_state == PlaybackState::Idle);
+        // the next playback starts with a clean flag
+        assert!(!rec.begin_playback().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_review_timeout_action() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.start_recording();
+        rec.stop_recording();
+        let id = rec.review_id;
+        assert_eq!(rec.review_timeout_action(id), None); // off by default
+
+        rec.settings.review_timeout = Some(5.0);
+        rec.settings.review_default = ReviewAction::Reject;
+        assert_eq!(rec.review_timeout_action(id), Some(ReviewAction::Reject));
+
+        // a retried take is a new review, the old timer must not act on it
+        rec.retry_current_take();
+        rec.stop_recording();
+        assert_eq!(rec.review_timeout_action(id), None);
+
+        // already decided
+        let id = rec.review_id;
+        rec.approve();
+        assert_eq!(rec.review_timeout_action(id), None);
+    }
+
+    // a recorder whose snapshots go to a directory of its own
+    fn recovering(name: &str) -> (RecorderState, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("pos_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut rec = RecorderState::new(8000, 1);
+        rec.recovery_dir = Some(dir.clone());
+        (rec, dir)
+    }
+
+    #[test]
+    fn test_recovery_snapshot_round_trip() {
+        let (mut rec, dir) = recovering("recovery");
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.25; 8]);
+        rec.stop_recording();
+        dispatch_command(&mut rec, Command::Approve); // writes the snapshot
+        let path = rec.recovery_file().unwrap();
+        assert!(path.exists());
+
+        let mut next = RecorderState::new(8000, 1);
+        assert_eq!(next.restore_recovery(&path).unwrap(), 1);
+        assert_eq!(next.project.segments[0].samples, vec![0.25; 8]);
+        assert!(next.save_path.is_none());
+
+        rec.clear_recovery();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recovery_snapshot_follows_every_edit() {
+        let (mut rec, dir) = recovering("recovery_edits");
+        let path = rec.recovery_file().unwrap();
+        rec.project.segments.push(Segment::new(vec![0.1; 4]));
+        rec.project.segments.push(Segment::new(vec![0.2; 4]));
+        rec.save_state();
+        dispatch_command(&mut rec, Command::DeleteSegment(0));
+        assert_eq!(project_io::load_project(&path).unwrap().segments.len(), 1);
+
+        // nothing left to lose, a stale snapshot would bring the takes back
+        dispatch_command(&mut rec, Command::ClearProject);
+        assert!(!path.exists());
+        rec.undo();
+        assert_eq!(project_io::load_project(&path).unwrap().segments.len(), 1);
+
+        // renamed along with the save path
+        let saved = dir.join("chapter.bin").to_string_lossy().to_string();
+        rec.save_project_as(saved).unwrap();
+        assert!(!path.exists());
+        assert!(rec.recovery_file().unwrap().exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_interrupt_snapshot_keeps_the_take() {
+        let (mut rec, dir) = recovering("interrupt");
+        rec.recovery_dir = None;
+        assert_eq!(rec.write_recovery_with_take(), 0); // no recovery dir
+        rec.recovery_dir = Some(dir.clone());
+        assert_eq!(rec.write_recovery_with_take(), 0); // nothing recorded
+        rec.project.segments.push(Segment::new(vec![0.1; 4]));
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.5; 6]);
+        assert_eq!(rec.write_recovery_with_take(), 2);
+
+        let mut next = RecorderState::new(8000, 1);
+        assert_eq!(next.restore_recovery(&rec.recovery_file().unwrap()).unwrap(), 2);
+        assert_eq!(next.project.segments[1].samples, vec![0.5; 6]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_append_project() {
+        let path = std::env::temp_dir().join("pos_test_append_project.bin");
+        let path = path.to_string_lossy().to_string();
+
+        let mut other = RecorderState::new(48000, 1);
+        other.project.segments.push(Segment::new(vec![0.1; 4]));
+        other.project.segments.push(Segment::new(vec![0.2; 4]));
+        other.set_save_path(path.clone());
+        other.save_to_disk();
+
+        let mut rec = RecorderState::new(48000, 1);
+        rec.project.segments.push(Segment::new(vec![0.3; 4]));
+        assert_eq!(rec.append_project(&path).unwrap(), 2);
+        assert_eq!(rec.get_segment_count(), 3);
+        assert_eq!(rec.project.segments[2].samples, vec![0.2; 4]);
+
+        // a 24kHz project is stretched to the 48kHz timeline
+        let mut half = RecorderState::new(24000, 1);
+        half.project.segments.push(Segment::new(vec![0.5; 100]));
+        half.set_save_path(path.clone());
+        half.save_to_disk();
+        rec.append_project(&path).unwrap();
+        assert_eq!(rec.project.segments[3].samples.len(), 200);
+
+        let _ = std::fs::remove_file(&path);
+        assert!(rec.append_project(&path).is_err());
+    }
+
+    #[test]
+    fn test_reached_max_length() {
+        let mut rec = RecorderState::new(1000, 1);
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.1; 5000]);
+        assert!(!rec.reached_max_length()); // unlimited by default
+
+        rec.settings.max_record_seconds = Some(6.0);
+        assert!(!rec.reached_max_length());
+        rec.current.as_mut().unwrap().samples.extend([0.1; 1000]);
+        assert!(rec.reached_max_length());
+    }
+
+    #[test]
+    fn test_duplicate_segment() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.project.segments.push(Segment::new(vec![0.1, 0.2, 0.3]));
+        rec.project.segments.push(Segment::new(vec![0.9]));
+
+        assert!(rec.duplicate_segment(0));
+        assert_eq!(rec.get_segment_count(), 3);
+        assert_eq!(rec.project.segments[0].samples, rec.project.segments[1].samples);
+        assert_eq!(rec.project.segments[2].samples, vec![0.9]); // the rest moves along
+        assert!(!rec.duplicate_segment(3));
+    }
+
+    #[test]
+    fn test_reverse_segment() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.project.segments.push(Segment::new(vec![1.0, 2.0, 3.0]));
+        assert!(rec.reverse_segment(0));
+        assert_eq!(rec.project.segments[0].samples, vec![3.0, 2.0, 1.0]);
+        assert!(!rec.reverse_segment(1));
+
+        // stereo frames stay L/R
+        rec.project.channels = 2;
+        rec.project.segments[0].samples = vec![1.0, -1.0, 2.0, -2.0];
+        rec.reverse_segment(0);
+        assert_eq!(rec.project.segments[0].samples, vec![2.0, -2.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_remove_dc_offset() {
+        let mut rec = RecorderState::new(8000, 1);
+        let biased: Vec<f32> = (0..800).map(|i| 0.2 + 0.3 * (i as f32 * 0.05).sin()).collect();
+        rec.project.segments.push(Segment::new(biased));
+        assert!(rec.remove_dc_offset(0));
+        let samples = &rec.project.segments[0].samples;
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 1e-4, "mean {}", mean);
+        assert!(!rec.remove_dc_offset(1));
+
+        // stereo: each side has its own bias
+        rec.project.channels = 2;
+        rec.project.segments[0].samples = [0.5, -0.1].repeat(50);
+        assert_eq!(rec.remove_dc_all(), 1);
+        assert!(rec.project.segments[0].samples.iter().all(|s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_copy_and_paste() {
+        let mut rec = RecorderState::new(10, 1);
+        rec.project.segments.push(Segment::new((0..20).map(|i| i as f32).collect()));
+        rec.project.segments.push(Segment::new(vec![0.0; 10]));
+
+        // 0.5s..1.0s at 10Hz is samples 5..10
+        assert_eq!(rec.copy_range(0, 0.5, 1.0), Some(vec![5.0, 6.0, 7.0, 8.0, 9.0]));
+        assert!(rec.copy_range(0, 1.5, 2.5).is_none()); // runs past the end
+        assert!(rec.copy_range(0, 1.0, 0.5).is_none());
+        assert_eq!(rec.clipboard.as_deref(), Some(&[5.0, 6.0, 7.0, 8.0, 9.0][..])); // failed copies keep it
+
+        assert!(rec.paste_clipboard(1, 0.2));
+        assert_eq!(rec.project.segments[1].samples, vec![0.0, 0.0, 5.0, 6.0, 7.0, 8.0, 9.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(!rec.paste_clipboard(1, 2.0)); // past the end
+
+        // into a segment at x2 gain, it still sounds as copied
+        rec.project.segments[1].gain = 2.0;
+        assert!(rec.paste_samples(1, 0.0, &[1.0]));
+        assert_eq!(rec.project.segments[1].samples[0], 0.5);
+
+        // stereo keeps whole frames
+        rec.project.channels = 2;
+        rec.project.segments[0].samples = vec![1.0, -1.0, 2.0, -2.0, 3.0, -3.0];
+        assert_eq!(rec.copy_range(0, 0.1, 0.2), Some(vec![2.0, -2.0]));
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut rec = RecorderState::new(8000, 1);
+        assert!(!rec.pause_recording()); // nothing to pause
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.5; 10]);
+        assert!(rec.pause_recording());
+        assert!(rec.state == AppState::Recording); // the take is still open
+        assert!(rec.resume_recording());
+        assert!(!rec.resume_recording());
+
+        // stopping while paused reviews the take as usual, and approving commits it
+        rec.pause_recording();
+        rec.stop_recording();
+        assert!(!rec.paused);
+        rec.approve();
+        assert_eq!(rec.project.segments[0].samples, vec![0.5; 10]);
+    }
+
+    #[test]
+    fn test_takes_are_timestamped() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.start_recording();
+        let first = rec.current.as_ref().unwrap().recorded_at.unwrap();
+        rec.current.as_mut().unwrap().samples.push(0.5);
+        rec.stop_recording();
+        rec.approve();
+
+        // a retake gets its own, newer stamp
+        std::thread::sleep(Duration::from_millis(2));
+        rec.retry_segment(0);
+        assert!(rec.current.as_ref().unwrap().recorded_at.unwrap() > first);
+        assert!(Segment::new(vec![0.0]).recorded_at.is_none());
+    }
+
+    #[test]
+    fn test_normalize_project_shares_one_factor() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.project.segments.push(Segment::new(vec![0.5, -0.25]));
+        rec.project.segments.push(Segment::new(vec![0.1, -0.05]));
+        assert!(rec.normalize_project(1.0));
+
+        // x2 on both: the loud one hits 1.0, the quiet one stays 5x quieter
+        assert_eq!(rec.project.segments[0].samples, vec![1.0, -0.5]);
+        assert_eq!(rec.project.segments[1].samples, vec![0.2, -0.1]);
+
+        let mut silent = RecorderState::new(8000, 1);
+        silent.project.segments.push(Segment::new(vec![0.0; 4]));
+        assert!(!silent.normalize_project(1.0));
+    }
+
+    #[test]
+    fn test_auto_split_at_long_pauses() {
+        let mut rec = RecorderState::new(1000, 1); // 1 sample = 1ms
+        let word = |ms: usize| vec![0.5_f32; ms];
+        let gap = |ms: usize| vec![0.0_f32; ms];
+        // lead-in, word, long pause, word, short pause, word, long pause, word, tail
+        let take = [gap(300), word(200), gap(800), word(200), gap(100), word(200), gap(600), word(200), gap(400)].concat();
+        let mut seg = Segment::new(take);
+        seg.name = Some("para".into());
+        rec.project.segments.push(seg);
+        rec.project.segments.push(Segment::new(word(10)));
+
+        assert_eq!(rec.auto_split(0, 0.01, 0.5), 3);
+        let segs = &rec.project.segments;
+        assert_eq!(segs.len(), 4);
+        // 150ms of each long pause stays on both sides, the short one is kept whole
+        assert_eq!(segs[0].samples.len(), 300 + 200 + 150);
+        assert_eq!(segs[1].samples.len(), 150 + 200 + 100 + 200 + 150);
+        assert_eq!(segs[2].samples.len(), 150 + 200 + 400);
+        assert_eq!(segs[1].name.as_deref(), Some("para.2"));
+        assert_eq!(segs[3].samples.len(), 10); // neighbour untouched
+
+        assert_eq!(rec.auto_split(3, 0.01, 0.5), 1); // nothing to split
+        assert_eq!(rec.auto_split(9, 0.01, 0.5), 0);
+    }
+
+    #[test]
+    fn test_time_stretch_doubles_length() {
+        let mut rec = RecorderState::new(8000, 1);
+        let tone: Vec<f32> = (0..8000).map(|i| (i as f32 * 0.15).sin() * 0.5).collect();
+        rec.project.segments.push(Segment::new(tone));
+        assert!(rec.time_stretch(0, 2.0));
+        assert_eq!(rec.project.segments[0].samples.len(), 16000);
+        assert!(!rec.time_stretch(0, 10.0));
+        assert!(!rec.time_stretch(4, 2.0));
+
+        rec.project.segments.push(Segment::new(vec![0.5; 100])); // 12.5ms, too short
+        assert!(!rec.time_stretch(1, 2.0));
+        assert!(rec.project.segments[1].history.is_empty());
+    }
+
+    #[test]
+    fn test_pitch_shift_keeps_length() {
+        let mut rec = RecorderState::new(8000, 2);
+        let tone: Vec<f32> = (0..8000).flat_map(|i| { let s = (i as f32 * 0.1).sin() * 0.5; [s, s] }).collect();
+        rec.project.segments.push(Segment::new(tone));
+        dispatch_command(&mut rec, Command::PitchShift(0, 7.0));
+        assert_eq!(rec.project.segments[0].samples.len(), 16000);
+        assert!(rec.project.segments[0].history.last().unwrap().contains("+7"));
+        assert!(!rec.pitch_shift(0, 30.0));
+        assert!(!rec.pitch_shift(2, 1.0));
+    }
+
+    #[test]
+    fn test_clear_project() {
+        let mut rec = RecorderState::new(16000, 2);
+        rec.project.segments.push(Segment::new(vec![0.1; 4]));
+        rec.project.segments.push(Segment::new(vec![0.2; 4]));
+        rec.save_state();
+        rec.start_recording();
+        dispatch_command(&mut rec, Command::ClearProject); // refused mid-take
+        assert_eq!(rec.get_segment_count(), 2);
+        dispatch_command(&mut rec, Command::Reject);
+        dispatch_command(&mut rec, Command::ClearProject);
+        assert_eq!(rec.get_segment_count(), 0);
+        assert_eq!((rec.project.sample_rate, rec.project.channels), (16000, 2));
+
+        // recording still works afterwards
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.3; 4]);
+        rec.stop_recording();
+        dispatch_command(&mut rec, Command::Approve);
+        assert_eq!(rec.get_segment_count(), 1);
+
+        assert!(rec.undo() && rec.undo()); // back past the clear
+        assert_eq!(rec.get_segment_count(), 2);
+    }
+
+    #[test]
+    fn test_move_waits_for_a_pending_retake() {
+        let mut rec = RecorderState::new(10, 1);
+        for v in [0.1, 0.2, 0.3] { rec.project.segments.push(Segment::new(vec![v; 4])); }
+        rec.save_state();
+        dispatch_command(&mut rec, Command::RetrySegment(2));
+        rec.current.as_mut().unwrap().samples.extend([0.9; 4]);
+        rec.stop_recording();
+
+        // moving would make the retake overwrite the wrong slot
+        assert!(!rec.move_segment(2, 0));
+        dispatch_command(&mut rec, Command::MoveSegment(2, 0));
+        dispatch_command(&mut rec, Command::Approve);
+        let firsts: Vec<f32> = rec.project.segments.iter().map(|s| s.samples[0]).collect();
+        assert_eq!(firsts, vec![0.1, 0.2, 0.9]);
+        assert!(rec.move_segment(2, 0)); // fine again once idle
+    }
+
+    #[test]
+    fn test_refused_edit_keeps_the_rejected_take() {
+        let mut rec = RecorderState::new(10, 1);
+        rec.project.segments = vec![Segment::new(vec![0.1; 4]), Segment::new(vec![0.2; 4])];
+        rec.save_state();
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.5; 4]);
+        rec.stop_recording();
+        dispatch_command(&mut rec, Command::Reject);
+
+        let steps = rec.history.len();
+        dispatch_command(&mut rec, Command::SetGain(8, 1.5)); // no segment 9
+        assert_eq!(rec.history.len(), steps); // no empty undo step
+        dispatch_command(&mut rec, Command::Undo);
+        assert!(rec.state == AppState::Reviewing);
+        assert_eq!(rec.current.as_ref().unwrap().samples, vec![0.5; 4]);
+    }
+
+    #[test]
+    fn test_structural_edits_wait_for_a_pending_retake() {
+        let three = || {
+            let mut rec = RecorderState::new(10, 1);
+            for v in [0.1, 0.2, 0.3] { rec.project.segments.push(Segment::new(vec![v; 4])); }
+            rec.save_state();
+            dispatch_command(&mut rec, Command::RetrySegment(2));
+            rec.current.as_mut().unwrap().samples.extend([0.9; 4]);
+            rec.stop_recording();
+            rec
+        };
+        let firsts = |rec: &RecorderState| rec.project.segments.iter().map(|s| s.samples[0]).collect::<Vec<_>>();
+
+        // merging would leave editing_index past the end, approve used to panic
+        let mut rec = three();
+        dispatch_command(&mut rec, Command::MergeSegments(0, 1));
+        dispatch_command(&mut rec, Command::Approve);
+        assert_eq!(firsts(&rec), vec![0.1, 0.2, 0.9]);
+
+        let mut rec = three();
+        for cmd in [Command::SplitSegment(0, 0.2), Command::DuplicateSegment(0), Command::DeleteSegment(0),
+                    Command::InsertSilence(0, 1.0), Command::ClearProject] {
+            dispatch_command(&mut rec, cmd);
+        }
+        assert_eq!(rec.auto_split(0, 0.01, 0.1), 0);
+        // a rendered copy would shift the retake's slot onto itself
+        dispatch_command(&mut rec, Command::RenderEffect(0, EffectSpec::Gain(2.0), 0));
+        assert!(!rec.render_with_effect(0, EffectSpec::Gain(2.0), 0));
+        assert_eq!(rec.get_segment_count(), 3);
+        dispatch_command(&mut rec, Command::Approve);
+        assert_eq!(firsts(&rec), vec![0.1, 0.2, 0.9]);
+        assert!(rec.merge_segments(0, 1)); // fine again once idle
+    }
+
+    #[test]
+    fn test_insert_at_and_reject_are_one_undo_step_each() {
+        let mut rec = RecorderState::new(10, 1);
+        rec.project.segments = vec![Segment::new(vec![0.1; 20]), Segment::new(vec![0.2; 20])];
+        rec.save_state();
+
+        // insertat splits segment 1 and the take lands between the halves
+        dispatch_command(&mut rec, Command::InsertAt(1.0));
+        rec.current.as_mut().unwrap().samples.extend([0.9; 4]);
+        rec.stop_recording();
+        dispatch_command(&mut rec, Command::Approve);
+        assert_eq!(rec.get_segment_count(), 4);
+        dispatch_command(&mut rec, Command::Undo);
+        assert_eq!(rec.get_segment_count(), 2); // split and take undone together
+        assert!(rec.project.segments[0].samples.len() == 20);
+
+        // a rejected retake comes back under review, still aimed at its slot
+        dispatch_command(&mut rec, Command::RetrySegment(0));
+        rec.current.as_mut().unwrap().samples.extend([0.7; 4]);
+        rec.stop_recording();
+        dispatch_command(&mut rec, Command::Reject);
+        assert!(rec.state == AppState::Idle);
+        dispatch_command(&mut rec, Command::Undo);
+        assert!(rec.state == AppState::Reviewing);
+        assert_eq!(rec.project.editing_index, Some(0));
+        assert_eq!(rec.get_segment_count(), 2);
+
+        dispatch_command(&mut rec, Command::Redo); // rejected again
+        assert!(rec.state == AppState::Idle && rec.current.is_none());
+        dispatch_command(&mut rec, Command::Undo);
+        dispatch_command(&mut rec, Command::Approve);
+        assert_eq!(rec.project.segments[0].samples, vec![0.7; 4]);
+        assert_eq!(rec.get_segment_count(), 2);
+    }
+
+    #[test]
+    fn test_take_seconds() {
+        let mut rec = RecorderState::new(100, 2);
+        assert_eq!(rec.take_seconds(), 0.0);
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.0; 150]); // 75 stereo frames
+        assert_eq!(rec.take_seconds(), 0.75);
+    }
+
+    #[test]
+    fn test_restore_rejected_take() {
+        let mut rec = RecorderState::new(8000, 1);
+        assert!(!rec.restore_rejected()); // nothing rejected yet
+        rec.start_recording();
+        rec.current.as_mut().unwrap().samples.extend([0.3, 0.6]);
+        rec.stop_recording();
+        rec.reject();
+        assert!(rec.current.is_none());
+
+        assert!(rec.restore_rejected());
+        assert!(rec.state == AppState::Reviewing);
+        assert_eq!(rec.current.as_ref().unwrap().samples, vec![0.3, 0.6]);
+        assert!(!rec.restore_rejected()); // only kept once
+        rec.approve();
+        assert_eq!(rec.project.segments[0].samples, vec![0.3, 0.6]);
+    }
+
+    #[test]
+    fn test_insert_silence() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.project.segments.push(Segment::new(vec![0.5; 10]));
+        rec.project.segments.push(Segment::new(vec![0.5; 10]));
+
+        assert!(rec.insert_silence(0, 0.5));
+        assert_eq!(rec.get_segment_count(), 3);
+        assert_eq!(rec.project.segments[1].samples, vec![0.0; 4000]); // 0.5 * 8000
+        assert!(!rec.insert_silence(3, 0.5));
+        assert!(!rec.insert_silence(0, 0.0));
+
+        rec.project.channels = 2; // stereo needs both samples of every frame
+        assert!(rec.insert_silence(2, 0.25));
+        assert_eq!(rec.project.segments[3].samples.len(), 4000);
+    }
+
+    #[test]
+    fn test_convert_sample_rate() {
+        let mut rec = RecorderState::new(44100, 2);
+        rec.project.segments.push(Segment::new([0.25, -0.25].repeat(441))); // 10ms stereo
+        rec.save_state();
+
+        assert_eq!(rec.convert_sample_rate(48000), 1);
+        assert_eq!(rec.project.sample_rate, 48000);
+        assert_eq!(rec.project.segments[0].samples.len(), 960); // still 10ms
+        assert!(rec.project.segments[0].samples.chunks(2).all(|f| f == [0.25, -0.25]));
+        assert!(!rec.undo()); // nothing to step back into at the old rate
+        assert_eq!(rec.convert_sample_rate(48000), 0);
+    }
+
+    #[test]
+    fn test_reconcile_channels() {
+        use ChannelPolicy::*;
+        assert_eq!(reconcile_channels(1, 1, Refuse), Ok(ChannelPlan::Passthrough));
+        assert_eq!(reconcile_channels(2, 1, Duplicate), Ok(ChannelPlan::Duplicate(2)));
+        assert!(reconcile_channels(2, 1, Refuse).is_err());
+        assert!(reconcile_channels(1, 2, Duplicate).is_err()); // can't duplicate down
+
+        // a stereo project opened on mono hardware follows the setting
+        let mut rec = RecorderState::new(48000, 1);
+        rec.project.channels = 2;
+        assert_eq!(rec.capture_plan(), Ok(ChannelPlan::Duplicate(2)));
+        rec.settings.channel_mismatch = Refuse;
+        assert!(rec.capture_plan().is_err());
+    }
+
+    #[test]
+    fn test_reverb_extends_segment() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.project.segments.push(Segment::new(vec![0.5; 480]));
+        assert!(rec.reverb(0, 0.5, 0.3));
+        let seg = &rec.project.segments[0];
+        assert!(seg.samples.len() > 480);
+        assert!(seg.samples[480..].iter().any(|s| s.abs() > 1e-4)); // tail isn't silent
+        assert!(!rec.reverb(3, 0.5, 0.3));
+    }
+
+    #[test]
+    fn test_delay_time_is_bounded() {
+        let mut rec = RecorderState::new(8000, 1);
+        rec.project.segments.push(Segment::new(vec![0.5; 80]));
+        assert!(!rec.delay(0, f32::INFINITY, 0.5, 0.5));
+        assert!(!rec.delay(0, dsp::MAX_DELAY_MS * 2.0, 0.5, 0.5)); // first echo would be past the tail
+        assert_eq!(rec.project.segments[0].samples.len(), 80);
+        assert!(rec.delay(0, dsp::MAX_DELAY_MS, 0.5, 0.5));
+        assert!(rec.project.segments[0].samples.len() > 80 + 2 * 8000); // the echo made it in
+    }
+
+    #[test]
+    fn test_render_with_effect_keeps_original() {
+        let mut rec = RecorderState::new(48000, 1);
+        rec.project.segments.push(Segment::new(vec![0.1, -0.2, 0.3]));
+        rec.project.segments.push(Segment::new(vec![0.9]));
+
+        assert!(rec.render_with_effect(0, EffectSpec::Gain(2.0), 1));
+        assert_eq!(rec.get_segment_count(), 3);
+        assert_eq!(rec.project.segments[0].samples, vec![0.1, -0.2, 0.3]);
+        assert!(rec.project.segments[0].history.is_empty());
+        assert_eq!(rec.project.segments[2].samples, vec![0.2, -0.4, 0.6]);
+
+        assert!(!rec.render_with_effect(7, EffectSpec::Gain(2.0), 0));
+    }
+
+    #[test]
+    fn test_waveform_buckets() {
+        let seg = Segment::new(vec![0.1, -0.5, 0.2, 0.0, -1.0, 0.3]);
+        assert_eq!(seg.waveform(3), vec![0.5, 0.2, 1.0]);
+        // more buckets than samples still yields the requested width
+        assert_eq!(seg.waveform(12).len(), 12);
     }
 }
@@ -0,0 +1,66 @@
+// Text-mode renderings of segment audio for the CLI.
+// Everything here is pure (peaks in, String out) so it can be
+// unit tested without a terminal or audio device.
+
+// eight heights of the unicode lower-block characters, quietest first
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// map each peak (0.0..=1.0, louder is clamped) to one block character
+// a fully silent bucket is drawn as a space so gaps stand out
+pub fn sparkline(peaks: &[f32]) -> String {
+    peaks.iter().map(|&p| {
+        if p <= 0.0 { return ' '; }
+        let level = (p.min(1.0) * (BLOCKS.len() - 1) as f32).round() as usize;
+        BLOCKS[level]
+    }).collect()
+}
+
+// taller version of sparkline for `waveform <n>`: `height` rows, top row
+// first, each column a bar rising to its peak. the top cell of a bar uses a
+// partial block so levels between rows still show
+pub fn waveform_rows(peaks: &[f32], height: usize) -> Vec<String> {
+    (0..height).rev().map(|row| {
+        peaks.iter().map(|&p| {
+            let fill = p.clamp(0.0, 1.0) * height as f32 - row as f32; // how much of this cell is lit
+            if fill >= 1.0 { '█' }
+            else if fill <= 0.0 { ' ' }
+            else { BLOCKS[((fill * BLOCKS.len() as f32) as usize).min(BLOCKS.len() - 1)] }
+        }).collect()
+    }).collect()
+}
+
+// horizontal level bar for the status line, `width` cells of which the
+// lit portion tracks `level` (0.0..=1.0). clipping lights the whole bar
+pub fn meter_bar(level: f32, width: usize) -> String {
+    let lit = ((level.clamp(0.0, 1.0) * width as f32).round() as usize).min(width);
+    format!("{}{}", "▮".repeat(lit), "▯".repeat(width - lit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_maps_peaks_to_blocks() {
+        assert_eq!(sparkline(&[0.0, 0.01, 0.5, 1.0, 2.5]), " ▁▅██");
+        assert_eq!(sparkline(&[]), "");
+        // width always matches the number of buckets
+        assert_eq!(sparkline(&[0.3; 16]).chars().count(), 16);
+    }
+
+    #[test]
+    fn test_waveform_rows() {
+        let rows = waveform_rows(&[0.0, 0.25, 0.5, 1.0, 3.0], 2);
+        assert_eq!(rows, vec!["   ██", " ▅███"]);
+        // a level between rows lights part of a cell
+        assert_eq!(waveform_rows(&[0.3], 1), vec!["▃"]);
+        assert!(waveform_rows(&[0.5; 60], 8).iter().all(|r| r.chars().count() == 60));
+    }
+
+    #[test]
+    fn test_meter_bar() {
+        assert_eq!(meter_bar(0.0, 4), "▯▯▯▯");
+        assert_eq!(meter_bar(0.5, 4), "▮▮▯▯");
+        assert_eq!(meter_bar(1.7, 4), "▮▮▮▮");
+    }
+}